@@ -0,0 +1,16 @@
+-- Groups RID types into the three fact blocks shown on the commerce page
+-- (patents, registration certificates, trademarks). Types left NULL are not counted.
+alter table Type
+    add column category enum('patent', 'registration', 'trademark') null;
+
+update Type set category = 'patent'
+    where lower(name) like '%изобретен%'
+       or lower(name) like '%полезн%модел%'
+       or lower(name) like '%промышлен%образ%';
+
+update Type set category = 'registration'
+    where lower(name) like '%программ%'
+       or lower(name) like '%баз%данных%';
+
+update Type set category = 'trademark'
+    where lower(name) like '%товарн%знак%';
@@ -0,0 +1,159 @@
+-- Type now carries the texts of the "ВИДЫ" cards on the definitions page.
+alter table Type
+    add column description text null,
+    add column legal_basis varchar(255) null,
+    add column protection_term varchar(255) null,
+    add column icon varchar(64) null;
+
+insert into Type (name) select 'Произведения науки, литературы и искусства' from dual
+    where not exists (select 1 from Type where lower(name) like '%произведен%наук%');
+update Type set
+    description = 'оригинальные результаты творческой деятельности в области науки, литературы или искусства, выраженные в объективной форме (например, книги, картины, музыкальные произведения)',
+    legal_basis = 'ст. 1259 ГК РФ',
+    protection_term = 'в течение жизни автора и 70 лет после его смерти',
+    icon = 'sal.svg'
+    where lower(name) like '%произведен%наук%';
+
+insert into Type (name) select 'Программы для ЭВМ' from dual
+    where not exists (select 1 from Type where lower(name) like '%программ%');
+update Type set
+    description = 'наборы инструкций и данных, предназначенные для функционирования компьютеров с целью выполнения определенных задач',
+    legal_basis = 'ст. 1261 ГК РФ',
+    protection_term = 'в течение жизни автора и 70 лет после его смерти',
+    icon = 'pro.svg'
+    where lower(name) like '%программ%';
+
+insert into Type (name) select 'Базы данных' from dual
+    where not exists (select 1 from Type where lower(name) like '%баз%данных%');
+update Type set
+    description = 'систематизированные коллекции данных, организованные таким образом, чтобы облегчить их поиск и использование',
+    legal_basis = 'ст. 1260, 1333 ГК РФ',
+    protection_term = 'в течение жизни автора и 70 лет после его смерти; право изготовителя — 15 лет',
+    icon = 'db.svg'
+    where lower(name) like '%баз%данных%';
+
+insert into Type (name) select 'Исполнения' from dual
+    where not exists (select 1 from Type where lower(name) like 'исполнени%');
+update Type set
+    description = 'результаты творческой деятельности исполнителей (актеров, музыкантов, танцоров), воплощенные в процессе публичного представления произведений',
+    legal_basis = 'ст. 1313 ГК РФ',
+    protection_term = 'в течение жизни исполнителя, но не менее 50 лет',
+    icon = 'exe.svg'
+    where lower(name) like 'исполнени%';
+
+insert into Type (name) select 'Фонограммы' from dual
+    where not exists (select 1 from Type where lower(name) like 'фонограмм%');
+update Type set
+    description = 'записи звуков, включая музыку, речь или другие звуки, зафиксированные на материальном носителе',
+    legal_basis = 'ст. 1322 ГК РФ',
+    protection_term = '70 лет после обнародования',
+    icon = 'pho.svg'
+    where lower(name) like 'фонограмм%';
+
+insert into Type (name) select 'Сообщение в эфир или по кабелю радио- или телепередач' from dual
+    where not exists (select 1 from Type where lower(name) like '%эфир%');
+update Type set
+    description = 'передача программ радио- или телевещательных организаций через эфир или кабельную сеть',
+    legal_basis = 'ст. 1329 ГК РФ',
+    protection_term = '50 лет после первого сообщения',
+    icon = 'mes.svg'
+    where lower(name) like '%эфир%';
+
+insert into Type (name) select 'Изобретения' from dual
+    where not exists (select 1 from Type where lower(name) like '%изобретен%');
+update Type set
+    description = 'технические решения, обладающие новизной и промышленной применимостью, направленные на улучшение существующих технологий или создание новых',
+    legal_basis = 'ст. 1350 ГК РФ',
+    protection_term = '20 лет с даты подачи заявки',
+    icon = 'inv.svg'
+    where lower(name) like '%изобретен%';
+
+insert into Type (name) select 'Полезные модели' from dual
+    where not exists (select 1 from Type where lower(name) like '%полезн%модел%');
+update Type set
+    description = 'новые конструктивные решения устройств, которые обладают промышленной применимостью',
+    legal_basis = 'ст. 1351 ГК РФ',
+    protection_term = '10 лет с даты подачи заявки',
+    icon = 'mod.svg'
+    where lower(name) like '%полезн%модел%';
+
+insert into Type (name) select 'Промышленные образцы' from dual
+    where not exists (select 1 from Type where lower(name) like '%промышлен%образ%');
+update Type set
+    description = 'художественно-конструкторские решения изделий промышленного или кустарно-ремесленного производства, определяющие их внешний вид',
+    legal_basis = 'ст. 1352 ГК РФ',
+    protection_term = '5 лет с продлением до 25 лет',
+    icon = 'ref.svg'
+    where lower(name) like '%промышлен%образ%';
+
+insert into Type (name) select 'Селекционные достижения' from dual
+    where not exists (select 1 from Type where lower(name) like '%селекцион%');
+update Type set
+    description = 'выведенные человеком сорта растений или породы животных, обладающие новыми свойствами',
+    legal_basis = 'ст. 1412 ГК РФ',
+    protection_term = '30 лет с даты регистрации',
+    icon = 'sel.svg'
+    where lower(name) like '%селекцион%';
+
+insert into Type (name) select 'Топологии интегральных микросхем' from dual
+    where not exists (select 1 from Type where lower(name) like '%топологи%');
+update Type set
+    description = 'геометрическое расположение элементов интегральной схемы, предназначенное для ее изготовления и эксплуатации',
+    legal_basis = 'ст. 1448 ГК РФ',
+    protection_term = '10 лет',
+    icon = 'top.svg'
+    where lower(name) like '%топологи%';
+
+insert into Type (name) select 'Секреты производства (ноу-хау)' from dual
+    where not exists (select 1 from Type where lower(name) like '%ноу-хау%');
+update Type set
+    description = 'конфиденциальная информация технического, организационного или коммерческого характера, используемая в производстве и представляющая коммерческую ценность.',
+    legal_basis = 'ст. 1465 ГК РФ',
+    protection_term = 'пока сохраняется конфиденциальность сведений',
+    icon = 'k-h.svg'
+    where lower(name) like '%ноу-хау%';
+
+insert into Type (name) select 'Фирменные наименования' from dual
+    where not exists (select 1 from Type where lower(name) like '%фирменн%');
+update Type set
+    description = 'название юридического лица, которое используется для индивидуализации предприятия',
+    legal_basis = 'ст. 1473 ГК РФ',
+    protection_term = 'до прекращения юридического лица',
+    icon = 'nam.svg'
+    where lower(name) like '%фирменн%';
+
+insert into Type (name) select 'Товарные знаки и знаки обслуживания' from dual
+    where not exists (select 1 from Type where lower(name) like '%товарн%знак%');
+update Type set
+    description = 'обозначения, служащие для индивидуализации товаров или услуг одного производителя от аналогичных товаров или услуг других производителей',
+    legal_basis = 'ст. 1477 ГК РФ',
+    protection_term = '10 лет с продлением каждые 10 лет',
+    icon = 'lab.svg'
+    where lower(name) like '%товарн%знак%';
+
+insert into Type (name) select 'Географические указания' from dual
+    where not exists (select 1 from Type where lower(name) like '%географ%');
+update Type set
+    description = 'названия географического объекта, указывающие на происхождение товара, свойства которого связаны с данным объектом',
+    legal_basis = 'ст. 1516 ГК РФ',
+    protection_term = 'бессрочно; свидетельство — 10 лет с продлением',
+    icon = 'loc.svg'
+    where lower(name) like '%географ%';
+
+insert into Type (name) select 'Наименования мест происхождения товаров' from dual
+    where not exists (select 1 from Type where lower(name) like '%мест%происхожден%');
+update Type set
+    description = 'обозначение места, где производится товар, уникальные характеристики которого определяются природными условиями данного региона',
+    legal_basis = 'ст. 1516 ГК РФ',
+    protection_term = 'бессрочно; свидетельство — 10 лет с продлением',
+    icon = 'or.svg'
+    where lower(name) like '%мест%происхожден%';
+
+insert into Type (name) select 'Коммерческие обозначения' from dual
+    where not exists (select 1 from Type where lower(name) like '%коммерческ%обозначен%');
+update Type set
+    description = 'обозначения, используемые для индивидуализации торговых, промышленных и иных предприятий',
+    legal_basis = 'ст. 1538 ГК РФ',
+    protection_term = 'до неиспользования в течение года',
+    icon = 'com.svg'
+    where lower(name) like '%коммерческ%обозначен%';
@@ -0,0 +1,25 @@
+-- Lifecycle of a RID: every change of its legal status with the date it happened.
+create table StatusHistory
+(
+    id int not null auto_increment primary key,
+    idRID int not null,
+    status enum('filed', 'published', 'granted', 'in_force', 'lapsed', 'expired') not null,
+    date date not null,
+    notes text null,
+    index (idRID, date),
+    foreign key (idRID) references RID(id) on delete cascade
+);
+
+-- The latest entry of every RID; later ids win on the same date.
+create view CurrentStatus as
+    select
+        StatusHistory.idRID as idRID,
+        StatusHistory.status as status,
+        StatusHistory.date as date
+    from StatusHistory
+    where not exists (
+        select 1 from StatusHistory as Later
+        where Later.idRID = StatusHistory.idRID
+          and (Later.date > StatusHistory.date
+               or (Later.date = StatusHistory.date and Later.id > StatusHistory.id))
+    );
@@ -0,0 +1,40 @@
+-- Register of licensing and commercialisation agreements.
+-- public = 1 allows the agreement to be shown as a badge on the RID cards.
+create table Agreement
+(
+    id int not null auto_increment primary key,
+    kind enum('licence_exclusive', 'licence_non_exclusive', 'assignment', 'spin_off') not null,
+    counterparty varchar(255) not null,
+    number varchar(64) null,
+    date_signed date not null,
+    date_start date null,
+    date_end date null,
+    territory varchar(255) null,
+    royalty_terms text null,
+    status enum('draft', 'active', 'terminated', 'expired') not null default 'draft',
+    public tinyint(1) not null default 0,
+    notes text null
+);
+
+-- An agreement may cover several RIDs.
+create table AGREEMENTxRID
+(
+    idAgreement int not null,
+    idRID int not null,
+    primary key (idAgreement, idRID),
+    foreign key (idAgreement) references Agreement(id) on delete cascade,
+    foreign key (idRID) references RID(id) on delete cascade
+);
+
+-- Money received under an agreement. In the revenue report a payment is split
+-- evenly between the RIDs the agreement covers.
+create table AgreementPayment
+(
+    id int not null auto_increment primary key,
+    idAgreement int not null,
+    date date not null,
+    amount decimal(14, 2) not null,
+    notes text null,
+    index (idAgreement, date),
+    foreign key (idAgreement) references Agreement(id) on delete cascade
+);
@@ -0,0 +1,7 @@
+-- Technology Readiness Level (1-9) and whether the RID is offered for
+-- licensing or partnership, with the terms shown to visitors.
+alter table RID
+    add column trl tinyint null,
+    add column available tinyint(1) not null default 0,
+    add column partnership_terms text null,
+    add constraint RID_trl_range check (trl between 1 and 9);
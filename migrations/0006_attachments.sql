@@ -0,0 +1,16 @@
+-- Files attached to a RID. The file itself is kept in the attachment store
+-- under attachments/{id}, its PNG preview under attachments/{id}.thumb.png.
+create table Attachment
+(
+    id bigint unsigned not null auto_increment primary key,
+    idRID int not null,
+    kind enum('patent', 'certificate', 'drawing', 'presentation', 'other') not null default 'other',
+    title varchar(255) not null,
+    file_name varchar(255) not null,
+    mime varchar(128) not null,
+    size bigint not null,
+    has_thumbnail tinyint(1) not null default 0,
+    uploaded_at datetime not null default current_timestamp,
+    index (idRID),
+    foreign key (idRID) references RID(id) on delete cascade
+);
@@ -0,0 +1,5 @@
+-- Optional English name and description of a RID for the /en/ pages; the
+-- Russian ones are shown where these are NULL or empty.
+alter table RID
+    add column name_en varchar(1000) null,
+    add column description_en text null;
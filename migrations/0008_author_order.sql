@@ -0,0 +1,4 @@
+-- Place of an author in the contribution order of a RID, starting with 1.
+-- Authors without one are listed after the numbered ones.
+alter table AUTHORxRID
+    add column position int null;
@@ -0,0 +1,100 @@
+-- Versions of every RID: a JSON snapshot of the record with its authors and
+-- agreements, taken by triggers after each change. The actor is @audit_actor
+-- when the session sets it, otherwise the database account; set it before
+-- editing by hand:  set @audit_actor = 'Иванов И. И.';
+create table RIDHistory
+(
+    id bigint unsigned not null auto_increment primary key,
+    idRID int not null,
+    changed_at datetime not null default current_timestamp,
+    actor varchar(255) not null,
+    action enum('insert', 'update', 'delete', 'rollback') not null,
+    snapshot json not null,
+    index (idRID, id)
+);
+
+-- Snapshot of one RID as stored in RIDHistory. The keys of the top level
+-- are RID columns; authors and agreements list the relation table rows.
+create function rid_snapshot(rid int) returns json
+reads sql data
+return (
+    select json_object(
+        'name', RID.name,
+        'name_en', RID.name_en,
+        'description', RID.description,
+        'description_en', RID.description_en,
+        'numPotent', RID.numPotent,
+        'idFaculty', RID.idFaculty,
+        'idType', RID.idType,
+        'idYear', RID.idYear,
+        'idSubjectArea', RID.idSubjectArea,
+        'link', RID.link,
+        'trl', RID.trl,
+        'available', RID.available,
+        'partnership_terms', RID.partnership_terms,
+        'authors', coalesce((
+            select json_arrayagg(json_object(
+                'id', AUTHORxRID.idAuthor,
+                'position', AUTHORxRID.position,
+                'name', concat_ws(' ', FIO.surname, FIO.name, FIO.lastname)
+            ))
+            from AUTHORxRID
+            left join ConnectionAuthorCountry on (ConnectionAuthorCountry.id = AUTHORxRID.idAuthor)
+            left join FIO on (FIO.id = ConnectionAuthorCountry.idFIO)
+            where AUTHORxRID.idRID = RID.id
+        ), json_array()),
+        'agreements', coalesce((
+            select json_arrayagg(AGREEMENTxRID.idAgreement)
+            from AGREEMENTxRID
+            where AGREEMENTxRID.idRID = RID.id
+        ), json_array())
+    )
+    from RID
+    where RID.id = rid
+);
+
+-- @audit_skip is set while a rollback rewrites the relation tables, which
+-- then records a single 'rollback' version itself.
+create trigger RID_history_insert after insert on RID for each row
+    insert into RIDHistory (idRID, actor, action, snapshot)
+    select NEW.id, coalesce(@audit_actor, current_user()), 'insert', rid_snapshot(NEW.id)
+    from dual where @audit_skip is null;
+
+create trigger RID_history_update after update on RID for each row
+    insert into RIDHistory (idRID, actor, action, snapshot)
+    select NEW.id, coalesce(@audit_actor, current_user()), 'update', rid_snapshot(NEW.id)
+    from dual where @audit_skip is null;
+
+create trigger RID_history_delete before delete on RID for each row
+    insert into RIDHistory (idRID, actor, action, snapshot)
+    select OLD.id, coalesce(@audit_actor, current_user()), 'delete', rid_snapshot(OLD.id)
+    from dual where @audit_skip is null;
+
+create trigger AUTHORxRID_history_insert after insert on AUTHORxRID for each row
+    insert into RIDHistory (idRID, actor, action, snapshot)
+    select NEW.idRID, coalesce(@audit_actor, current_user()), 'update', rid_snapshot(NEW.idRID)
+    from dual where @audit_skip is null;
+
+create trigger AUTHORxRID_history_update after update on AUTHORxRID for each row
+    insert into RIDHistory (idRID, actor, action, snapshot)
+    select NEW.idRID, coalesce(@audit_actor, current_user()), 'update', rid_snapshot(NEW.idRID)
+    from dual where @audit_skip is null;
+
+create trigger AUTHORxRID_history_delete after delete on AUTHORxRID for each row
+    insert into RIDHistory (idRID, actor, action, snapshot)
+    select OLD.idRID, coalesce(@audit_actor, current_user()), 'update', rid_snapshot(OLD.idRID)
+    from dual where @audit_skip is null and rid_snapshot(OLD.idRID) is not null;
+
+create trigger AGREEMENTxRID_history_insert after insert on AGREEMENTxRID for each row
+    insert into RIDHistory (idRID, actor, action, snapshot)
+    select NEW.idRID, coalesce(@audit_actor, current_user()), 'update', rid_snapshot(NEW.idRID)
+    from dual where @audit_skip is null;
+
+create trigger AGREEMENTxRID_history_delete after delete on AGREEMENTxRID for each row
+    insert into RIDHistory (idRID, actor, action, snapshot)
+    select OLD.idRID, coalesce(@audit_actor, current_user()), 'update', rid_snapshot(OLD.idRID)
+    from dual where @audit_skip is null and rid_snapshot(OLD.idRID) is not null;
+
+-- The state before the history starts.
+insert into RIDHistory (idRID, actor, action, snapshot)
+    select RID.id, current_user(), 'insert', rid_snapshot(RID.id) from RID;
@@ -0,0 +1,17 @@
+-- Likely duplicate RIDs found by the duplicate scan, idRID1 < idRID2.
+-- reasons lists the signals that matched; dismissed pairs are not reopened
+-- by later scans. Merged pairs are kept after one of the RIDs is deleted,
+-- so there are no foreign keys to RID.
+create table DuplicateCandidate
+(
+    id int not null auto_increment primary key,
+    idRID1 int not null,
+    idRID2 int not null,
+    score double not null,
+    reasons varchar(255) not null,
+    status enum('open', 'merged', 'dismissed') not null default 'open',
+    found_at datetime not null default current_timestamp,
+    resolved_at datetime null,
+    unique (idRID1, idRID2),
+    index (status, score)
+);
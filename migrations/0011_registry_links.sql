@@ -0,0 +1,23 @@
+-- Link to the official registry record of a RID of this type, used when
+-- RID.link is empty; {number} is replaced with RID.numPotent.
+alter table Type
+    add column registry_link varchar(512) null;
+
+update Type set registry_link = 'https://www1.fips.ru/registers-doc-view/fips_servlet?DB=RUPAT&DocNumber={number}&TypeFile=html'
+    where lower(name) like '%изобретен%';
+update Type set registry_link = 'https://www1.fips.ru/registers-doc-view/fips_servlet?DB=RUPM&DocNumber={number}&TypeFile=html'
+    where lower(name) like '%полезн%модел%';
+update Type set registry_link = 'https://www1.fips.ru/registers-doc-view/fips_servlet?DB=RUDE&DocNumber={number}&TypeFile=html'
+    where lower(name) like '%промышлен%образ%';
+update Type set registry_link = 'https://www1.fips.ru/registers-doc-view/fips_servlet?DB=EVM&DocNumber={number}&TypeFile=html'
+    where lower(name) like '%программ%';
+update Type set registry_link = 'https://www1.fips.ru/registers-doc-view/fips_servlet?DB=DB&DocNumber={number}&TypeFile=html'
+    where lower(name) like '%баз%данных%';
+update Type set registry_link = 'https://www1.fips.ru/registers-doc-view/fips_servlet?DB=TIMS&DocNumber={number}&TypeFile=html'
+    where lower(name) like '%топологи%';
+update Type set registry_link = 'https://www1.fips.ru/registers-doc-view/fips_servlet?DB=RUTM&DocNumber={number}&TypeFile=html'
+    where lower(name) like '%товарн%знак%';
+update Type set registry_link = 'https://www1.fips.ru/registers-doc-view/fips_servlet?DB=RUGP&DocNumber={number}&TypeFile=html'
+    where lower(name) like '%географ%';
+update Type set registry_link = 'https://www1.fips.ru/registers-doc-view/fips_servlet?DB=RUPMNP&DocNumber={number}&TypeFile=html'
+    where lower(name) like '%мест%происхожден%';
@@ -0,0 +1,13 @@
+-- Accounts of the /admin area. password_hash is an argon2 PHC string; the
+-- first account is created from the command line:
+--   server staff <login> "<name as shown in the RID history>"
+-- which asks for the password (and resets it for an existing login).
+create table Staff
+(
+    id int not null auto_increment primary key,
+    login varchar(64) not null unique,
+    name varchar(255) not null,
+    password_hash varchar(255) not null,
+    active tinyint(1) not null default 1,
+    created_at datetime not null default current_timestamp
+);
@@ -0,0 +1,10 @@
+-- What a staff account may change (see staff::Role): superadmin also manages
+-- the accounts, office edits everything else, editor only the RIDs of
+-- idFaculty, auditor only reads. Accounts created before roles keep full
+-- access.
+alter table Staff
+    add column role enum('superadmin', 'office', 'editor', 'auditor') not null default 'auditor',
+    add column idFaculty int null,
+    add foreign key (idFaculty) references Faculty(id);
+
+update Staff set role = 'superadmin';
@@ -0,0 +1,53 @@
+-- Invention disclosures sent by inventors through /disclose. Inventors sign
+-- in with a one-time link mailed to their university address
+-- (InventorLogin.token, valid for 30 minutes). A disclosure stays
+-- 'submitted' until the patent office turns it into a RID (idRID) or
+-- declines it with a note. Its files live in the attachment store under
+-- disclosures/{id} (the DisclosureFile id) and are copied to the RID on conversion.
+create table Inventor
+(
+    id int not null auto_increment primary key,
+    email varchar(255) not null unique,
+    name varchar(255) null,
+    created_at datetime not null default current_timestamp
+);
+
+create table InventorLogin
+(
+    token char(64) not null primary key,
+    email varchar(255) not null,
+    expires_at datetime not null,
+    used_at datetime null
+);
+
+create table Disclosure
+(
+    id int not null auto_increment primary key,
+    idInventor int not null,
+    title varchar(1000) not null,
+    description text not null,
+    coauthors text null,
+    idFaculty int null,
+    idType int null,
+    status enum('submitted', 'converted', 'declined') not null default 'submitted',
+    idRID int null,
+    note text null,
+    reviewed_by varchar(255) null,
+    submitted_at datetime not null default current_timestamp,
+    reviewed_at datetime null,
+    index (status, submitted_at),
+    foreign key (idInventor) references Inventor(id),
+    foreign key (idFaculty) references Faculty(id),
+    foreign key (idType) references Type(id),
+    foreign key (idRID) references RID(id) on delete set null
+);
+
+create table DisclosureFile
+(
+    id int not null auto_increment primary key,
+    idDisclosure int not null,
+    file_name varchar(255) not null,
+    mime varchar(128) not null,
+    size bigint not null,
+    foreign key (idDisclosure) references Disclosure(id) on delete cascade
+);
@@ -0,0 +1,33 @@
+-- Registration of a RID, from the office review of the disclosure to the
+-- grant or refusal. Workflow holds the current stage with whoever it waits
+-- on (idStaff) and its due date; WorkflowStep is the log of transitions with
+-- their comments. The allowed transitions are listed in src/workflow.rs.
+-- A workflow starts when a disclosure is converted, or by hand from
+-- /admin/rids/{id}/workflow for RIDs entered otherwise.
+create table Workflow
+(
+    idRID int not null primary key,
+    state enum('office_review', 'prior_art_search', 'drafting', 'filing', 'examination', 'granted', 'refused') not null,
+    idStaff int null,
+    due_date date null,
+    updated_at datetime not null default current_timestamp,
+    index (idStaff, due_date),
+    foreign key (idRID) references RID(id) on delete cascade,
+    foreign key (idStaff) references Staff(id)
+);
+
+create table WorkflowStep
+(
+    id int not null auto_increment primary key,
+    idRID int not null,
+    from_state enum('office_review', 'prior_art_search', 'drafting', 'filing', 'examination', 'granted', 'refused') null,
+    to_state enum('office_review', 'prior_art_search', 'drafting', 'filing', 'examination', 'granted', 'refused') not null,
+    idStaff int null,
+    due_date date null,
+    comment text null,
+    actor varchar(255) not null,
+    created_at datetime not null default current_timestamp,
+    index (idRID, created_at),
+    foreign key (idRID) references RID(id) on delete cascade,
+    foreign key (idStaff) references Staff(id)
+);
@@ -0,0 +1,20 @@
+-- protection_years is the fixed term of a type used for the deadline
+-- calculations; NULL when the term is not a fixed number of years (author's
+-- life, confidentiality, ...).
+alter table Type add column protection_years int null;
+
+update Type set protection_years = 70 where lower(name) like 'фонограмм%';
+update Type set protection_years = 50 where lower(name) like '%эфир%';
+update Type set protection_years = 20 where lower(name) like '%изобретен%';
+update Type set protection_years = 10 where lower(name) like '%полезн%модел%';
+update Type set protection_years = 5 where lower(name) like '%промышлен%образ%';
+update Type set protection_years = 30 where lower(name) like '%селекцион%';
+update Type set protection_years = 10 where lower(name) like '%топологи%';
+update Type set protection_years = 10 where lower(name) like '%товарн%знак%';
+update Type set protection_years = 10 where lower(name) like '%географ%';
+update Type set protection_years = 10 where lower(name) like '%мест%происхожден%';
+
+-- Personal links to the deadline calendar, issued on /admin/deadlines.
+-- A token opens /deadlines.ics for as long as its account is active; the
+-- feed of an editor only lists the RIDs of their faculty.
+alter table Staff add calendar_token char(64) null unique;
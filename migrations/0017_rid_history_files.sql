@@ -0,0 +1,93 @@
+-- The legal status entries and attachments of a RID belong to its versions
+-- too: the snapshot lists them and their changes add a version, which also
+-- moves the OAI-PMH datestamp and the sitemap date of the record. A rollback
+-- restores the status entries; attachments are only listed, since the files
+-- themselves are not kept with the versions.
+drop function rid_snapshot;
+
+create function rid_snapshot(rid int) returns json
+reads sql data
+return (
+    select json_object(
+        'name', RID.name,
+        'name_en', RID.name_en,
+        'description', RID.description,
+        'description_en', RID.description_en,
+        'numPotent', RID.numPotent,
+        'idFaculty', RID.idFaculty,
+        'idType', RID.idType,
+        'idYear', RID.idYear,
+        'idSubjectArea', RID.idSubjectArea,
+        'link', RID.link,
+        'trl', RID.trl,
+        'available', RID.available,
+        'partnership_terms', RID.partnership_terms,
+        'authors', coalesce((
+            select json_arrayagg(json_object(
+                'id', AUTHORxRID.idAuthor,
+                'position', AUTHORxRID.position,
+                'name', concat_ws(' ', FIO.surname, FIO.name, FIO.lastname)
+            ))
+            from AUTHORxRID
+            left join ConnectionAuthorCountry on (ConnectionAuthorCountry.id = AUTHORxRID.idAuthor)
+            left join FIO on (FIO.id = ConnectionAuthorCountry.idFIO)
+            where AUTHORxRID.idRID = RID.id
+        ), json_array()),
+        'agreements', coalesce((
+            select json_arrayagg(AGREEMENTxRID.idAgreement)
+            from AGREEMENTxRID
+            where AGREEMENTxRID.idRID = RID.id
+        ), json_array()),
+        'statuses', coalesce((
+            select json_arrayagg(json_object(
+                'id', StatusHistory.id,
+                'status', StatusHistory.status,
+                'date', date_format(StatusHistory.date, '%Y-%m-%d'),
+                'notes', StatusHistory.notes
+            ))
+            from StatusHistory
+            where StatusHistory.idRID = RID.id
+        ), json_array()),
+        'attachments', coalesce((
+            select json_arrayagg(json_object(
+                'id', Attachment.id,
+                'kind', Attachment.kind,
+                'title', Attachment.title
+            ))
+            from Attachment
+            where Attachment.idRID = RID.id
+        ), json_array())
+    )
+    from RID
+    where RID.id = rid
+);
+
+create trigger StatusHistory_history_insert after insert on StatusHistory for each row
+    insert into RIDHistory (idRID, actor, action, snapshot)
+    select NEW.idRID, coalesce(@audit_actor, current_user()), 'update', rid_snapshot(NEW.idRID)
+    from dual where @audit_skip is null;
+
+create trigger StatusHistory_history_update after update on StatusHistory for each row
+    insert into RIDHistory (idRID, actor, action, snapshot)
+    select NEW.idRID, coalesce(@audit_actor, current_user()), 'update', rid_snapshot(NEW.idRID)
+    from dual where @audit_skip is null;
+
+create trigger StatusHistory_history_delete after delete on StatusHistory for each row
+    insert into RIDHistory (idRID, actor, action, snapshot)
+    select OLD.idRID, coalesce(@audit_actor, current_user()), 'update', rid_snapshot(OLD.idRID)
+    from dual where @audit_skip is null and rid_snapshot(OLD.idRID) is not null;
+
+create trigger Attachment_history_insert after insert on Attachment for each row
+    insert into RIDHistory (idRID, actor, action, snapshot)
+    select NEW.idRID, coalesce(@audit_actor, current_user()), 'update', rid_snapshot(NEW.idRID)
+    from dual where @audit_skip is null;
+
+create trigger Attachment_history_update after update on Attachment for each row
+    insert into RIDHistory (idRID, actor, action, snapshot)
+    select NEW.idRID, coalesce(@audit_actor, current_user()), 'update', rid_snapshot(NEW.idRID)
+    from dual where @audit_skip is null;
+
+create trigger Attachment_history_delete after delete on Attachment for each row
+    insert into RIDHistory (idRID, actor, action, snapshot)
+    select OLD.idRID, coalesce(@audit_actor, current_user()), 'update', rid_snapshot(OLD.idRID)
+    from dual where @audit_skip is null and rid_snapshot(OLD.idRID) is not null;
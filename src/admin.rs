@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+use std::env;
+
+use actix_web::HttpResponse;
+
+/// Staff pages need a session (see `staff::Staff`); only feeds read by
+/// other programs, like the deadline calendar, are opened with `?token=`
+/// matching `ADMIN_TOKEN`, or `CALENDAR_TOKEN` as the calendar was first
+/// configured. Nothing is accessible while both are unset.
+pub fn authorized(query: &HashMap<String, String>) -> bool
+{
+    ["ADMIN_TOKEN", "CALENDAR_TOKEN"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .any(|token| !token.is_empty() && query.get("token") == Some(&token))
+}
+
+pub fn forbidden() -> HttpResponse
+{
+    HttpResponse::Forbidden()
+        .content_type("text/html; charset=utf-8")
+        .body("<h1>Error 403</h1>")
+}
+
+/// Text put into a page or an attribute value as it is, not as markup.
+pub fn escape(text: &str) -> String
+{
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Wraps the content of a staff page into a bare HTML document.
+pub fn page(title: &str, content: &str) -> HttpResponse
+{
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(format!(
+            r#"
+            <!DOCTYPE html>
+            <html lang="ru">
+            <head>
+                <meta charset="utf-8">
+                <title>{0}</title>
+            </head>
+            <body style="font-family: sans-serif;">
+                <h1>{0}</h1>
+                {1}
+            </body>
+            </html>
+            "#,
+            title,
+            content
+        ))
+}
+
+/// A `<table>` with one header row; cells are inserted as they are.
+pub fn table(header: &[&str], rows: &[Vec<String>]) -> String
+{
+    let mut body = String::from(r#"<table style="border-collapse: collapse;"><tr>"#);
+
+    for cell in header
+    {
+        body.push_str(&format!(r#"<th style="padding: .3rem 1rem; text-align: left;">{}</th>"#, cell));
+    }
+    body.push_str("</tr>");
+
+    for row in rows
+    {
+        body.push_str("<tr>");
+        for cell in row
+        {
+            body.push_str(&format!(r#"<td style="padding: .3rem 1rem; vertical-align: top;">{}</td>"#, cell));
+        }
+        body.push_str("</tr>");
+    }
+
+    body.push_str("</table>");
+    body
+}
@@ -0,0 +1,707 @@
+use std::collections::HashMap;
+
+use actix_web::{http::header, web, HttpResponse, Responder};
+use chrono::NaiveDate;
+use sqlx::mysql::{MySqlConnection, MySqlPool};
+use sqlx::Connection;
+use log::error;
+
+use crate::admin::{self, escape};
+use crate::i18n::{t, Locale};
+use crate::staff::Staff;
+
+/// Kinds and statuses as stored in Agreement, in the order the forms offer them.
+const KINDS: [&str; 4] = ["licence_exclusive", "licence_non_exclusive", "assignment", "spin_off"];
+const STATUSES: [&str; 4] = ["draft", "active", "terminated", "expired"];
+
+/// Text columns of Agreement edited on the form, with their labels.
+const TEXT_FIELDS: [(&str, &str); 5] = [
+    ("counterparty", "Контрагент"),
+    ("number", "Номер"),
+    ("territory", "Территория"),
+    ("royalty_terms", "Вознаграждение"),
+    ("notes", "Примечания")
+];
+
+/// Date columns of Agreement with their labels; only the signing date is required.
+const DATE_FIELDS: [(&str, &str); 3] = [
+    ("date_signed", "Дата подписания"),
+    ("date_start", "Начало действия"),
+    ("date_end", "Окончание действия")
+];
+
+#[derive(Debug, sqlx::FromRow)]
+struct AgreementRow
+{
+    id: i32,
+    kind: String,
+    counterparty: String,
+    number: Option<String>,
+    date_signed: String,
+    date_start: Option<String>,
+    date_end: Option<String>,
+    territory: Option<String>,
+    royalty_terms: Option<String>,
+    status: String,
+    rids: Option<String>
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct StoredAgreement
+{
+    kind: String,
+    counterparty: String,
+    number: Option<String>,
+    date_signed: String,
+    date_start: Option<String>,
+    date_end: Option<String>,
+    territory: Option<String>,
+    royalty_terms: Option<String>,
+    status: String,
+    public: bool,
+    notes: Option<String>,
+    rids: Option<String>
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct RevenueRow
+{
+    name: String,
+    faculty: Option<String>,
+    revenue: String
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct FacultyRevenueRow
+{
+    faculty: Option<String>,
+    revenue: String
+}
+
+pub fn kind_label(kind: &str) -> &str
+{
+    match kind
+    {
+        "licence_exclusive" => "Исключительная лицензия",
+        "licence_non_exclusive" => "Неисключительная лицензия",
+        "assignment" => "Отчуждение права",
+        "spin_off" => "Малое инновационное предприятие",
+        _ => kind
+    }
+}
+
+fn status_label(status: &str) -> &str
+{
+    match status
+    {
+        "draft" => "Проект",
+        "active" => "Действует",
+        "terminated" => "Расторгнут",
+        "expired" => "Истёк",
+        _ => status
+    }
+}
+
+/// Card tag for a RID covered by an active public agreement of `kind`.
+pub fn badge(locale: Locale, kind: Option<&str>) -> String
+{
+    let label = match kind
+    {
+        Some("licence_exclusive") | Some("licence_non_exclusive") => "Лицензирован",
+        Some("assignment") => "Права переданы",
+        Some("spin_off") => "Внедрён в МИП",
+        _ => return String::default()
+    };
+
+    format!(r#"<a href="" class="category-tag" style="background-color: #ff6f61; color: #ffffff;">{}</a>"#, t(locale, label))
+}
+
+fn server_error(err: sqlx::Error, page: &str) -> HttpResponse
+{
+    error!("{}: {}", err, page);
+    HttpResponse::ServiceUnavailable()
+        .content_type("text/html; charset=utf-8")
+        .body(err.to_string())
+}
+
+pub async fn register(staff: Staff, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let rows: Result<Vec<AgreementRow>, sqlx::Error> = sqlx::query_as(r#"
+        select
+            Agreement.id as id,
+            cast(Agreement.kind as char) as kind,
+            Agreement.counterparty as counterparty,
+            Agreement.number as number,
+            date_format(Agreement.date_signed, '%d.%m.%Y') as date_signed,
+            date_format(Agreement.date_start, '%d.%m.%Y') as date_start,
+            date_format(Agreement.date_end, '%d.%m.%Y') as date_end,
+            Agreement.territory as territory,
+            Agreement.royalty_terms as royalty_terms,
+            cast(Agreement.status as char) as status,
+            group_concat(concat(RID.id, ': ', RID.name) order by RID.id SEPARATOR '\n') as rids
+        from Agreement
+
+        left join AGREEMENTxRID on (AGREEMENTxRID.idAgreement = Agreement.id)
+        left join RID on (RID.id = AGREEMENTxRID.idRID)
+        group by Agreement.id
+        order by Agreement.date_signed desc
+        "#)
+        .fetch_all(pool.get_ref())
+        .await;
+
+    let rows = match rows
+    {
+        Ok(rows) => rows,
+        Err(err) => return server_error(err, "admin/agreements")
+    };
+
+    let rows: Vec<Vec<String>> = rows
+        .into_iter()
+        .map(|row| vec![
+            row.date_signed,
+            format!(
+                r#"<a href="/admin/agreements/{}/edit">{} {}</a>"#,
+                row.id, kind_label(&row.kind), escape(&row.number.unwrap_or_default())
+            ),
+            escape(&row.counterparty),
+            escape(&row.rids.unwrap_or_default()).replace('\n', "<br>"),
+            format!("{} — {}", row.date_start.unwrap_or_default(), row.date_end.unwrap_or_default()),
+            escape(&row.territory.unwrap_or_default()),
+            escape(&row.royalty_terms.unwrap_or_default()),
+            status_label(&row.status).to_string()
+        ])
+        .collect();
+
+    admin::page("Договоры о распоряжении правами", &format!(
+        "{}{}",
+        if staff.may_write() { r#"<p><a href="/admin/agreements/new">Новый договор</a></p>"# } else { "" },
+        admin::table(&["Дата", "Вид", "Контрагент", "РИД", "Срок", "Территория", "Вознаграждение", "Статус"], &rows)
+    ))
+}
+
+pub async fn revenue(_staff: Staff, query: web::Query<HashMap<String, String>>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let year: Option<i32> = query.get("year").and_then(|year| year.parse().ok());
+    let period = if year.is_some() { " where year(AgreementPayment.date) = ?" } else { "" };
+
+    // Every payment is split evenly between the RIDs its agreement covers.
+    let shares = format!(r#"
+        select
+            AGREEMENTxRID.idRID as idRID,
+            AgreementPayment.amount / Shares.rid_count as amount
+        from AgreementPayment
+
+        join (
+            select idAgreement, count(*) as rid_count from AGREEMENTxRID group by idAgreement
+        ) as Shares on (Shares.idAgreement = AgreementPayment.idAgreement)
+        join AGREEMENTxRID on (AGREEMENTxRID.idAgreement = AgreementPayment.idAgreement)
+        {}
+    "#, period);
+
+    let by_rid = format!(r#"
+        select
+            RID.name as name,
+            Faculty.name as faculty,
+            cast(round(sum(Share.amount), 2) as char) as revenue
+        from ({}) as Share
+
+        join RID on (RID.id = Share.idRID)
+        left join Faculty on (RID.idFaculty = Faculty.id)
+        group by RID.id, RID.name, Faculty.name
+        order by sum(Share.amount) desc
+    "#, shares);
+
+    let by_faculty = format!(r#"
+        select
+            Faculty.name as faculty,
+            cast(round(sum(Share.amount), 2) as char) as revenue
+        from ({}) as Share
+
+        join RID on (RID.id = Share.idRID)
+        left join Faculty on (RID.idFaculty = Faculty.id)
+        group by Faculty.name
+        order by sum(Share.amount) desc
+    "#, shares);
+
+    let mut rid_query = sqlx::query_as::<_, RevenueRow>(&by_rid);
+    let mut faculty_query = sqlx::query_as::<_, FacultyRevenueRow>(&by_faculty);
+
+    if let Some(year) = year
+    {
+        rid_query = rid_query.bind(year);
+        faculty_query = faculty_query.bind(year);
+    }
+
+    let rid_rows = match rid_query.fetch_all(pool.get_ref()).await
+    {
+        Ok(rows) => rows,
+        Err(err) => return server_error(err, "admin/revenue")
+    };
+
+    let faculty_rows = match faculty_query.fetch_all(pool.get_ref()).await
+    {
+        Ok(rows) => rows,
+        Err(err) => return server_error(err, "admin/revenue")
+    };
+
+    let rid_rows: Vec<Vec<String>> = rid_rows
+        .into_iter()
+        .map(|row| vec![escape(&row.name), escape(&row.faculty.unwrap_or_default()), row.revenue])
+        .collect();
+
+    let faculty_rows: Vec<Vec<String>> = faculty_rows
+        .into_iter()
+        .map(|row| vec![escape(&row.faculty.unwrap_or_default()), row.revenue])
+        .collect();
+
+    let title = match year
+    {
+        Some(year) => format!("Доходы от распоряжения правами за {} год", year),
+        None => "Доходы от распоряжения правами".to_string()
+    };
+
+    admin::page(&title, &format!(
+        "<h2>По факультетам</h2>{}<h2>По РИД</h2>{}",
+        admin::table(&["Факультет", "Сумма, ₽"], &faculty_rows),
+        admin::table(&["РИД", "Факультет", "Сумма, ₽"], &rid_rows)
+    ))
+}
+
+fn redirect(location: String) -> HttpResponse
+{
+    HttpResponse::SeeOther()
+        .append_header((header::LOCATION, location))
+        .finish()
+}
+
+fn not_found() -> HttpResponse
+{
+    HttpResponse::NotFound()
+        .content_type("text/html; charset=utf-8")
+        .body("<h1>Error 404</h1>")
+}
+
+fn select(name: &str, values: &[&str], label: fn(&str) -> &str, selected: &str) -> String
+{
+    let options: Vec<String> = values
+        .iter()
+        .map(|value| format!(
+            r#"<option value="{}"{}>{}</option>"#,
+            value, if *value == selected { " selected" } else { "" }, label(value)
+        ))
+        .collect();
+
+    format!(r#"<select name="{}">{}</select>"#, name, options.concat())
+}
+
+/// Columns of one agreement as the form shows them, with `rids` listing the
+/// covered RID ids one per line.
+async fn stored(pool: &MySqlPool, id: i32) -> Result<Option<HashMap<String, String>>, sqlx::Error>
+{
+    let row: Option<StoredAgreement> = sqlx::query_as(r#"
+        select
+            cast(Agreement.kind as char) as kind,
+            Agreement.counterparty as counterparty,
+            Agreement.number as number,
+            date_format(Agreement.date_signed, '%Y-%m-%d') as date_signed,
+            date_format(Agreement.date_start, '%Y-%m-%d') as date_start,
+            date_format(Agreement.date_end, '%Y-%m-%d') as date_end,
+            Agreement.territory as territory,
+            Agreement.royalty_terms as royalty_terms,
+            cast(Agreement.status as char) as status,
+            Agreement.public as public,
+            Agreement.notes as notes,
+            (select cast(group_concat(idRID order by idRID SEPARATOR '\n') as char) from AGREEMENTxRID where idAgreement = Agreement.id) as rids
+        from Agreement
+        where Agreement.id = ?
+        "#)
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(row.map(|row| HashMap::from([
+        ("kind".to_string(), row.kind),
+        ("counterparty".to_string(), row.counterparty),
+        ("number".to_string(), row.number.unwrap_or_default()),
+        ("date_signed".to_string(), row.date_signed),
+        ("date_start".to_string(), row.date_start.unwrap_or_default()),
+        ("date_end".to_string(), row.date_end.unwrap_or_default()),
+        ("territory".to_string(), row.territory.unwrap_or_default()),
+        ("royalty_terms".to_string(), row.royalty_terms.unwrap_or_default()),
+        ("status".to_string(), row.status),
+        ("public".to_string(), if row.public { "1" } else { "" }.to_string()),
+        ("notes".to_string(), row.notes.unwrap_or_default()),
+        ("rids".to_string(), row.rids.unwrap_or_default())
+    ])))
+}
+
+async fn payments(pool: &MySqlPool, id: i32) -> Result<Vec<(i32, String, String, Option<String>)>, sqlx::Error>
+{
+    sqlx::query_as(r#"
+        select id, date_format(date, '%d.%m.%Y'), cast(amount as char), notes
+        from AgreementPayment
+        where idAgreement = ?
+        order by date, id
+        "#)
+        .bind(id)
+        .fetch_all(pool)
+        .await
+}
+
+fn form_page(id: Option<i32>, values: &HashMap<String, String>, payments: &[(i32, String, String, Option<String>)], problems: &[String]) -> HttpResponse
+{
+    let value = |key: &str| escape(values.get(key).map(String::as_str).unwrap_or_default());
+
+    let mut fields = vec![
+        format!("<p><label>Вид<br>{}</label></p>", select("kind", &KINDS, kind_label, values.get("kind").map(String::as_str).unwrap_or_default())),
+        format!("<p><label>Статус<br>{}</label></p>", select("status", &STATUSES, status_label, values.get("status").map(String::as_str).unwrap_or("draft")))
+    ];
+
+    for (key, label) in TEXT_FIELDS
+    {
+        fields.push(match key
+        {
+            "royalty_terms" | "notes" => format!(r#"<p><label>{}<br><textarea name="{}" rows="3" cols="80">{}</textarea></label></p>"#, label, key, value(key)),
+            _ => format!(r#"<p><label>{}<br><input name="{}" value="{}" size="60"{}></label></p>"#, label, key, value(key), if key == "counterparty" { " required" } else { "" })
+        });
+    }
+
+    for (key, label) in DATE_FIELDS
+    {
+        fields.push(format!(
+            r#"<p><label>{}<br><input type="date" name="{}" value="{}"{}></label></p>"#,
+            label, key, value(key), if key == "date_signed" { " required" } else { "" }
+        ));
+    }
+
+    fields.push(format!(
+        r#"<p><label><input type="checkbox" name="public" value="1"{}> показывать на карточках РИД</label></p>"#,
+        if values.get("public").is_some_and(|public| !public.is_empty()) { " checked" } else { "" }
+    ));
+    fields.push(format!(
+        r#"<p><label>РИД по договору — номера записей (id), по одному в строке<br><textarea name="rids" rows="4" cols="20">{}</textarea></label></p>"#,
+        value("rids")
+    ));
+
+    let action = match id
+    {
+        Some(id) => format!("/admin/agreements/{}/edit", id),
+        None => "/admin/agreements/new".to_string()
+    };
+
+    // Payments belong to a saved agreement.
+    let payment_list = match id
+    {
+        Some(id) => {
+            let rows: Vec<Vec<String>> = payments
+                .iter()
+                .map(|(payment, date, amount, notes)| vec![
+                    date.clone(),
+                    amount.clone(),
+                    escape(notes.as_deref().unwrap_or_default()),
+                    format!(
+                        r#"<form method="post" action="/admin/agreements/{}/payments/{}/delete" onsubmit="return confirm('Удалить платёж?')"><button type="submit">удалить</button></form>"#,
+                        id, payment
+                    )
+                ])
+                .collect();
+
+            format!(
+                r#"<h2>Платежи</h2>{}
+                <form method="post" action="/admin/agreements/{}/payments"><input type="date" name="date" required> <input name="amount" size="12" placeholder="Сумма, ₽" required> <input name="notes" size="40" placeholder="Примечание"> <button type="submit">Добавить платёж</button></form>"#,
+                admin::table(&["Дата", "Сумма, ₽", "Примечание", ""], &rows),
+                id
+            )
+        },
+        None => String::default()
+    };
+
+    admin::page(&match id { Some(id) => format!("Договор {}", id), None => "Новый договор".to_string() }, &format!(
+        r#"<p><a href="/admin">Администрирование</a> / <a href="/admin/agreements">Договоры</a></p>{}<form method="post" action="{}">{}<p><button type="submit">Сохранить</button></p></form>{}"#,
+        problems.iter().map(|problem| format!(r#"<p style="color: #b00020;">{}</p>"#, escape(problem))).collect::<String>(),
+        action,
+        fields.concat(),
+        payment_list
+    ))
+}
+
+pub async fn create_form(staff: Staff) -> impl Responder
+{
+    if !staff.may_write()
+    {
+        return staff.deny("admin/agreements/new");
+    }
+
+    form_page(None, &HashMap::new(), &[], &[])
+}
+
+pub async fn edit_form(staff: Staff, id: web::Path<i32>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let page = format!("admin/agreements/{}/edit", id);
+
+    if !staff.may_write()
+    {
+        return staff.deny(&page);
+    }
+
+    match (stored(pool.get_ref(), *id).await, payments(pool.get_ref(), *id).await)
+    {
+        (Ok(Some(values)), Ok(payments)) => form_page(Some(*id), &values, &payments, &[]),
+        (Ok(None), _) => not_found(),
+        (Err(err), _) | (_, Err(err)) => server_error(err, &page)
+    }
+}
+
+/// Ids of the RIDs listed on the form, or the lines that are not RIDs.
+async fn covered(pool: &MySqlPool, list: &str) -> Result<Result<Vec<i32>, Vec<String>>, sqlx::Error>
+{
+    let mut rids = Vec::new();
+    let mut problems = Vec::new();
+
+    for line in list.split([',', '\n']).map(str::trim).filter(|line| !line.is_empty())
+    {
+        match line.parse::<i32>()
+        {
+            Ok(rid) if sqlx::query_scalar::<_, i64>("select count(*) from RID where id = ?").bind(rid).fetch_one(pool).await? > 0 => rids.push(rid),
+            _ => problems.push(format!("нет РИД с номером записи «{}»", line))
+        }
+    }
+
+    rids.sort();
+    rids.dedup();
+
+    Ok(if problems.is_empty() { Ok(rids) } else { Err(problems) })
+}
+
+/// Checks the form; returns the problems found.
+fn check(form: &HashMap<String, String>) -> Vec<String>
+{
+    let mut problems = Vec::new();
+    let given = |key: &str| form.get(key).map(|value| value.trim()).filter(|value| !value.is_empty());
+
+    if !form.get("kind").is_some_and(|kind| KINDS.contains(&kind.as_str()))
+    {
+        problems.push("выберите вид договора".to_string());
+    }
+    if !form.get("status").is_some_and(|status| STATUSES.contains(&status.as_str()))
+    {
+        problems.push("выберите статус".to_string());
+    }
+    if given("counterparty").is_none()
+    {
+        problems.push("укажите контрагента".to_string());
+    }
+    if given("date_signed").is_none()
+    {
+        problems.push("укажите дату подписания".to_string());
+    }
+    for (key, label) in DATE_FIELDS
+    {
+        if given(key).is_some_and(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err())
+        {
+            problems.push(format!("{}: дата в виде ГГГГ-ММ-ДД", label));
+        }
+    }
+
+    problems
+}
+
+/// Writes the agreement and replaces its RID links; returns its id.
+async fn save(conn: &mut MySqlConnection, id: Option<i32>, form: &HashMap<String, String>, rids: &[i32]) -> Result<i32, sqlx::Error>
+{
+    let given = |key: &str| form.get(key).map(|value| value.trim()).filter(|value| !value.is_empty());
+
+    let mut tx = conn.begin().await?;
+
+    let statement = match id
+    {
+        Some(_) => r#"
+            update Agreement
+            set kind = ?, status = ?, counterparty = ?, number = ?, territory = ?, royalty_terms = ?, notes = ?,
+                date_signed = ?, date_start = ?, date_end = ?, public = ?
+            where id = ?
+            "#,
+        None => r#"
+            insert into Agreement (kind, status, counterparty, number, territory, royalty_terms, notes, date_signed, date_start, date_end, public)
+            values (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+    };
+
+    let mut query = sqlx::query(statement)
+        .bind(given("kind"))
+        .bind(given("status"));
+    for (key, _) in TEXT_FIELDS
+    {
+        query = query.bind(given(key));
+    }
+    for (key, _) in DATE_FIELDS
+    {
+        query = query.bind(given(key));
+    }
+    query = query.bind(given("public").is_some());
+
+    let id = match id
+    {
+        Some(id) => {
+            query.bind(id).execute(&mut *tx).await?;
+            id
+        },
+        None => query.execute(&mut *tx).await?.last_insert_id() as i32
+    };
+
+    let linked: Vec<i32> = sqlx::query_scalar("select idRID from AGREEMENTxRID where idAgreement = ?")
+        .bind(id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+    // Only the changed links are touched, so only those RIDs get a new version.
+    for rid in linked.iter().filter(|rid| !rids.contains(rid))
+    {
+        sqlx::query("delete from AGREEMENTxRID where idAgreement = ? and idRID = ?").bind(id).bind(rid).execute(&mut *tx).await?;
+    }
+    for rid in rids.iter().filter(|rid| !linked.contains(rid))
+    {
+        sqlx::query("insert into AGREEMENTxRID (idAgreement, idRID) values (?, ?)").bind(id).bind(rid).execute(&mut *tx).await?;
+    }
+
+    tx.commit().await?;
+    Ok(id)
+}
+
+async fn submit(pool: &MySqlPool, staff: &Staff, id: Option<i32>, form: &HashMap<String, String>) -> HttpResponse
+{
+    let page = match id
+    {
+        Some(id) => format!("admin/agreements/{}/edit", id),
+        None => "admin/agreements/new".to_string()
+    };
+
+    if !staff.may_write()
+    {
+        return staff.deny(&page);
+    }
+
+    let payments = match id
+    {
+        Some(id) => match payments(pool, id).await
+        {
+            Ok(payments) => payments,
+            Err(err) => return server_error(err, &page)
+        },
+        None => Vec::new()
+    };
+
+    let mut problems = check(form);
+    let rids = match covered(pool, form.get("rids").map(String::as_str).unwrap_or_default()).await
+    {
+        Ok(Ok(rids)) => rids,
+        Ok(Err(mut missing)) => {
+            problems.append(&mut missing);
+            Vec::new()
+        },
+        Err(err) => return server_error(err, &page)
+    };
+
+    if !problems.is_empty()
+    {
+        return form_page(id, form, &payments, &problems);
+    }
+
+    let mut conn = match pool.acquire().await
+    {
+        Ok(conn) => conn,
+        Err(err) => return server_error(err, &page)
+    };
+
+    // Changed RID links are versioned with the RID (see migrations/0009_rid_history.sql).
+    let saved = match sqlx::query("set @audit_actor = ?").bind(&staff.name).execute(&mut *conn).await
+    {
+        Ok(_) => save(&mut conn, id, form, &rids).await,
+        Err(err) => Err(err)
+    };
+
+    if let Err(err) = sqlx::query("set @audit_actor = null").execute(&mut *conn).await
+    {
+        error!("{}: {}", err, page);
+        conn.close_on_drop();
+    }
+
+    match saved
+    {
+        Ok(id) => redirect(format!("/admin/agreements/{}/edit", id)),
+        Err(err) => server_error(err, &page)
+    }
+}
+
+pub async fn create(staff: Staff, form: web::Form<HashMap<String, String>>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    submit(pool.get_ref(), &staff, None, &form).await
+}
+
+pub async fn edit(staff: Staff, id: web::Path<i32>, form: web::Form<HashMap<String, String>>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    submit(pool.get_ref(), &staff, Some(*id), &form).await
+}
+
+pub async fn add_payment(staff: Staff, id: web::Path<i32>, form: web::Form<HashMap<String, String>>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let page = format!("admin/agreements/{}/payments", id);
+
+    if !staff.may_write()
+    {
+        return staff.deny(&page);
+    }
+
+    let date = form.get("date").filter(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok());
+    // Amounts are typed with a comma as often as with a point.
+    let amount = form
+        .get("amount")
+        .map(|amount| amount.trim().replace(' ', "").replace(',', "."))
+        .filter(|amount| amount.parse::<f64>().is_ok_and(|amount| amount.is_finite()));
+
+    let (Some(date), Some(amount)) = (date, amount) else {
+        return match (stored(pool.get_ref(), *id).await, payments(pool.get_ref(), *id).await)
+        {
+            (Ok(Some(values)), Ok(payments)) => form_page(Some(*id), &values, &payments, &["платёж: укажите дату и сумму числом".to_string()]),
+            (Ok(None), _) => not_found(),
+            (Err(err), _) | (_, Err(err)) => server_error(err, &page)
+        };
+    };
+
+    let added = sqlx::query("insert into AgreementPayment (idAgreement, date, amount, notes) values (?, ?, ?, ?)")
+        .bind(*id)
+        .bind(date)
+        .bind(amount)
+        .bind(form.get("notes").map(|notes| notes.trim()).filter(|notes| !notes.is_empty()))
+        .execute(pool.get_ref())
+        .await;
+
+    match added
+    {
+        Ok(_) => redirect(format!("/admin/agreements/{}/edit", id)),
+        Err(err) => server_error(err, &page)
+    }
+}
+
+pub async fn delete_payment(staff: Staff, path: web::Path<(i32, i32)>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let (id, payment) = path.into_inner();
+    let page = format!("admin/agreements/{}/payments/{}/delete", id, payment);
+
+    if !staff.may_write()
+    {
+        return staff.deny(&page);
+    }
+
+    let deleted = sqlx::query("delete from AgreementPayment where id = ? and idAgreement = ?")
+        .bind(payment)
+        .bind(id)
+        .execute(pool.get_ref())
+        .await;
+
+    match deleted
+    {
+        Ok(_) => redirect(format!("/admin/agreements/{}/edit", id)),
+        Err(err) => server_error(err, &page)
+    }
+}
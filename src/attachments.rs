@@ -0,0 +1,647 @@
+use std::collections::HashMap;
+use std::env;
+use std::io::Cursor;
+use std::sync::Arc;
+
+use actix_multipart::{Field, Multipart};
+use actix_web::{http::header, web, HttpRequest, HttpResponse, Responder};
+use futures_util::{StreamExt, TryStreamExt};
+use object_store::{aws::AmazonS3Builder, local::LocalFileSystem, path::Path, GetOptions, GetRange, ObjectStore};
+use sqlx::mysql::{MySqlConnection, MySqlPool};
+use log::error;
+
+use crate::admin::escape;
+use crate::staff::{self, Staff};
+
+/// Content types accepted for upload, detected from the file contents
+/// rather than trusted from the client.
+const ALLOWED_TYPES: [&str; 9] = [
+    "application/pdf",
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "application/vnd.ms-powerpoint",
+    "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+    "application/msword",
+    "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+];
+
+const KINDS: [&str; 5] = ["patent", "certificate", "drawing", "presentation", "other"];
+
+fn kind_label(kind: &str) -> &str
+{
+    match kind
+    {
+        "patent" => "Охранный документ",
+        "certificate" => "Свидетельство",
+        "drawing" => "Чертёж",
+        "presentation" => "Презентация",
+        "other" => "Другое",
+        _ => kind
+    }
+}
+
+const THUMBNAIL_SIZE: u32 = 320;
+
+/// Limit of a text field of an upload form, in bytes.
+pub const MAX_FIELD_SIZE: usize = 64 * 1024;
+
+/// The value of a text field of a multipart form, None if it is larger than
+/// `MAX_FIELD_SIZE` or the upload broke off.
+pub async fn text_field(field: &mut Field) -> Option<String>
+{
+    let mut value: Vec<u8> = Vec::new();
+
+    while let Some(chunk) = field.next().await
+    {
+        let chunk = chunk.ok()?;
+        if value.len() + chunk.len() > MAX_FIELD_SIZE
+        {
+            return None;
+        }
+        value.extend_from_slice(&chunk);
+    }
+
+    Some(String::from_utf8_lossy(&value).to_string())
+}
+
+/// Where attachment files live. Chosen once at startup from the environment:
+/// `ATTACHMENTS_STORE=s3` uses `ATTACHMENTS_BUCKET` and the usual `AWS_*`
+/// variables (endpoint, region, keys), anything else a local directory.
+pub struct Storage
+{
+    store: Arc<dyn ObjectStore>,
+    max_size: usize
+}
+
+impl Storage
+{
+    pub fn from_env() -> Storage
+    {
+        let store: Arc<dyn ObjectStore> = match env::var("ATTACHMENTS_STORE").as_deref()
+        {
+            Ok("s3") => Arc::new(
+                AmazonS3Builder::from_env()
+                    .with_bucket_name(env::var("ATTACHMENTS_BUCKET").expect("No env var found"))
+                    .build()
+                    .expect("S3 store failed")
+            ),
+            _ => {
+                let path = env::var("ATTACHMENTS_PATH").unwrap_or("attachments".to_string());
+                std::fs::create_dir_all(&path).expect("attachments directory failed");
+                Arc::new(LocalFileSystem::new_with_prefix(path).expect("local store failed"))
+            }
+        };
+
+        let max_size = env::var("ATTACHMENTS_MAX_SIZE")
+            .ok()
+            .and_then(|size| size.parse().ok())
+            .unwrap_or(50 * 1024 * 1024);
+
+        Storage { store, max_size }
+    }
+
+    pub fn max_size(&self) -> usize
+    {
+        self.max_size
+    }
+
+    /// Files of invention disclosures, kept apart from RID attachments
+    /// until the disclosure becomes a RID.
+    pub async fn put_disclosure_file(&self, id: u64, data: Vec<u8>) -> object_store::Result<()>
+    {
+        self.store.put(&disclosure_key(id), data.into()).await.map(|_| ())
+    }
+
+    pub async fn disclosure_file(&self, id: u64) -> object_store::Result<Vec<u8>>
+    {
+        Ok(self.store.get(&disclosure_key(id)).await?.bytes().await?.to_vec())
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct AttachmentFile
+{
+    file_name: String,
+    mime: String,
+    size: i64,
+    has_thumbnail: bool
+}
+
+fn file_key(id: u64) -> Path
+{
+    Path::from(format!("attachments/{}", id))
+}
+
+fn disclosure_key(id: u64) -> Path
+{
+    Path::from(format!("disclosures/{}", id))
+}
+
+fn thumbnail_key(id: u64) -> Path
+{
+    Path::from(format!("attachments/{}.thumb.png", id))
+}
+
+fn bad_request(message: &str) -> HttpResponse
+{
+    HttpResponse::BadRequest()
+        .content_type("text/html; charset=utf-8")
+        .body(message.to_string())
+}
+
+fn server_error(err: impl std::fmt::Display, page: &str) -> HttpResponse
+{
+    error!("{}: {}", err, page);
+    HttpResponse::ServiceUnavailable()
+        .content_type("text/html; charset=utf-8")
+        .body(err.to_string())
+}
+
+fn not_found() -> HttpResponse
+{
+    HttpResponse::NotFound()
+        .content_type("text/html; charset=utf-8")
+        .body("<h1>Error 404</h1>")
+}
+
+/// Content type of an upload if it is one of `ALLOWED_TYPES`.
+pub fn accepted_type(data: &[u8]) -> Option<&'static str>
+{
+    infer::get(data).map(|kind| kind.mime_type()).filter(|mime| ALLOWED_TYPES.contains(mime))
+}
+
+/// Adds `data`, of one of the allowed types, to the files of `rid`, with a
+/// thumbnail for images. Returns the id of the attachment. The caller names
+/// the change in the RID history through `@audit_actor` on `conn`.
+pub async fn add(
+    conn: &mut MySqlConnection,
+    storage: &Storage,
+    rid: i32,
+    kind: &str,
+    title: &str,
+    file_name: &str,
+    data: Vec<u8>
+) -> Result<u64, Box<dyn std::error::Error + Send + Sync>>
+{
+    let mime = accepted_type(&data).unwrap_or("application/octet-stream");
+
+    let thumbnail = if mime.starts_with("image/")
+    {
+        let image = data.clone();
+        web::block(move || make_thumbnail(&image)).await.ok().flatten()
+    }
+    else
+    {
+        None
+    };
+
+    let id = sqlx::query(r#"
+        insert into Attachment (idRID, kind, title, file_name, mime, size, has_thumbnail)
+        values (?, ?, ?, ?, ?, ?, ?)
+        "#)
+        .bind(rid)
+        .bind(kind)
+        .bind(title)
+        .bind(file_name)
+        .bind(mime)
+        .bind(data.len() as i64)
+        .bind(thumbnail.is_some())
+        .execute(&mut *conn)
+        .await?
+        .last_insert_id();
+
+    let mut stored = storage.store.put(&file_key(id), data.into()).await.map(|_| ());
+    if let (Ok(()), Some(thumbnail)) = (&stored, thumbnail)
+    {
+        stored = storage.store.put(&thumbnail_key(id), thumbnail.into()).await.map(|_| ());
+    }
+
+    if let Err(err) = stored
+    {
+        let _ = sqlx::query("delete from Attachment where id = ?").bind(id).execute(&mut *conn).await;
+        let _ = storage.store.delete(&file_key(id)).await;
+        return Err(err.into());
+    }
+
+    Ok(id)
+}
+
+/// Scales an image down to fit `THUMBNAIL_SIZE` and encodes it as PNG.
+fn make_thumbnail(data: &[u8]) -> Option<Vec<u8>>
+{
+    let image = image::load_from_memory(data).ok()?;
+    let mut png = Cursor::new(Vec::new());
+
+    image
+        .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        .write_to(&mut png, image::ImageFormat::Png)
+        .ok()?;
+
+    Some(png.into_inner())
+}
+
+/// Parses a single `bytes=` range against an object of `size` bytes.
+/// `Ok(None)` means the whole object, `Err(())` an unsatisfiable range.
+fn parse_range(value: &str, size: u64) -> Result<Option<(u64, u64)>, ()>
+{
+    let Some(spec) = value.strip_prefix("bytes=") else { return Ok(None) };
+
+    // Several ranges at once are legal but not worth a multipart reply.
+    if spec.contains(',')
+    {
+        return Ok(None);
+    }
+
+    let Some((start, end)) = spec.trim().split_once('-') else { return Ok(None) };
+
+    let (start, end) = match (start.parse::<u64>(), end.parse::<u64>())
+    {
+        (Ok(start), Ok(end)) => (start, end.min(size.saturating_sub(1))),
+        (Ok(start), Err(_)) if end.is_empty() => (start, size.saturating_sub(1)),
+        (Err(_), Ok(suffix)) if start.is_empty() => (size.saturating_sub(suffix), size.saturating_sub(1)),
+        _ => return Ok(None)
+    };
+
+    if size == 0 || start > end || start >= size
+    {
+        return Err(());
+    }
+
+    Ok(Some((start, end)))
+}
+
+pub async fn upload(
+    rid: web::Path<i32>,
+    staff: Staff,
+    mut payload: Multipart,
+    storage: web::Data<Storage>,
+    pool: web::Data<MySqlPool>
+) -> impl Responder
+{
+    match staff::rid_faculty(pool.get_ref(), *rid).await
+    {
+        Ok(Some(faculty)) if staff.may_edit(faculty) => {},
+        Ok(Some(_)) => return staff.deny("attachments upload"),
+        Ok(None) => return not_found(),
+        Err(err) => return server_error(err, "attachments upload")
+    }
+
+    let mut data: Vec<u8> = Vec::new();
+    let mut file_name = String::default();
+    let mut fields: HashMap<String, String> = HashMap::new();
+
+    while let Ok(Some(mut field)) = payload.try_next().await
+    {
+        let name = field.name().unwrap_or_default().to_string();
+
+        if name == "file"
+        {
+            file_name = field
+                .content_disposition()
+                .and_then(|disposition| disposition.get_filename())
+                .unwrap_or("file")
+                .to_string();
+
+            while let Some(chunk) = field.next().await
+            {
+                let Ok(chunk) = chunk else { return bad_request("Upload interrupted") };
+
+                if data.len() + chunk.len() > storage.max_size
+                {
+                    return HttpResponse::PayloadTooLarge()
+                        .content_type("text/html; charset=utf-8")
+                        .body(format!("File is larger than {} bytes", storage.max_size));
+                }
+                data.extend_from_slice(&chunk);
+            }
+        }
+        else
+        {
+            let Some(value) = text_field(&mut field).await else {
+                return bad_request(&format!("Field {} is larger than {} bytes", escape(&name), MAX_FIELD_SIZE));
+            };
+            fields.insert(name, value);
+        }
+    }
+
+    if data.is_empty()
+    {
+        return bad_request("No file uploaded");
+    }
+
+    if accepted_type(&data).is_none()
+    {
+        return bad_request("File type is not allowed");
+    }
+
+    let kind = fields.get("kind").map(|kind| kind.as_str()).filter(|kind| KINDS.contains(kind)).unwrap_or("other");
+    let title = fields.get("title").filter(|title| !title.trim().is_empty()).cloned().unwrap_or(file_name.clone());
+
+    let mut conn = match pool.acquire().await
+    {
+        Ok(conn) => conn,
+        Err(err) => return server_error(err, "attachments upload")
+    };
+
+    // Names the change in the RID history (see migrations/0017_rid_history_files.sql).
+    let added = match sqlx::query("set @audit_actor = ?").bind(&staff.name).execute(&mut *conn).await
+    {
+        Ok(_) => add(&mut conn, &storage, *rid, kind, &title, &file_name, data).await,
+        Err(err) => Err(err.into())
+    };
+
+    if let Err(err) = sqlx::query("set @audit_actor = null").execute(&mut *conn).await
+    {
+        error!("{}: attachments upload", err);
+        conn.close_on_drop();
+    }
+
+    let id = match added
+    {
+        Ok(id) => id,
+        Err(err) => return server_error(err, "attachments upload")
+    };
+
+    // The panel under the RID form goes back to the RID.
+    if fields.contains_key("panel")
+    {
+        return HttpResponse::SeeOther()
+            .append_header((header::LOCATION, format!("/admin/rids/{}/edit", rid)))
+            .finish();
+    }
+
+    HttpResponse::Created()
+        .append_header((header::LOCATION, format!("/attachments/{}", id)))
+        .content_type("text/html; charset=utf-8")
+        .body(format!(r#"<a href="/attachments/{}">{}</a>"#, id, escape(&title)))
+}
+
+/// Removes one attachment with its files; returns the RID it belonged to.
+async fn remove(id: u64, staff: &Staff, storage: &Storage, pool: &MySqlPool) -> Result<i32, HttpResponse>
+{
+    let owner: Result<Option<(i32, Option<i32>)>, sqlx::Error> = sqlx::query_as(r#"
+        select RID.id, RID.idFaculty from Attachment
+        join RID on (RID.id = Attachment.idRID)
+        where Attachment.id = ?
+        "#)
+        .bind(id)
+        .fetch_optional(pool)
+        .await;
+
+    let rid = match owner
+    {
+        Ok(Some((rid, faculty))) if staff.may_edit(faculty) => rid,
+        Ok(Some(_)) => return Err(staff.deny("attachments delete")),
+        Ok(None) => return Err(not_found()),
+        Err(err) => return Err(server_error(err, "attachments delete"))
+    };
+
+    let mut conn = match pool.acquire().await
+    {
+        Ok(conn) => conn,
+        Err(err) => return Err(server_error(err, "attachments delete"))
+    };
+
+    let deleted = match sqlx::query("set @audit_actor = ?").bind(&staff.name).execute(&mut *conn).await
+    {
+        Ok(_) => sqlx::query("delete from Attachment where id = ?").bind(id).execute(&mut *conn).await,
+        Err(err) => Err(err)
+    };
+
+    if let Err(err) = sqlx::query("set @audit_actor = null").execute(&mut *conn).await
+    {
+        error!("{}: attachments delete", err);
+        conn.close_on_drop();
+    }
+
+    match deleted
+    {
+        Ok(result) if result.rows_affected() == 0 => Err(not_found()),
+        Ok(_) => {
+            // The row is gone, so a leftover object is only wasted space.
+            for key in [file_key(id), thumbnail_key(id)]
+            {
+                if let Err(err) = storage.store.delete(&key).await
+                {
+                    error!("{}: attachments delete {}", err, key);
+                }
+            }
+            Ok(rid)
+        },
+        Err(err) => Err(server_error(err, "attachments delete"))
+    }
+}
+
+pub async fn delete(
+    id: web::Path<u64>,
+    staff: Staff,
+    storage: web::Data<Storage>,
+    pool: web::Data<MySqlPool>
+) -> impl Responder
+{
+    match remove(*id, &staff, &storage, pool.get_ref()).await
+    {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(response) => response
+    }
+}
+
+/// The delete button of the panel: browsers only send forms as POST.
+pub async fn delete_form(
+    id: web::Path<u64>,
+    staff: Staff,
+    storage: web::Data<Storage>,
+    pool: web::Data<MySqlPool>
+) -> impl Responder
+{
+    match remove(*id, &staff, &storage, pool.get_ref()).await
+    {
+        Ok(rid) => HttpResponse::SeeOther()
+            .append_header((header::LOCATION, format!("/admin/rids/{}/edit", rid)))
+            .finish(),
+        Err(response) => response
+    }
+}
+
+/// Files of a RID with the upload form and delete buttons, shown under the
+/// RID edit form.
+pub async fn panel(pool: &MySqlPool, rid: i32) -> Result<String, sqlx::Error>
+{
+    let files: Vec<(u64, String, String, String, i64)> = sqlx::query_as(r#"
+        select id, cast(kind as char), title, file_name, size
+        from Attachment
+        where idRID = ?
+        order by id
+        "#)
+        .bind(rid)
+        .fetch_all(pool)
+        .await?;
+
+    let rows: Vec<String> = files
+        .iter()
+        .map(|(id, kind, title, file_name, size)| format!(
+            r#"<li><a href="/attachments/{0}">{1}</a> ({2}, {3}, {4} КБ) <form method="post" action="/admin/attachments/{0}/delete" style="display: inline;" onsubmit="return confirm('Удалить файл?')"><button type="submit">удалить</button></form></li>"#,
+            id, escape(title), kind_label(kind), escape(file_name), size / 1024
+        ))
+        .collect();
+
+    let kinds: Vec<String> = KINDS
+        .iter()
+        .map(|kind| format!(r#"<option value="{}"{}>{}</option>"#, kind, if *kind == "other" { " selected" } else { "" }, kind_label(kind)))
+        .collect();
+
+    Ok(format!(
+        r#"<h2>Вложения</h2>{}
+        <form method="post" action="/admin/rids/{}/attachments" enctype="multipart/form-data">
+            <input type="hidden" name="panel" value="1">
+            <select name="kind">{}</select> <input name="title" size="40" placeholder="Название (по умолчанию имя файла)"> <input type="file" name="file" required> <button type="submit">Загрузить</button>
+        </form>"#,
+        if rows.is_empty() { "<p>Файлов нет.</p>".to_string() } else { format!("<ul>{}</ul>", rows.concat()) },
+        rid,
+        kinds.concat()
+    ))
+}
+
+async fn find_file(pool: &MySqlPool, id: u64) -> Result<Option<AttachmentFile>, sqlx::Error>
+{
+    sqlx::query_as("select file_name, mime, size, has_thumbnail from Attachment where id = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn download(
+    id: web::Path<u64>,
+    request: HttpRequest,
+    storage: web::Data<Storage>,
+    pool: web::Data<MySqlPool>
+) -> impl Responder
+{
+    let file = match find_file(pool.get_ref(), *id).await
+    {
+        Ok(Some(file)) => file,
+        Ok(None) => return not_found(),
+        Err(err) => return server_error(err, "attachments download")
+    };
+
+    let size = file.size.max(0) as u64;
+    let range = match request.headers().get(header::RANGE).and_then(|value| value.to_str().ok())
+    {
+        Some(value) => parse_range(value, size),
+        None => Ok(None)
+    };
+
+    let range = match range
+    {
+        Ok(range) => range,
+        Err(()) => {
+            return HttpResponse::RangeNotSatisfiable()
+                .append_header((header::CONTENT_RANGE, format!("bytes */{}", size)))
+                .finish();
+        }
+    };
+
+    let options = GetOptions {
+        range: range.map(|(start, end)| GetRange::Bounded(start..end + 1)),
+        ..GetOptions::default()
+    };
+
+    let result = match storage.store.get_opts(&file_key(*id), options).await
+    {
+        Ok(result) => result,
+        Err(object_store::Error::NotFound { .. }) => return not_found(),
+        Err(err) => return server_error(err, "attachments download")
+    };
+
+    let mut response = match range
+    {
+        Some((start, end)) => {
+            let mut response = HttpResponse::PartialContent();
+            response.append_header((header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, size)));
+            response
+        },
+        None => HttpResponse::Ok()
+    };
+
+    response
+        .content_type(file.mime)
+        .append_header((header::ACCEPT_RANGES, "bytes"))
+        .append_header((header::CONTENT_DISPOSITION, format!(
+            "inline; filename*=UTF-8''{}",
+            file.file_name.bytes().map(|byte| match byte
+            {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'.' | b'-' | b'_' => (byte as char).to_string(),
+                _ => format!("%{:02X}", byte)
+            }).collect::<String>()
+        )))
+        .no_chunking(result.range.end - result.range.start)
+        .streaming(result.into_stream())
+}
+
+pub async fn thumbnail(id: web::Path<u64>, storage: web::Data<Storage>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    match find_file(pool.get_ref(), *id).await
+    {
+        Ok(Some(file)) if file.has_thumbnail => (),
+        Ok(_) => return not_found(),
+        Err(err) => return server_error(err, "attachments thumbnail")
+    };
+
+    let result = match storage.store.get(&thumbnail_key(*id)).await
+    {
+        Ok(result) => result,
+        Err(err) => return server_error(err, "attachments thumbnail")
+    };
+
+    match result.bytes().await
+    {
+        Ok(bytes) => {
+            HttpResponse::Ok()
+                .content_type("image/png")
+                .append_header((header::CACHE_CONTROL, "public, max-age=86400"))
+                .body(bytes)
+        },
+        Err(err) => server_error(err, "attachments thumbnail")
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn parse_range_bounded_and_open()
+    {
+        assert_eq!(parse_range("bytes=0-99", 1000), Ok(Some((0, 99))));
+        assert_eq!(parse_range("bytes=500-", 1000), Ok(Some((500, 999))));
+        assert_eq!(parse_range("bytes=900-5000", 1000), Ok(Some((900, 999))));
+    }
+
+    #[test]
+    fn parse_range_suffix()
+    {
+        assert_eq!(parse_range("bytes=-100", 1000), Ok(Some((900, 999))));
+        assert_eq!(parse_range("bytes=-5000", 1000), Ok(Some((0, 999))));
+        assert_eq!(parse_range("bytes=-0", 1000), Err(()));
+    }
+
+    #[test]
+    fn parse_range_unsatisfiable()
+    {
+        assert_eq!(parse_range("bytes=1000-", 1000), Err(()));
+        assert_eq!(parse_range("bytes=20-10", 1000), Err(()));
+        assert_eq!(parse_range("bytes=0-", 0), Err(()));
+    }
+
+    #[test]
+    fn parse_range_whole_object()
+    {
+        assert_eq!(parse_range("items=0-10", 1000), Ok(None));
+        assert_eq!(parse_range("bytes=0-10,20-30", 1000), Ok(None));
+        assert_eq!(parse_range("bytes=a-b", 1000), Ok(None));
+        assert_eq!(parse_range("bytes=10", 1000), Ok(None));
+    }
+}
@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use std::env;
+
+use actix_web::{web, HttpResponse, Responder};
+use serde::Serialize;
+use utoipa::ToSchema;
+use sqlx::mysql::MySqlPool;
+use log::error;
+
+use crate::i18n::Locale;
+
+/// An author of a RID as stored in FIO, ConnectionAuthorCountry and AUTHORxRID.
+/// `position` is the place in the contribution order, NULL if not entered yet.
+#[derive(Serialize, Debug, sqlx::FromRow, Clone, ToSchema)]
+pub struct Author
+{
+    #[serde(skip)]
+    rid: i32,
+    pub id: i32,
+    pub surname: String,
+    pub name: Option<String>,
+    pub patronymic: Option<String>,
+    pub country: Option<String>,
+    pub position: Option<i32>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NameFormat
+{
+    /// Иванов И. И.
+    Initials,
+    /// Иванов Иван Иванович
+    Full,
+    /// Ivanov I. I.
+    Latin
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct NameStyle
+{
+    format: NameFormat,
+    latin: bool
+}
+
+impl NameStyle
+{
+    /// Surname and initials in the original script, as in bibliographic references.
+    pub const INITIALS: NameStyle = NameStyle { format: NameFormat::Initials, latin: false };
+
+    /// Full names in the original script, for exported tables.
+    pub const FULL: NameStyle = NameStyle { format: NameFormat::Full, latin: false };
+
+    /// `AUTHOR_NAME_FORMAT` (`initials`, `full` or `latin`), initials by default.
+    /// English pages always get the Latin form of the configured format.
+    pub fn for_locale(locale: Locale) -> NameStyle
+    {
+        let format = match env::var("AUTHOR_NAME_FORMAT").as_deref()
+        {
+            Ok("full") => NameFormat::Full,
+            Ok("latin") => NameFormat::Latin,
+            _ => NameFormat::Initials
+        };
+
+        NameStyle { format, latin: format == NameFormat::Latin || locale == Locale::En }
+    }
+}
+
+/// ICAO Doc 9303 romanisation, used in Russian passports.
+fn transliterate(text: &str) -> String
+{
+    let mut latin = String::with_capacity(text.len());
+
+    for ch in text.chars()
+    {
+        let lower = ch.to_lowercase().next().unwrap_or(ch);
+        let romanised = match lower
+        {
+            'а' => "a", 'б' => "b", 'в' => "v", 'г' => "g", 'д' => "d",
+            'е' | 'ё' | 'э' => "e", 'ж' => "zh", 'з' => "z", 'и' | 'й' => "i",
+            'к' => "k", 'л' => "l", 'м' => "m", 'н' => "n", 'о' => "o",
+            'п' => "p", 'р' => "r", 'с' => "s", 'т' => "t", 'у' => "u",
+            'ф' => "f", 'х' => "kh", 'ц' => "ts", 'ч' => "ch", 'ш' => "sh",
+            'щ' => "shch", 'ъ' => "ie", 'ы' => "y", 'ь' => "", 'ю' => "iu",
+            'я' => "ia",
+            _ => {
+                latin.push(ch);
+                continue;
+            }
+        };
+
+        if ch != lower
+        {
+            let mut chars = romanised.chars();
+            if let Some(first) = chars.next()
+            {
+                latin.extend(first.to_uppercase());
+                latin.push_str(chars.as_str());
+            }
+        }
+        else
+        {
+            latin.push_str(romanised);
+        }
+    }
+
+    latin
+}
+
+fn initial(part: &str) -> Option<String>
+{
+    part.chars().next().map(|first| format!("{}.", first))
+}
+
+/// Name of one author in `style`. Missing first names and patronymics, common
+/// for foreign authors, are left out rather than dropping the author.
+pub fn name(author: &Author, style: NameStyle) -> String
+{
+    let parts = [Some(author.surname.as_str()), author.name.as_deref(), author.patronymic.as_deref()];
+    let parts: Vec<String> = parts
+        .into_iter()
+        .flatten()
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .map(|part| if style.latin { transliterate(part) } else { part.to_string() })
+        .collect();
+
+    let Some((surname, given)) = parts.split_first() else { return String::default() };
+
+    let given: Vec<String> = match style.format
+    {
+        NameFormat::Full => given.to_vec(),
+        NameFormat::Initials | NameFormat::Latin => given.iter().filter_map(|part| initial(part)).collect()
+    };
+
+    let mut name = surname.clone();
+    if !given.is_empty()
+    {
+        name.push(' ');
+        name.push_str(&given.join(" "));
+    }
+
+    name
+}
+
+/// Display form of one author: the name followed by the country.
+pub fn display(author: &Author, style: NameStyle) -> String
+{
+    let name = name(author, style);
+
+    match author.country.as_deref().filter(|country| !country.is_empty())
+    {
+        Some(country) => format!("{} ({})", name, country),
+        None => name
+    }
+}
+
+/// Authors of every RID in `rids`, in contribution order.
+pub async fn for_rids(pool: &MySqlPool, rids: &[i32]) -> Result<HashMap<i32, Vec<Author>>, sqlx::Error>
+{
+    let mut authors: HashMap<i32, Vec<Author>> = HashMap::new();
+
+    if rids.is_empty()
+    {
+        return Ok(authors);
+    }
+
+    let ids: Vec<String> = rids.iter().map(|id| id.to_string()).collect();
+
+    let rows: Vec<Author> = sqlx::query_as(&format!(r#"
+        select
+            AUTHORxRID.idRID as rid,
+            AUTHORxRID.idAuthor as id,
+            coalesce(FIO.surname, '') as surname,
+            FIO.name as name,
+            FIO.lastname as patronymic,
+            AuthorCountry.name as country,
+            AUTHORxRID.position as position
+        from AUTHORxRID
+
+        join ConnectionAuthorCountry on (ConnectionAuthorCountry.id = AUTHORxRID.idAuthor)
+        join FIO on (FIO.id = ConnectionAuthorCountry.idFIO)
+        left join AuthorCountry on (AuthorCountry.id = ConnectionAuthorCountry.idCountry)
+        where AUTHORxRID.idRID in ({})
+        order by AUTHORxRID.idRID, AUTHORxRID.position is null, AUTHORxRID.position, AUTHORxRID.idAuthor
+        "#, ids.join(", ")))
+        .fetch_all(pool)
+        .await?;
+
+    for row in rows
+    {
+        authors.entry(row.rid).or_default().push(row);
+    }
+
+    Ok(authors)
+}
+
+/// Comma-separated display list of a RID's authors.
+pub fn list(authors: Option<&Vec<Author>>, style: NameStyle) -> String
+{
+    authors
+        .map(|authors| authors.iter().map(|author| display(author, style)).collect::<Vec<String>>().join(", "))
+        .unwrap_or_default()
+}
+
+/// Structured authors of one RID as JSON.
+#[utoipa::path(
+    get,
+    path = "/rids/{id}/authors",
+    tag = "rids",
+    params(("id" = i32, Path, description = "RID id")),
+    responses(
+        (status = 200, description = "Authors in contribution order", body = Vec<Author>),
+        (status = 503, description = "Database error")
+    )
+)]
+pub async fn of_rid(id: web::Path<i32>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    match for_rids(pool.get_ref(), &[*id]).await
+    {
+        Ok(mut authors) => HttpResponse::Ok().json(authors.remove(&id).unwrap_or_default()),
+        Err(err) => {
+            error!("{}: rids/{}/authors", err, id);
+            HttpResponse::ServiceUnavailable()
+                .content_type("text/html; charset=utf-8")
+                .body(err.to_string())
+        }
+    }
+}
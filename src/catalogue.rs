@@ -0,0 +1,1253 @@
+use std::collections::HashMap;
+
+use actix_session::Session;
+use actix_web::{http::header, web, HttpResponse, Responder};
+use sqlx::mysql::{MySqlConnection, MySqlPool};
+use sqlx::{Connection, Row};
+use log::error;
+
+use crate::admin::{self, escape};
+use crate::attachments;
+use crate::authors::{self, NameStyle};
+use crate::import::{self, ImportedAuthor, Problem};
+use crate::staff::{self, Role, Staff};
+
+/// Records per list page.
+const PAGE_SIZE: i64 = 50;
+
+/// Session entry with the outcome of the last change, shown once on the list.
+const FLASH: &str = "flash";
+
+#[derive(Debug, Clone, Copy)]
+enum Kind
+{
+    Text { required: bool, max: usize },
+    LongText,
+    Integer { required: bool, min: i64, max: i64 },
+    Flag,
+    /// Stored value and its label.
+    Choice(&'static [(&'static str, &'static str)]),
+    /// Id of a row of `table`, shown as the `label` SQL expression.
+    Reference { table: &'static str, label: &'static str, required: bool }
+}
+
+#[derive(Debug)]
+struct Field
+{
+    column: &'static str,
+    label: &'static str,
+    kind: Kind
+}
+
+/// An editable table and how its screens look.
+#[derive(Debug)]
+struct Section
+{
+    slug: &'static str,
+    table: &'static str,
+    title: &'static str,
+    fields: &'static [Field],
+    /// List columns besides the id: header and SQL expression.
+    columns: &'static [(&'static str, &'static str)],
+    /// SQL expression the list search looks in.
+    search: &'static str,
+    /// SQL expression naming a record in confirmations.
+    label: &'static str,
+    /// Column whose values must differ, ignoring case.
+    unique: Option<&'static str>,
+    /// Columns of other tables pointing here; a record still in use cannot
+    /// be deleted.
+    references: &'static [(&'static str, &'static str, &'static str)],
+    order: &'static str,
+    /// RIDs are edited with their authors and versioned in RIDHistory.
+    rid: bool
+}
+
+const CATEGORIES: [(&str, &str); 3] = [
+    ("patent", "Патент"),
+    ("registration", "Свидетельство о регистрации"),
+    ("trademark", "Товарный знак")
+];
+
+const SECTIONS: [Section; 8] = [
+    Section {
+        slug: "rids",
+        table: "RID",
+        title: "РИД",
+        fields: &[
+            Field { column: "name", label: "Название", kind: Kind::Text { required: true, max: 1000 } },
+            Field { column: "name_en", label: "Название (англ.)", kind: Kind::Text { required: false, max: 1000 } },
+            Field { column: "description", label: "Описание", kind: Kind::LongText },
+            Field { column: "description_en", label: "Описание (англ.)", kind: Kind::LongText },
+            Field { column: "numPotent", label: "Номер (0, если ещё нет)", kind: Kind::Integer { required: true, min: 0, max: i32::MAX as i64 } },
+            Field { column: "idType", label: "Вид", kind: Kind::Reference { table: "Type", label: "name", required: true } },
+            Field { column: "idFaculty", label: "Факультет", kind: Kind::Reference { table: "Faculty", label: "name", required: false } },
+            Field { column: "idYear", label: "Год", kind: Kind::Reference { table: "Year", label: "year", required: false } },
+            Field { column: "idSubjectArea", label: "Предметная область", kind: Kind::Reference { table: "SubjectArea", label: "name", required: false } },
+            Field { column: "link", label: "Ссылка (пусто — запись в реестре)", kind: Kind::Text { required: false, max: 1000 } },
+            Field { column: "trl", label: "TRL", kind: Kind::Integer { required: false, min: 1, max: 9 } },
+            Field { column: "available", label: "Доступно для лицензирования", kind: Kind::Flag },
+            Field { column: "partnership_terms", label: "Условия партнёрства", kind: Kind::LongText }
+        ],
+        columns: &[
+            ("Название", "RID.name"),
+            ("Номер", "RID.numPotent"),
+            ("Вид", "(select name from Type where Type.id = RID.idType)"),
+            ("Факультет", "(select name from Faculty where Faculty.id = RID.idFaculty)"),
+            ("Год", "(select year from Year where Year.id = RID.idYear)")
+        ],
+        search: "concat_ws(' ', RID.name, RID.name_en, RID.numPotent)",
+        label: "RID.name",
+        unique: None,
+        references: &[("AGREEMENTxRID", "idRID", "договорах")],
+        order: "RID.id desc",
+        rid: true
+    },
+    Section {
+        slug: "faculties",
+        table: "Faculty",
+        title: "Факультеты",
+        fields: &[Field { column: "name", label: "Название", kind: Kind::Text { required: true, max: 255 } }],
+        columns: &[("Название", "Faculty.name"), ("РИД", "(select count(*) from RID where RID.idFaculty = Faculty.id)")],
+        search: "Faculty.name",
+        label: "Faculty.name",
+        unique: Some("name"),
+        references: &[("RID", "idFaculty", "РИД"), ("Staff", "idFaculty", "учётных записях редакторов"), ("Disclosure", "idFaculty", "заявках изобретателей")],
+        order: "Faculty.name",
+        rid: false
+    },
+    Section {
+        slug: "types",
+        table: "Type",
+        title: "Виды РИД",
+        fields: &[
+            Field { column: "name", label: "Название", kind: Kind::Text { required: true, max: 255 } },
+            Field { column: "category", label: "Охранный документ", kind: Kind::Choice(&CATEGORIES) },
+            Field { column: "description", label: "Определение", kind: Kind::LongText },
+            Field { column: "legal_basis", label: "Правовая основа", kind: Kind::Text { required: false, max: 255 } },
+            Field { column: "protection_term", label: "Срок охраны", kind: Kind::Text { required: false, max: 255 } },
+            Field { column: "protection_years", label: "Срок охраны, лет (для расчёта сроков)", kind: Kind::Integer { required: false, min: 1, max: 200 } },
+            Field { column: "icon", label: "Значок", kind: Kind::Text { required: false, max: 64 } },
+            Field { column: "registry_link", label: "Адрес записи в реестре с {number}", kind: Kind::Text { required: false, max: 512 } }
+        ],
+        columns: &[("Название", "Type.name"), ("РИД", "(select count(*) from RID where RID.idType = Type.id)")],
+        search: "Type.name",
+        label: "Type.name",
+        unique: Some("name"),
+        references: &[("RID", "idType", "РИД"), ("Disclosure", "idType", "заявках изобретателей")],
+        order: "Type.name",
+        rid: false
+    },
+    Section {
+        slug: "years",
+        table: "Year",
+        title: "Годы",
+        fields: &[Field { column: "year", label: "Год", kind: Kind::Integer { required: true, min: 1900, max: 2155 } }],
+        columns: &[("Год", "Year.year"), ("РИД", "(select count(*) from RID where RID.idYear = Year.id)")],
+        search: "Year.year",
+        label: "Year.year",
+        unique: Some("year"),
+        references: &[("RID", "idYear", "РИД")],
+        order: "Year.year desc",
+        rid: false
+    },
+    Section {
+        slug: "subject-areas",
+        table: "SubjectArea",
+        title: "Предметные области",
+        fields: &[Field { column: "name", label: "Название", kind: Kind::Text { required: true, max: 255 } }],
+        columns: &[("Название", "SubjectArea.name"), ("РИД", "(select count(*) from RID where RID.idSubjectArea = SubjectArea.id)")],
+        search: "SubjectArea.name",
+        label: "SubjectArea.name",
+        unique: Some("name"),
+        references: &[("RID", "idSubjectArea", "РИД"), ("BestRID", "idSubjectArea", "лучших РИД")],
+        order: "SubjectArea.name",
+        rid: false
+    },
+    Section {
+        slug: "names",
+        table: "FIO",
+        title: "ФИО авторов",
+        fields: &[
+            Field { column: "surname", label: "Фамилия", kind: Kind::Text { required: true, max: 255 } },
+            Field { column: "name", label: "Имя", kind: Kind::Text { required: false, max: 255 } },
+            Field { column: "lastname", label: "Отчество", kind: Kind::Text { required: false, max: 255 } }
+        ],
+        columns: &[
+            ("ФИО", "concat_ws(' ', FIO.surname, FIO.name, FIO.lastname)"),
+            ("Страны", "(select group_concat(AuthorCountry.name separator ', ') from ConnectionAuthorCountry join AuthorCountry on (AuthorCountry.id = ConnectionAuthorCountry.idCountry) where ConnectionAuthorCountry.idFIO = FIO.id)")
+        ],
+        search: "concat_ws(' ', FIO.surname, FIO.name, FIO.lastname)",
+        label: "concat_ws(' ', FIO.surname, FIO.name, FIO.lastname)",
+        unique: None,
+        references: &[("ConnectionAuthorCountry", "idFIO", "авторов")],
+        order: "FIO.surname, FIO.name, FIO.lastname",
+        rid: false
+    },
+    Section {
+        slug: "countries",
+        table: "AuthorCountry",
+        title: "Страны авторов",
+        fields: &[Field { column: "name", label: "Название", kind: Kind::Text { required: true, max: 255 } }],
+        columns: &[("Название", "AuthorCountry.name")],
+        search: "AuthorCountry.name",
+        label: "AuthorCountry.name",
+        unique: Some("name"),
+        references: &[("ConnectionAuthorCountry", "idCountry", "авторов")],
+        order: "AuthorCountry.name",
+        rid: false
+    },
+    Section {
+        slug: "best",
+        table: "BestRID",
+        title: "Лучшие РИД",
+        fields: &[
+            Field { column: "name", label: "Название", kind: Kind::Text { required: true, max: 1000 } },
+            Field { column: "notes", label: "Номинация", kind: Kind::LongText },
+            Field { column: "numPotent", label: "Номер РИД", kind: Kind::Integer { required: false, min: 0, max: i32::MAX as i64 } },
+            Field { column: "idSubjectArea", label: "Предметная область", kind: Kind::Reference { table: "SubjectArea", label: "name", required: false } }
+        ],
+        columns: &[("Название", "BestRID.name"), ("Номинация", "BestRID.notes")],
+        search: "BestRID.name",
+        label: "BestRID.name",
+        unique: None,
+        references: &[],
+        order: "BestRID.name",
+        rid: false
+    }
+];
+
+/// Column values of a record, `None` for NULL.
+type Values = HashMap<&'static str, Option<String>>;
+
+/// Options of every reference field: id and label.
+type Options = HashMap<&'static str, Vec<(String, String)>>;
+
+fn server_error(err: impl std::fmt::Display, page: &str) -> HttpResponse
+{
+    error!("{}: {}", err, page);
+    HttpResponse::ServiceUnavailable()
+        .content_type("text/html; charset=utf-8")
+        .body(err.to_string())
+}
+
+fn not_found() -> HttpResponse
+{
+    HttpResponse::NotFound()
+        .content_type("text/html; charset=utf-8")
+        .body("<h1>Error 404</h1>")
+}
+
+fn redirect(location: String) -> HttpResponse
+{
+    HttpResponse::SeeOther()
+        .append_header((header::LOCATION, location))
+        .finish()
+}
+
+fn section(slug: &str) -> Option<&'static Section>
+{
+    SECTIONS.iter().find(|section| section.slug == slug)
+}
+
+fn breadcrumbs(section: &Section) -> String
+{
+    format!(r#"<p><a href="/admin">Администрирование</a> / <a href="/admin/{}">{}</a></p>"#, section.slug, section.title)
+}
+
+/// Faculty of a RID as written in its values.
+fn faculty(values: &Values) -> Option<i32>
+{
+    values.get("idFaculty").cloned().flatten().and_then(|faculty| faculty.parse().ok())
+}
+
+/// Whether `staff` may change a record of the section with `values`, or
+/// add one when there are none.
+fn may_change(staff: &Staff, section: &Section, values: Option<&Values>) -> bool
+{
+    match (section.rid, values)
+    {
+        (false, _) => staff.may_write(),
+        (true, Some(values)) => staff.may_edit(faculty(values)),
+        (true, None) => staff.may_edit(staff.faculty)
+    }
+}
+
+/// Like `may_change()` for a stored record; `None` if there is no such record.
+async fn may_change_stored(pool: &MySqlPool, staff: &Staff, section: &Section, id: i32) -> Result<Option<bool>, sqlx::Error>
+{
+    match section.rid
+    {
+        true => Ok(staff::rid_faculty(pool, id).await?.map(|faculty| staff.may_edit(faculty))),
+        false => Ok(record_label(pool, section, id).await?.map(|_| staff.may_write()))
+    }
+}
+
+/// Editors only get their own faculty to choose from.
+async fn options(pool: &MySqlPool, section: &Section, staff: &Staff) -> Result<Options, sqlx::Error>
+{
+    let mut options = Options::new();
+
+    for field in section.fields
+    {
+        if let Kind::Reference { table, label, .. } = field.kind
+        {
+            let rows: Vec<(String, Option<String>)> = sqlx::query_as(&format!(
+                "select cast(id as char), cast({1} as char) from {0} order by {1}",
+                table, label
+            ))
+                .fetch_all(pool)
+                .await?;
+
+            let mut rows: Vec<(String, String)> = rows.into_iter().map(|(id, label)| (id, label.unwrap_or_default())).collect();
+            if section.rid && field.column == "idFaculty" && !staff.may_write()
+            {
+                let own = staff.faculty.map(|faculty| faculty.to_string());
+                rows.retain(|(id, _)| Some(id) == own.as_ref());
+            }
+
+            options.insert(field.column, rows);
+        }
+    }
+
+    Ok(options)
+}
+
+async fn stored(pool: &MySqlPool, section: &Section, id: i32) -> Result<Option<Values>, sqlx::Error>
+{
+    let columns: Vec<String> = section.fields.iter().map(|field| format!("cast({} as char)", field.column)).collect();
+
+    let row = sqlx::query(&format!("select {} from {} where id = ?", columns.join(", "), section.table))
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+
+    let Some(row) = row else { return Ok(None) };
+
+    let mut values = Values::new();
+    for (i, field) in section.fields.iter().enumerate()
+    {
+        values.insert(field.column, row.try_get(i)?);
+    }
+
+    Ok(Some(values))
+}
+
+async fn record_label(pool: &MySqlPool, section: &Section, id: i32) -> Result<Option<String>, sqlx::Error>
+{
+    let label: Option<Option<String>> = sqlx::query_scalar(&format!("select cast({} as char) from {} where id = ?", section.label, section.table))
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(label.map(Option::unwrap_or_default))
+}
+
+/// Authors of a RID as the form shows them: one per line, the id at the end.
+async fn stored_authors(pool: &MySqlPool, id: i32) -> Result<String, sqlx::Error>
+{
+    let authors = authors::for_rids(pool, &[id]).await?;
+
+    Ok(authors
+        .get(&id)
+        .map(|authors| authors
+            .iter()
+            .map(|author| format!("{} #{}", authors::display(author, NameStyle::FULL), author.id))
+            .collect::<Vec<String>>()
+            .join("\n"))
+        .unwrap_or_default())
+}
+
+/// Lines of the authors field: `Фамилия Имя Отчество (Страна)`, optionally
+/// followed by `#id` of an existing author.
+fn parse_authors(text: &str) -> Vec<ImportedAuthor>
+{
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (line, id) = match line.rsplit_once('#')
+            {
+                Some((rest, id)) if id.trim().parse::<i32>().is_ok() => (rest.trim(), id.trim().parse().ok()),
+                _ => (line, None)
+            };
+
+            let (names, country) = match line.strip_suffix(')').and_then(|line| line.rsplit_once('('))
+            {
+                Some((names, country)) => (names.trim(), Some(country.trim().to_string()).filter(|country| !country.is_empty())),
+                None => (line, None)
+            };
+
+            let mut names = names.split_whitespace();
+            ImportedAuthor {
+                id,
+                surname: names.next().unwrap_or_default().to_string(),
+                name: names.next().unwrap_or_default().to_string(),
+                patronymic: names.collect::<Vec<&str>>().join(" "),
+                country
+            }
+        })
+        .collect()
+}
+
+fn normalized_authors(text: &str) -> Vec<String>
+{
+    text.lines().map(|line| line.split_whitespace().collect::<Vec<&str>>().join(" ")).filter(|line| !line.is_empty()).collect()
+}
+
+fn submitted(section: &Section, form: &HashMap<String, String>) -> Values
+{
+    section.fields
+        .iter()
+        .map(|field| {
+            let value = form.get(field.column).map(|value| value.trim().to_string()).unwrap_or_default();
+            let value = match field.kind
+            {
+                Kind::Flag => Some(if value == "1" { "1" } else { "0" }.to_string()),
+                _ if value.is_empty() => None,
+                _ => Some(value)
+            };
+
+            (field.column, value)
+        })
+        .collect()
+}
+
+async fn validate(pool: &MySqlPool, section: &Section, id: Option<i32>, values: &Values, options: &Options) -> Result<Vec<String>, sqlx::Error>
+{
+    let mut errors = Vec::new();
+
+    for field in section.fields
+    {
+        let value = values.get(field.column).cloned().flatten();
+
+        match (field.kind, value.as_deref())
+        {
+            (Kind::Text { required: true, .. }, None) |
+            (Kind::Integer { required: true, .. }, None) |
+            (Kind::Reference { required: true, .. }, None) => errors.push(format!("«{}»: заполните поле", field.label)),
+            (Kind::Text { max, .. }, Some(value)) if value.chars().count() > max => {
+                errors.push(format!("«{}»: не длиннее {} символов", field.label, max));
+            },
+            (Kind::Integer { min, max, .. }, Some(value)) if !value.parse::<i64>().is_ok_and(|number| (min..=max).contains(&number)) => {
+                errors.push(format!("«{}»: нужно целое число от {} до {}", field.label, min, max));
+            },
+            (Kind::Choice(choices), Some(value)) if !choices.iter().any(|(choice, _)| *choice == value) => {
+                errors.push(format!("«{}»: выберите значение из списка", field.label));
+            },
+            (Kind::Reference { .. }, Some(value)) if !options.get(field.column).is_some_and(|options| options.iter().any(|(id, _)| id == value)) => {
+                errors.push(format!("«{}»: выберите значение из списка", field.label));
+            },
+            _ => {}
+        }
+    }
+
+    if let Some(column) = section.unique
+        && let Some(Some(value)) = values.get(column)
+    {
+        let taken: i64 = sqlx::query_scalar(&format!(
+            "select count(*) from {} where lower({}) = lower(?) and id <> ?",
+            section.table, column
+        ))
+            .bind(value)
+            .bind(id.unwrap_or_default())
+            .fetch_one(pool)
+            .await?;
+
+        if taken > 0
+        {
+            errors.push(format!("«{}» уже есть в списке", value));
+        }
+    }
+
+    Ok(errors)
+}
+
+/// A value as staff read it: labels of references and choices, да/нет for flags.
+fn display(field: &Field, value: Option<&str>, options: &Options) -> String
+{
+    let Some(value) = value else { return "—".to_string() };
+
+    let text = match field.kind
+    {
+        Kind::Flag => if value == "1" { "да" } else { "нет" }.to_string(),
+        Kind::Choice(choices) => choices.iter().find(|(choice, _)| *choice == value).map(|(_, label)| label.to_string()).unwrap_or(value.to_string()),
+        Kind::Reference { .. } => options
+            .get(field.column)
+            .and_then(|options| options.iter().find(|(id, _)| id == value))
+            .map(|(_, label)| label.clone())
+            .unwrap_or(value.to_string()),
+        _ => value.to_string()
+    };
+
+    escape(&text).replace('\n', "<br>")
+}
+
+fn input(field: &Field, value: Option<&str>, options: &Options) -> String
+{
+    let value = value.unwrap_or_default();
+
+    match field.kind
+    {
+        Kind::Text { required, max } => format!(
+            r#"<input name="{}" value="{}" maxlength="{}" size="80"{}>"#,
+            field.column, escape(value), max, if required { " required" } else { "" }
+        ),
+        Kind::LongText => format!(r#"<textarea name="{}" rows="5" cols="80">{}</textarea>"#, field.column, escape(value)),
+        Kind::Integer { required, min, max } => format!(
+            r#"<input type="number" name="{}" value="{}" min="{}" max="{}"{}>"#,
+            field.column, escape(value), min, max, if required { " required" } else { "" }
+        ),
+        Kind::Flag => format!(r#"<input type="checkbox" name="{}" value="1"{}>"#, field.column, if value == "1" { " checked" } else { "" }),
+        Kind::Choice(choices) => {
+            let items: Vec<String> = choices
+                .iter()
+                .map(|(choice, label)| format!(r#"<option value="{}"{}>{}</option>"#, choice, if *choice == value { " selected" } else { "" }, label))
+                .collect();
+
+            format!(r#"<select name="{}"><option value="">—</option>{}</select>"#, field.column, items.concat())
+        },
+        Kind::Reference { required, .. } => {
+            let items: Vec<String> = options
+                .get(field.column)
+                .map(Vec::as_slice)
+                .unwrap_or_default()
+                .iter()
+                .map(|(id, label)| format!(r#"<option value="{}"{}>{}</option>"#, id, if id == value { " selected" } else { "" }, escape(label)))
+                .collect();
+
+            format!(
+                r#"<select name="{}"{}><option value="">—</option>{}</select>"#,
+                field.column, if required { " required" } else { "" }, items.concat()
+            )
+        }
+    }
+}
+
+fn action(section: &Section, id: Option<i32>) -> String
+{
+    match id
+    {
+        Some(id) => format!("/admin/{}/{}/edit", section.slug, id),
+        None => format!("/admin/{}/new", section.slug)
+    }
+}
+
+/// `extra` goes under the form, e.g. the attachments of a RID.
+fn form_page(section: &Section, id: Option<i32>, values: &Values, authors: &str, options: &Options, errors: &[String], extra: &str) -> HttpResponse
+{
+    let mut rows: Vec<String> = section.fields
+        .iter()
+        .map(|field| format!(
+            r#"<p><label>{}<br>{}</label></p>"#,
+            field.label,
+            input(field, values.get(field.column).cloned().flatten().as_deref(), options)
+        ))
+        .collect();
+
+    if section.rid
+    {
+        rows.push(format!(
+            r#"<p><label>Авторы — по одному в строке: Фамилия Имя Отчество (Страна); #id в конце выбирает автора из базы<br><textarea name="authors" rows="6" cols="80">{}</textarea></label></p>"#,
+            escape(authors)
+        ));
+    }
+
+    let errors: Vec<String> = errors.iter().map(|error| format!("<li>{}</li>", escape(error))).collect();
+
+    admin::page(
+        match id { Some(_) => "Изменение записи", None => "Новая запись" },
+        &format!(
+            r#"{}{}<form method="post" action="{}">{}<p><button type="submit" name="action" value="check">Проверить</button></p></form>{}"#,
+            breadcrumbs(section),
+            if errors.is_empty() { String::default() } else { format!(r#"<ul style="color: #b00020;">{}</ul>"#, errors.concat()) },
+            action(section, id),
+            rows.concat(),
+            extra
+        )
+    )
+}
+
+fn hidden(section: &Section, values: &Values, authors: &str) -> String
+{
+    let mut inputs: Vec<String> = section.fields
+        .iter()
+        .map(|field| format!(
+            r#"<input type="hidden" name="{}" value="{}">"#,
+            field.column,
+            escape(values.get(field.column).cloned().flatten().as_deref().unwrap_or_default())
+        ))
+        .collect();
+
+    if section.rid
+    {
+        inputs.push(format!(r#"<input type="hidden" name="authors" value="{}">"#, escape(authors)));
+    }
+
+    inputs.concat()
+}
+
+/// The record side by side with the stored one, to be saved or corrected.
+fn confirm_page(section: &Section, id: Option<i32>, before: Option<&Values>, values: &Values, authors: (&str, &str), options: &Options) -> HttpResponse
+{
+    let mut changed = 0;
+    let mut rows: Vec<Vec<String>> = Vec::new();
+
+    for field in section.fields
+    {
+        let old = before.and_then(|before| before.get(field.column).cloned().flatten());
+        let new = values.get(field.column).cloned().flatten();
+        let differs = before.is_none() || old != new;
+        changed += usize::from(differs);
+
+        let new = display(field, new.as_deref(), options);
+        rows.push(vec![
+            field.label.to_string(),
+            display(field, old.as_deref(), options),
+            if differs { format!("<b>{}</b>", new) } else { new }
+        ]);
+    }
+
+    if section.rid
+    {
+        let differs = before.is_none() || normalized_authors(authors.0) != normalized_authors(authors.1);
+        changed += usize::from(differs);
+
+        let new = escape(authors.1).replace('\n', "<br>");
+        rows.push(vec![
+            "Авторы".to_string(),
+            escape(authors.0).replace('\n', "<br>"),
+            if differs { format!("<b>{}</b>", new) } else { new }
+        ]);
+    }
+
+    let header: &[&str] = match before
+    {
+        Some(_) => &["Поле", "Сейчас", "Станет"],
+        None => &["Поле", "", "Значение"]
+    };
+
+    let buttons = if changed == 0
+    {
+        format!(r#"<p>Изменений нет. <a href="/admin/{}">К списку</a></p>"#, section.slug)
+    }
+    else
+    {
+        format!(
+            r#"<p>Проверьте {} и сохраните.</p><form method="post" action="{}">{}<button type="submit" name="action" value="save">Сохранить</button> <button type="submit" name="action" value="back">Исправить</button></form>"#,
+            if before.is_some() { "изменения (выделены)" } else { "запись" },
+            action(section, id),
+            hidden(section, values, authors.1)
+        )
+    };
+
+    admin::page("Подтверждение", &format!("{}{}{}", breadcrumbs(section), admin::table(header, &rows), buttons))
+}
+
+/// Writes the record (and the authors of a RID) in the open transaction,
+/// returns its id and notes on created authors.
+async fn save(conn: &mut MySqlConnection, section: &Section, id: Option<i32>, before: Option<&Values>, values: &Values, authors: Option<&str>) -> Result<(i64, Vec<String>), Problem>
+{
+    let changed: Vec<&Field> = section.fields
+        .iter()
+        .filter(|field| before.is_none_or(|before| before.get(field.column) != values.get(field.column)))
+        .collect();
+
+    let id: i64 = match id
+    {
+        Some(id) => {
+            if !changed.is_empty()
+            {
+                let assignments: Vec<String> = changed.iter().map(|field| format!("{} = ?", field.column)).collect();
+                let sql = format!("update {} set {} where id = ?", section.table, assignments.join(", "));
+                let mut update = sqlx::query(&sql);
+                for field in &changed
+                {
+                    update = update.bind(values.get(field.column).cloned().flatten());
+                }
+                update.bind(id).execute(&mut *conn).await?;
+            }
+
+            id.into()
+        },
+        None => {
+            let columns: Vec<&str> = section.fields.iter().map(|field| field.column).collect();
+            let sql = format!(
+                "insert into {} ({}) values ({})",
+                section.table,
+                columns.join(", "),
+                vec!["?"; columns.len()].join(", ")
+            );
+            let mut insert = sqlx::query(&sql);
+            for field in section.fields
+            {
+                insert = insert.bind(values.get(field.column).cloned().flatten());
+            }
+
+            insert.execute(&mut *conn).await?.last_insert_id() as i64
+        }
+    };
+
+    let mut notes = Vec::new();
+
+    if let Some(authors) = authors
+    {
+        set_authors(conn, id, authors, &mut notes).await?;
+    }
+
+    Ok((id, notes))
+}
+
+/// Replaces the authors of `rid` with the lines of `authors` (see
+/// `parse_authors()`), finding or creating each of them; notes name the
+/// created ones.
+pub async fn set_authors(conn: &mut MySqlConnection, rid: i64, authors: &str, notes: &mut Vec<String>) -> Result<(), Problem>
+{
+    let mut ids = Vec::new();
+    for author in parse_authors(authors)
+    {
+        if author.id.is_none() && author.surname.is_empty()
+        {
+            continue;
+        }
+
+        let author_id = import::author(&mut *conn, &author, notes).await?;
+        if ids.contains(&author_id)
+        {
+            return Err(Problem::Invalid(format!("автор {} указан дважды", author_id)));
+        }
+        ids.push(author_id);
+    }
+
+    sqlx::query("delete from AUTHORxRID where idRID = ?").bind(rid).execute(&mut *conn).await?;
+    for (position, author) in ids.iter().enumerate()
+    {
+        sqlx::query("insert into AUTHORxRID (idRID, idAuthor, position) values (?, ?, ?)")
+            .bind(rid)
+            .bind(author)
+            .bind(position as i32 + 1)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(())
+}
+
+pub async fn list(
+    staff: Staff,
+    session: Session,
+    slug: web::Path<String>,
+    query: web::Query<HashMap<String, String>>,
+    pool: web::Data<MySqlPool>
+) -> impl Responder
+{
+    let Some(section) = section(&slug) else { return not_found() };
+
+    let search = query.get("q").map(|search| search.trim().to_string()).filter(|search| !search.is_empty());
+    let page: i64 = query.get("page").and_then(|page| page.parse().ok()).unwrap_or(0).max(0);
+
+    // Editors see the RIDs they maintain.
+    let own = (section.rid && staff.role == Role::Editor).then_some(staff.faculty.unwrap_or_default());
+
+    let mut conditions = Vec::new();
+    if search.is_some()
+    {
+        conditions.push(format!("{} like ?", section.search));
+    }
+    if own.is_some()
+    {
+        conditions.push("RID.idFaculty = ?".to_string());
+    }
+
+    let columns: Vec<String> = section.columns.iter().map(|(_, column)| format!("cast({} as char)", column)).collect();
+    let sql = format!(
+        "select cast(id as char), {} from {} {} order by {} limit {} offset {}",
+        columns.join(", "),
+        section.table,
+        if conditions.is_empty() { String::default() } else { format!("where {}", conditions.join(" and ")) },
+        section.order,
+        PAGE_SIZE + 1,
+        page * PAGE_SIZE
+    );
+
+    let mut rows = sqlx::query(&sql);
+    if let Some(search) = &search
+    {
+        rows = rows.bind(format!("%{}%", search));
+    }
+    if let Some(faculty) = own
+    {
+        rows = rows.bind(faculty);
+    }
+
+    let editable = may_change(&staff, section, None);
+
+    let rows = match rows.fetch_all(pool.get_ref()).await
+    {
+        Ok(rows) => rows,
+        Err(err) => return server_error(err, &format!("admin/{}", section.slug))
+    };
+
+    let more = rows.len() as i64 > PAGE_SIZE;
+    let mut cells: Vec<Vec<String>> = Vec::new();
+
+    for row in rows.iter().take(PAGE_SIZE as usize)
+    {
+        let id: String = row.try_get(0).unwrap_or_default();
+        let mut line = vec![id.clone()];
+
+        for i in 0..section.columns.len()
+        {
+            let value: Option<String> = row.try_get(i + 1).unwrap_or_default();
+            line.push(escape(&value.unwrap_or_default()));
+        }
+
+        let mut links = Vec::new();
+        if editable
+        {
+            links.push(format!(r#"<a href="/admin/{}/{}/edit">изменить</a>"#, section.slug, id));
+            links.push(format!(r#"<a href="/admin/{}/{}/delete">удалить</a>"#, section.slug, id));
+        }
+        if section.rid
+        {
+            links.push(format!(r#"<a href="/admin/rids/{}/status">статус</a>"#, id));
+            links.push(format!(r#"<a href="/admin/rids/{}/history">история</a>"#, id));
+            links.push(format!(r#"<a href="/admin/rids/{}/workflow">регистрация</a>"#, id));
+        }
+        line.push(links.join(" | "));
+
+        cells.push(line);
+    }
+
+    let mut header = vec!["ID"];
+    header.extend(section.columns.iter().map(|(header, _)| *header));
+    header.push("");
+
+    let flash = session.get::<String>(FLASH).unwrap_or(None);
+    session.remove(FLASH);
+
+    // Pages are switched by forms, so the search text needs no URL encoding.
+    let page_button = |page: i64, label: &str| format!(
+        r#"<form method="get" action="/admin/{}" style="display: inline;"><input type="hidden" name="q" value="{}"><button type="submit" name="page" value="{}">{}</button></form>"#,
+        section.slug, escape(search.as_deref().unwrap_or_default()), page, label
+    );
+    let mut pages = Vec::new();
+    if page > 0
+    {
+        pages.push(page_button(page - 1, "← назад"));
+    }
+    if more
+    {
+        pages.push(page_button(page + 1, "дальше →"));
+    }
+
+    admin::page(section.title, &format!(
+        r#"<p><a href="/admin">Администрирование</a></p>{}
+        <form method="get" action="/admin/{}"><input name="q" value="{}" size="40"> <button type="submit">Найти</button> {}</form>
+        {}<p>{}</p>"#,
+        flash.map(|flash| format!(r#"<p style="color: #1b5e20;">{}</p>"#, escape(&flash))).unwrap_or_default(),
+        section.slug,
+        escape(search.as_deref().unwrap_or_default()),
+        if editable { format!(r#"<a href="/admin/{}/new">Добавить</a>"#, section.slug) } else { String::default() },
+        admin::table(&header, &cells),
+        pages.join(" ")
+    ))
+}
+
+pub async fn create_form(staff: Staff, slug: web::Path<String>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let Some(section) = section(&slug) else { return not_found() };
+
+    if !may_change(&staff, section, None)
+    {
+        return staff.deny(&format!("admin/{}/new", section.slug));
+    }
+
+    let options = match options(pool.get_ref(), section, &staff).await
+    {
+        Ok(options) => options,
+        Err(err) => return server_error(err, &format!("admin/{}/new", section.slug))
+    };
+
+    let values: Values = section.fields
+        .iter()
+        .map(|field| (field.column, match field.kind { Kind::Flag => Some("0".to_string()), _ => None }))
+        .collect();
+
+    form_page(section, None, &values, "", &options, &[], "")
+}
+
+pub async fn edit_form(staff: Staff, path: web::Path<(String, i32)>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let (slug, id) = path.into_inner();
+    let Some(section) = section(&slug) else { return not_found() };
+    let page = format!("admin/{}/{}/edit", section.slug, id);
+
+    let values = match stored(pool.get_ref(), section, id).await
+    {
+        Ok(Some(values)) => values,
+        Ok(None) => return not_found(),
+        Err(err) => return server_error(err, &page)
+    };
+
+    if !may_change(&staff, section, Some(&values))
+    {
+        return staff.deny(&page);
+    }
+
+    let authors = match section.rid
+    {
+        true => match stored_authors(pool.get_ref(), id).await
+        {
+            Ok(authors) => authors,
+            Err(err) => return server_error(err, &page)
+        },
+        false => String::default()
+    };
+
+    let files = match section.rid
+    {
+        true => match attachments::panel(pool.get_ref(), id).await
+        {
+            Ok(files) => files,
+            Err(err) => return server_error(err, &page)
+        },
+        false => String::default()
+    };
+
+    match options(pool.get_ref(), section, &staff).await
+    {
+        Ok(options) => form_page(section, Some(id), &values, &authors, &options, &[], &files),
+        Err(err) => server_error(err, &page)
+    }
+}
+
+pub async fn create(
+    staff: Staff,
+    session: Session,
+    slug: web::Path<String>,
+    form: web::Form<HashMap<String, String>>,
+    pool: web::Data<MySqlPool>
+) -> impl Responder
+{
+    let Some(section) = section(&slug) else { return not_found() };
+
+    submit(staff, session, section, None, form.into_inner(), pool.get_ref()).await
+}
+
+pub async fn edit(
+    staff: Staff,
+    session: Session,
+    path: web::Path<(String, i32)>,
+    form: web::Form<HashMap<String, String>>,
+    pool: web::Data<MySqlPool>
+) -> impl Responder
+{
+    let (slug, id) = path.into_inner();
+    let Some(section) = section(&slug) else { return not_found() };
+
+    submit(staff, session, section, Some(id), form.into_inner(), pool.get_ref()).await
+}
+
+/// The form goes through three steps: `check` validates it and shows the
+/// confirmation, `back` returns to the form, `save` writes the record.
+async fn submit(staff: Staff, session: Session, section: &'static Section, id: Option<i32>, form: HashMap<String, String>, pool: &MySqlPool) -> HttpResponse
+{
+    let page = action(section, id);
+
+    let options = match options(pool, section, &staff).await
+    {
+        Ok(options) => options,
+        Err(err) => return server_error(err, &page)
+    };
+
+    let (before, authors_before) = match id
+    {
+        Some(id) => {
+            let before = match stored(pool, section, id).await
+            {
+                Ok(Some(before)) => before,
+                Ok(None) => return not_found(),
+                Err(err) => return server_error(err, &page)
+            };
+
+            if !may_change(&staff, section, Some(&before))
+            {
+                return staff.deny(&page);
+            }
+
+            let authors = match section.rid
+            {
+                true => match stored_authors(pool, id).await
+                {
+                    Ok(authors) => authors,
+                    Err(err) => return server_error(err, &page)
+                },
+                false => String::default()
+            };
+
+            (Some(before), authors)
+        },
+        None => (None, String::default())
+    };
+
+    let values = submitted(section, &form);
+    let authors = form.get("authors").map(|authors| authors.trim().to_string()).unwrap_or_default();
+
+    // An editor can neither add a RID to another faculty nor move one there.
+    if !may_change(&staff, section, Some(&values))
+    {
+        return staff.deny(&page);
+    }
+
+    if form.get("action").map(String::as_str) == Some("back")
+    {
+        return form_page(section, id, &values, &authors, &options, &[], "");
+    }
+
+    let mut errors = match validate(pool, section, id, &values, &options).await
+    {
+        Ok(errors) => errors,
+        Err(err) => return server_error(err, &page)
+    };
+
+    if section.rid
+    {
+        for author in parse_authors(&authors)
+        {
+            if author.id.is_none() && author.surname.is_empty()
+            {
+                errors.push("«Авторы»: в строке нет фамилии".to_string());
+            }
+        }
+    }
+
+    if !errors.is_empty()
+    {
+        return form_page(section, id, &values, &authors, &options, &errors, "");
+    }
+
+    if form.get("action").map(String::as_str) != Some("save")
+    {
+        return confirm_page(section, id, before.as_ref(), &values, (&authors_before, &authors), &options);
+    }
+
+    let changed_authors = section.rid.then_some(authors.as_str()).filter(|authors| id.is_none() || normalized_authors(authors) != normalized_authors(&authors_before));
+
+    let mut conn = match pool.acquire().await
+    {
+        Ok(conn) => conn,
+        Err(err) => return server_error(err, &page)
+    };
+
+    // Names the change in the RID history (see migrations/0009_rid_history.sql).
+    if section.rid
+        && let Err(err) = sqlx::query("set @audit_actor = ?").bind(&staff.name).execute(&mut *conn).await
+    {
+        return server_error(err, &page);
+    }
+
+    let saved = match conn.begin().await
+    {
+        Ok(mut tx) => match save(&mut tx, section, id, before.as_ref(), &values, changed_authors).await
+        {
+            Ok(saved) => match tx.commit().await
+            {
+                Ok(()) => Ok(saved),
+                Err(err) => Err(Problem::Database(err))
+            },
+            Err(problem) => {
+                let _ = tx.rollback().await;
+                Err(problem)
+            }
+        },
+        Err(err) => Err(Problem::Database(err))
+    };
+
+    if section.rid
+        && let Err(err) = sqlx::query("set @audit_actor = null").execute(&mut *conn).await
+    {
+        error!("{}: {}", err, page);
+        conn.close_on_drop();
+    }
+
+    match saved
+    {
+        Ok((saved, notes)) => {
+            let mut message = format!("Сохранено: запись {}", saved);
+            if !notes.is_empty()
+            {
+                message.push_str(&format!(" ({})", notes.join("; ")));
+            }
+            let _ = session.insert(FLASH, message);
+
+            redirect(format!("/admin/{}", section.slug))
+        },
+        Err(Problem::Invalid(message)) => form_page(section, id, &values, &authors, &options, &[message], ""),
+        Err(Problem::Database(err)) => server_error(err, &page)
+    }
+}
+
+/// Why a record cannot be deleted, if anything still uses it.
+async fn in_use(pool: &MySqlPool, section: &Section, id: i32) -> Result<Vec<String>, sqlx::Error>
+{
+    let mut uses = Vec::new();
+
+    for (table, column, what) in section.references
+    {
+        let count: i64 = sqlx::query_scalar(&format!("select count(*) from {} where {} = ?", table, column))
+            .bind(id)
+            .fetch_one(pool)
+            .await?;
+
+        if count > 0
+        {
+            uses.push(format!("используется в {} {}", count, what));
+        }
+    }
+
+    if section.rid
+    {
+        // Files in the attachment store are removed one by one on the RID page.
+        let attachments: i64 = sqlx::query_scalar("select count(*) from Attachment where idRID = ?")
+            .bind(id)
+            .fetch_one(pool)
+            .await?;
+
+        if attachments > 0
+        {
+            uses.push(format!("сначала удалите вложения ({})", attachments));
+        }
+    }
+
+    Ok(uses)
+}
+
+pub async fn delete_form(staff: Staff, path: web::Path<(String, i32)>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let (slug, id) = path.into_inner();
+    let Some(section) = section(&slug) else { return not_found() };
+    let page = format!("admin/{}/{}/delete", section.slug, id);
+
+    match may_change_stored(pool.get_ref(), &staff, section, id).await
+    {
+        Ok(Some(true)) => {},
+        Ok(Some(false)) => return staff.deny(&page),
+        Ok(None) => return not_found(),
+        Err(err) => return server_error(err, &page)
+    }
+
+    let label = match record_label(pool.get_ref(), section, id).await
+    {
+        Ok(Some(label)) => label,
+        Ok(None) => return not_found(),
+        Err(err) => return server_error(err, &page)
+    };
+
+    let uses = match in_use(pool.get_ref(), section, id).await
+    {
+        Ok(uses) => uses,
+        Err(err) => return server_error(err, &page)
+    };
+
+    let content = if uses.is_empty()
+    {
+        format!(
+            r#"<p>Удалить «{}»?{}</p><form method="post" action="/admin/{}/{}/delete"><button type="submit">Удалить</button> <a href="/admin/{}">Отмена</a></form>"#,
+            escape(&label),
+            if section.rid { " Статусы и авторство будут удалены, прежние версии останутся в истории." } else { "" },
+            section.slug,
+            id,
+            section.slug
+        )
+    }
+    else
+    {
+        format!(
+            r#"<p>«{}» нельзя удалить: {}.</p><p><a href="/admin/{}">К списку</a></p>"#,
+            escape(&label),
+            uses.join(", "),
+            section.slug
+        )
+    };
+
+    admin::page("Удаление", &format!("{}{}", breadcrumbs(section), content))
+}
+
+pub async fn delete(staff: Staff, session: Session, path: web::Path<(String, i32)>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let (slug, id) = path.into_inner();
+    let Some(section) = section(&slug) else { return not_found() };
+    let page = format!("admin/{}/{}/delete", section.slug, id);
+
+    match may_change_stored(pool.get_ref(), &staff, section, id).await
+    {
+        Ok(Some(true)) => {},
+        Ok(Some(false)) => return staff.deny(&page),
+        Ok(None) => return not_found(),
+        Err(err) => return server_error(err, &page)
+    }
+
+    let label = match record_label(pool.get_ref(), section, id).await
+    {
+        Ok(Some(label)) => label,
+        Ok(None) => return not_found(),
+        Err(err) => return server_error(err, &page)
+    };
+
+    match in_use(pool.get_ref(), section, id).await
+    {
+        Ok(uses) if uses.is_empty() => {},
+        Ok(_) => return redirect(format!("/admin/{}/{}/delete", section.slug, id)),
+        Err(err) => return server_error(err, &page)
+    }
+
+    let mut conn = match pool.acquire().await
+    {
+        Ok(conn) => conn,
+        Err(err) => return server_error(err, &page)
+    };
+
+    if section.rid
+        && let Err(err) = sqlx::query("set @audit_actor = ?").bind(&staff.name).execute(&mut *conn).await
+    {
+        return server_error(err, &page);
+    }
+
+    let deleted = delete_record(&mut conn, section, id).await;
+
+    if section.rid
+        && let Err(err) = sqlx::query("set @audit_actor = null").execute(&mut *conn).await
+    {
+        error!("{}: {}", err, page);
+        conn.close_on_drop();
+    }
+
+    match deleted
+    {
+        Ok(()) => {
+            let _ = session.insert(FLASH, format!("Удалено: {}", label));
+            redirect(format!("/admin/{}", section.slug))
+        },
+        Err(err) => server_error(err, &page)
+    }
+}
+
+async fn delete_record(conn: &mut MySqlConnection, section: &Section, id: i32) -> Result<(), sqlx::Error>
+{
+    let mut tx = conn.begin().await?;
+
+    if section.rid
+    {
+        sqlx::query("delete from DuplicateCandidate where status = 'open' and (idRID1 = ? or idRID2 = ?)")
+            .bind(id)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("delete from AUTHORxRID where idRID = ?").bind(id).execute(&mut *tx).await?;
+    }
+
+    sqlx::query(&format!("delete from {} where id = ?", section.table)).bind(id).execute(&mut *tx).await?;
+
+    tx.commit().await
+}
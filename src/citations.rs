@@ -0,0 +1,446 @@
+use std::collections::HashMap;
+
+use actix_web::{http::header, web, HttpResponse, Responder};
+use serde_json::{json, Value};
+use sqlx::mysql::MySqlPool;
+use log::error;
+
+use crate::authors::{self, Author, NameStyle};
+use crate::handlers;
+use crate::registry;
+
+/// Right holder named in the references; every RID in the base belongs to the university.
+pub const HOLDER: &str = "Российский университет дружбы народов имени Патриса Лумумбы";
+
+#[derive(Debug, sqlx::FromRow)]
+struct CitedRid
+{
+    id: i32,
+    name: String,
+    number: i32,
+    rid_type: Option<String>,
+    category: Option<String>,
+    year: Option<String>,
+    link: Option<String>,
+    registry_link: Option<String>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format
+{
+    Gost,
+    Bibtex,
+    Ris,
+    Csl
+}
+
+impl Format
+{
+    fn from_query(query: &HashMap<String, String>) -> Option<Format>
+    {
+        match query.get("format").map(|format| format.as_str()).unwrap_or("gost")
+        {
+            "gost" => Some(Format::Gost),
+            "bibtex" => Some(Format::Bibtex),
+            "ris" => Some(Format::Ris),
+            "csl" => Some(Format::Csl),
+            _ => None
+        }
+    }
+
+    fn content_type(self) -> &'static str
+    {
+        match self
+        {
+            Format::Gost => "text/plain; charset=utf-8",
+            Format::Bibtex => "application/x-bibtex; charset=utf-8",
+            Format::Ris => "application/x-research-info-systems; charset=utf-8",
+            Format::Csl => "application/vnd.citationstyles.csl+json; charset=utf-8"
+        }
+    }
+
+    fn extension(self) -> &'static str
+    {
+        match self
+        {
+            Format::Gost => "txt",
+            Format::Bibtex => "bib",
+            Format::Ris => "ris",
+            Format::Csl => "json"
+        }
+    }
+}
+
+/// Document a RID is registered with, as it opens a GOST R 7.0.100 reference.
+fn document(rid: &CitedRid) -> String
+{
+    let rid_type = rid.rid_type.as_deref().unwrap_or_default();
+    let lower = rid_type.to_lowercase();
+
+    match rid.category.as_deref()
+    {
+        Some("patent") => "Патент".to_string(),
+        Some("registration") if lower.contains("программ") => "Свидетельство о государственной регистрации программы для ЭВМ".to_string(),
+        Some("registration") if lower.contains("баз") => "Свидетельство о государственной регистрации базы данных".to_string(),
+        Some("registration") => "Свидетельство о государственной регистрации".to_string(),
+        Some("trademark") => "Свидетельство на товарный знак".to_string(),
+        _ => rid_type.to_string()
+    }
+}
+
+fn link(rid: &CitedRid) -> Option<String>
+{
+    registry::link(rid.link.as_deref(), rid.registry_link.as_deref(), rid.number)
+}
+
+/// Surname followed by the first name and patronymic, for the formats that
+/// keep the two apart.
+fn given(author: &Author) -> String
+{
+    [author.name.as_deref(), author.patronymic.as_deref()]
+        .into_iter()
+        .flatten()
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+fn inverted(author: &Author) -> String
+{
+    match given(author).as_str()
+    {
+        "" => author.surname.clone(),
+        given => format!("{}, {}", author.surname, given)
+    }
+}
+
+/// GOST R 7.0.100-2018 reference, e.g.
+/// «Патент № 2700000 Российская Федерация. Название : опубл. 2019 / Иванов И. И. ; правообладатель ...».
+fn gost(rid: &CitedRid, authors: &[Author]) -> String
+{
+    let mut reference = format!("{} № {} Российская Федерация. {}", document(rid), rid.number, rid.name.trim());
+
+    if let Some(year) = &rid.year
+    {
+        reference.push_str(&format!(" : опубл. {}", year));
+    }
+
+    let names: Vec<String> = authors.iter().map(|author| authors::name(author, NameStyle::INITIALS)).collect();
+    if !names.is_empty()
+    {
+        reference.push_str(&format!(" / {}", names.join(", ")));
+        reference.push_str(&format!(" ; правообладатель {}", HOLDER));
+    }
+    else
+    {
+        reference.push_str(&format!(" / правообладатель {}", HOLDER));
+    }
+
+    reference.push_str(". – Текст : электронный");
+
+    if let Some(link) = link(rid)
+    {
+        reference.push_str(&format!(". – URL: {}", link));
+    }
+
+    reference.push('.');
+    reference
+}
+
+fn bibtex_escape(text: &str) -> String
+{
+    let mut escaped = String::with_capacity(text.len());
+
+    for ch in text.trim().chars()
+    {
+        match ch
+        {
+            '{' | '}' | '&' | '%' | '$' | '#' | '_' => {
+                escaped.push('\\');
+                escaped.push(ch);
+            },
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            _ => escaped.push(ch)
+        }
+    }
+
+    escaped
+}
+
+fn bibtex(rid: &CitedRid, authors: &[Author]) -> String
+{
+    let entry = match rid.category.as_deref()
+    {
+        Some("patent") => "patent",
+        Some("registration") if rid.rid_type.as_deref().unwrap_or_default().to_lowercase().contains("программ") => "software",
+        _ => "misc"
+    };
+
+    let mut fields: Vec<(&str, String)> = vec![("title", rid.name.clone())];
+
+    if !authors.is_empty()
+    {
+        fields.push(("author", authors.iter().map(inverted).collect::<Vec<String>>().join(" and ")));
+    }
+
+    fields.push(("type", document(rid)));
+    fields.push(("number", rid.number.to_string()));
+    fields.push(("holder", HOLDER.to_string()));
+
+    if let Some(year) = &rid.year
+    {
+        fields.push(("year", year.clone()));
+    }
+    if let Some(link) = link(rid)
+    {
+        fields.push(("url", link));
+    }
+
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|(key, value)| format!("  {} = {{{}}}", key, bibtex_escape(value)))
+        .collect();
+
+    format!("@{}{{rudn{},\n{}\n}}\n", entry, rid.id, fields.join(",\n"))
+}
+
+fn ris(rid: &CitedRid, authors: &[Author]) -> String
+{
+    let rid_type = rid.rid_type.as_deref().unwrap_or_default().to_lowercase();
+    let kind = match rid.category.as_deref()
+    {
+        Some("patent") => "PAT",
+        Some("registration") if rid_type.contains("программ") => "COMP",
+        Some("registration") if rid_type.contains("баз") => "DBASE",
+        _ => "GEN"
+    };
+
+    let mut lines: Vec<String> = vec![format!("TY  - {}", kind), format!("TI  - {}", rid.name.trim())];
+
+    for author in authors
+    {
+        lines.push(format!("AU  - {}", inverted(author)));
+    }
+
+    lines.push(format!("M3  - {}", document(rid)));
+    lines.push(format!("M1  - {}", rid.number));
+    lines.push(format!("PB  - {}", HOLDER));
+
+    if let Some(year) = &rid.year
+    {
+        lines.push(format!("PY  - {}", year));
+    }
+    if let Some(link) = link(rid)
+    {
+        lines.push(format!("UR  - {}", link));
+    }
+
+    lines.push("ER  - ".to_string());
+
+    // RIS records end with CRLF line breaks.
+    lines.join("\r\n") + "\r\n"
+}
+
+fn csl(rid: &CitedRid, authors: &[Author]) -> Value
+{
+    let rid_type = rid.rid_type.as_deref().unwrap_or_default().to_lowercase();
+    let kind = match rid.category.as_deref()
+    {
+        Some("patent") => "patent",
+        Some("registration") if rid_type.contains("программ") => "software",
+        Some("registration") if rid_type.contains("баз") => "dataset",
+        _ => "document"
+    };
+
+    let mut item = json!({
+        "id": format!("rudn{}", rid.id),
+        "type": kind,
+        "title": rid.name.trim(),
+        "genre": document(rid),
+        "number": rid.number.to_string(),
+        "publisher": HOLDER,
+        "author": authors
+            .iter()
+            .map(|author| json!({ "family": author.surname, "given": given(author) }))
+            .collect::<Vec<Value>>()
+    });
+
+    if let Some(year) = rid.year.as_deref().and_then(|year| year.parse::<i32>().ok())
+    {
+        item["issued"] = json!({ "date-parts": [[year]] });
+    }
+    if let Some(link) = link(rid)
+    {
+        item["URL"] = json!(link);
+    }
+
+    item
+}
+
+fn render(format: Format, rids: &[CitedRid], authors: &HashMap<i32, Vec<Author>>) -> String
+{
+    let authors_of = |rid: &CitedRid| authors.get(&rid.id).map(|authors| authors.as_slice()).unwrap_or_default();
+
+    match format
+    {
+        Format::Gost => rids.iter().map(|rid| gost(rid, authors_of(rid))).collect::<Vec<String>>().join("\n"),
+        Format::Bibtex => rids.iter().map(|rid| bibtex(rid, authors_of(rid))).collect::<Vec<String>>().join("\n"),
+        Format::Ris => rids.iter().map(|rid| ris(rid, authors_of(rid))).collect::<Vec<String>>().join("\r\n"),
+        Format::Csl => Value::Array(rids.iter().map(|rid| csl(rid, authors_of(rid))).collect()).to_string()
+    }
+}
+
+async fn cite(pool: &MySqlPool, format: Format, condition: &str, file_name: &str) -> HttpResponse
+{
+    let query = format!(r#"
+        select
+            RID.id as id,
+            RID.name as name,
+            RID.numPotent as number,
+            Type.name as rid_type,
+            cast(Type.category as char) as category,
+            cast(Year.year as char) as year,
+            RID.link as link,
+            Type.registry_link as registry_link
+        {}
+        {}
+        order by Year.year desc
+        "#, handlers::FILTERED_RIDS, condition);
+
+    let rids: Vec<CitedRid> = match sqlx::query_as(&query).fetch_all(pool).await
+    {
+        Ok(rids) => rids,
+        Err(err) => {
+            error!("{}: cite", err);
+            return HttpResponse::ServiceUnavailable()
+                .content_type("text/html; charset=utf-8")
+                .body(err.to_string());
+        }
+    };
+
+    if rids.is_empty()
+    {
+        return HttpResponse::NotFound()
+            .content_type("text/html; charset=utf-8")
+            .body("<h1>Error 404</h1>");
+    }
+
+    let ids: Vec<i32> = rids.iter().map(|rid| rid.id).collect();
+    let authors = match authors::for_rids(pool, &ids).await
+    {
+        Ok(authors) => authors,
+        Err(err) => {
+            error!("{}: cite", err);
+            return HttpResponse::ServiceUnavailable()
+                .content_type("text/html; charset=utf-8")
+                .body(err.to_string());
+        }
+    };
+
+    let mut response = HttpResponse::Ok();
+    response.content_type(format.content_type());
+
+    if format != Format::Gost
+    {
+        response.append_header((header::CONTENT_DISPOSITION, format!(
+            "attachment; filename=\"{}.{}\"",
+            file_name,
+            format.extension()
+        )));
+    }
+
+    response.body(render(format, &rids, &authors))
+}
+
+fn unknown_format() -> HttpResponse
+{
+    HttpResponse::BadRequest()
+        .content_type("text/html; charset=utf-8")
+        .body("Unknown format, use gost, bibtex, ris or csl")
+}
+
+/// Reference to one RID, `?format=gost|bibtex|ris|csl` (GOST by default).
+#[utoipa::path(
+    get,
+    path = "/rids/{id}/cite",
+    tag = "rids",
+    params(
+        ("id" = i32, Path, description = "RID id"),
+        ("format" = Option<String>, Query, description = "`gost` (default), `bibtex`, `ris` or `csl`")
+    ),
+    responses(
+        (status = 200, description = "The reference", content(
+            (String = "text/plain"),
+            (String = "application/x-bibtex"),
+            (String = "application/x-research-info-systems"),
+            (Object = "application/vnd.citationstyles.csl+json")
+        )),
+        (status = 400, description = "Unknown format")
+    )
+)]
+pub async fn rid(id: web::Path<i32>, query: web::Query<HashMap<String, String>>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let Some(format) = Format::from_query(&query) else { return unknown_format() };
+
+    cite(pool.get_ref(), format, &format!(" where RID.id = {}", *id), &format!("rid-{}", id)).await
+}
+
+/// References to everything `/find` returns for the same request body.
+#[utoipa::path(
+    post,
+    path = "/cite",
+    tag = "search",
+    params(("format" = Option<String>, Query, description = "`gost` (default), `bibtex`, `ris` or `csl`")),
+    request_body(content = Object, description = "The same filters as for `/find`"),
+    responses(
+        (status = 200, description = "References of the matching RIDs as a file"),
+        (status = 400, description = "Unknown format")
+    )
+)]
+pub async fn search(
+    query: web::Query<HashMap<String, String>>,
+    search_request: web::Json<HashMap<String, Value>>,
+    pool: web::Data<MySqlPool>
+) -> impl Responder
+{
+    let Some(format) = Format::from_query(&query) else { return unknown_format() };
+
+    cite(pool.get_ref(), format, &handlers::filter(search_request.into_inner()), "rid").await
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn cited(name: &str) -> CitedRid
+    {
+        CitedRid {
+            id: 7,
+            name: name.to_string(),
+            number: 2700000,
+            rid_type: Some("Патент на изобретение".to_string()),
+            category: Some("patent".to_string()),
+            year: Some("2019".to_string()),
+            link: None,
+            registry_link: None
+        }
+    }
+
+    #[test]
+    fn bibtex_escape_special_characters()
+    {
+        assert_eq!(bibtex_escape(" 50% of {C_2H_5} & #1 for $5 "), r"50\% of \{C\_2H\_5\} \& \#1 for \$5");
+        assert_eq!(bibtex_escape(r"a\}b"), r"a\textbackslash{}\}b");
+    }
+
+    #[test]
+    fn bibtex_entry_escapes_title()
+    {
+        assert_eq!(
+            bibtex(&cited("Сплав {Ni}_3Al"), &[]),
+            "@patent{rudn7,\n  title = {Сплав \\{Ni\\}\\_3Al},\n  type = {Патент},\n  number = {2700000},\n  holder = {Российский университет дружбы народов имени Патриса Лумумбы},\n  year = {2019}\n}\n"
+        );
+    }
+}
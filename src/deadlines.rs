@@ -0,0 +1,471 @@
+use std::collections::HashMap;
+
+use actix_web::{http::header, web, HttpResponse, Responder};
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use chrono::{Local, Months, NaiveDate, Utc};
+use sqlx::mysql::MySqlPool;
+use log::error;
+
+use crate::admin;
+use crate::staff::{Role, Staff};
+
+/// Fees for the 3rd and later years of a patent are due before the
+/// corresponding anniversary of the filing date.
+const FIRST_FEE_YEAR: u32 = 3;
+
+/// Fees for years that passed before the grant are paid within this many
+/// months after it.
+const FEES_AFTER_GRANT_MONTHS: u32 = 4;
+
+/// Longest look-ahead a page or feed may ask for with `?days=`.
+const MAX_DAYS: u64 = 3650;
+
+#[derive(Debug, sqlx::FromRow)]
+struct ProtectedRid
+{
+    id: i32,
+    name: String,
+    number: i32,
+    faculty: Option<String>,
+    rid_type: String,
+    category: Option<String>,
+    protection_years: i32,
+    filed: Option<String>,
+    granted: String
+}
+
+#[derive(Debug)]
+struct Deadline
+{
+    rid_id: i32,
+    name: String,
+    number: i32,
+    faculty: String,
+    date: NaiveDate,
+    kind: &'static str,
+    summary: String
+}
+
+fn parse_date(date: &str) -> Option<NaiveDate>
+{
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+fn add_years(date: NaiveDate, years: u32) -> Option<NaiveDate>
+{
+    date.checked_add_months(Months::new(12 * years))
+}
+
+/// All deadlines of one RID falling into `from..=to`.
+fn rid_deadlines(rid: &ProtectedRid, from: NaiveDate, to: NaiveDate) -> Vec<Deadline>
+{
+    let mut dates: Vec<(NaiveDate, &'static str, String)> = Vec::new();
+
+    let Some(granted) = parse_date(&rid.granted) else { return Vec::new() };
+    let base = rid.filed.as_deref().and_then(parse_date).unwrap_or(granted);
+    let term = rid.protection_years.max(0) as u32;
+
+    let Some(expiry) = add_years(base, term) else { return Vec::new() };
+
+    match rid.category.as_deref()
+    {
+        Some("patent") =>
+        {
+            let mut overdue_at_grant = false;
+
+            for year in FIRST_FEE_YEAR..=term
+            {
+                let Some(due) = add_years(base, year - 1) else { continue };
+
+                if due < granted
+                {
+                    overdue_at_grant = true;
+                    continue;
+                }
+
+                dates.push((due, "fee", format!("Пошлина за {} год действия", year)));
+            }
+
+            if overdue_at_grant
+                && let Some(due) = granted.checked_add_months(Months::new(FEES_AFTER_GRANT_MONTHS))
+            {
+                dates.push((due, "fee", "Пошлины за годы до выдачи патента".to_string()));
+            }
+
+            dates.push((expiry, "expiry", "Окончание срока действия".to_string()));
+        },
+
+        Some("trademark") =>
+        {
+            dates.push((expiry, "renewal", "Продление регистрации".to_string()));
+        },
+
+        _ =>
+        {
+            dates.push((expiry, "expiry", "Окончание срока действия".to_string()));
+        }
+    }
+
+    dates
+        .into_iter()
+        .filter(|(date, _, _)| from <= *date && *date <= to)
+        .map(|(date, kind, summary)| Deadline {
+            rid_id: rid.id,
+            name: rid.name.clone(),
+            number: rid.number,
+            faculty: rid.faculty.clone().unwrap_or_default(),
+            date,
+            kind,
+            summary: format!("{}: {} № {}", summary, rid.rid_type, rid.number)
+        })
+        .collect()
+}
+
+/// Upcoming fee, renewal and expiry deadlines of granted RIDs that are still
+/// in force, optionally for one faculty only, ordered by date.
+async fn upcoming(pool: &MySqlPool, faculty: Option<i32>, days: u64) -> Result<Vec<Deadline>, sqlx::Error>
+{
+    let mut query = String::from(r#"
+        select
+            RID.id as id,
+            RID.name as name,
+            RID.numPotent as number,
+            Faculty.name as faculty,
+            Type.name as rid_type,
+            cast(Type.category as char) as category,
+            Type.protection_years as protection_years,
+            (select date_format(min(StatusHistory.date), '%Y-%m-%d') from StatusHistory
+                where StatusHistory.idRID = RID.id and StatusHistory.status = 'filed') as filed,
+            date_format(Granted.date, '%Y-%m-%d') as granted
+        from RID
+
+        join Type on (RID.idType = Type.id)
+        join (
+            select idRID, min(date) as date from StatusHistory
+            where status = 'granted'
+            group by idRID
+        ) as Granted on (Granted.idRID = RID.id)
+        left join Faculty on (RID.idFaculty = Faculty.id)
+        left join CurrentStatus on (CurrentStatus.idRID = RID.id)
+        where Type.protection_years is not null
+          and (CurrentStatus.status is null or CurrentStatus.status not in ('lapsed', 'expired'))
+    "#);
+
+    if faculty.is_some()
+    {
+        query.push_str(" and RID.idFaculty = ?");
+    }
+
+    let mut rows = sqlx::query_as::<_, ProtectedRid>(&query);
+
+    if let Some(faculty) = faculty
+    {
+        rows = rows.bind(faculty);
+    }
+
+    let rows: Vec<ProtectedRid> = rows.fetch_all(pool).await?;
+
+    let from = Local::now().date_naive();
+    let to = from + chrono::Days::new(days);
+
+    let mut deadlines: Vec<Deadline> = rows
+        .iter()
+        .flat_map(|rid| rid_deadlines(rid, from, to))
+        .collect();
+
+    deadlines.sort_by(|a, b| a.date.cmp(&b.date).then(a.rid_id.cmp(&b.rid_id)));
+
+    Ok(deadlines)
+}
+
+/// Escapes a TEXT value and folds the resulting content line at 75 octets (RFC 5545).
+fn ics_line(name: &str, value: &str) -> String
+{
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n");
+
+    let line = format!("{}:{}", name, escaped);
+    let mut folded = String::new();
+    let mut width = 0;
+
+    for ch in line.chars()
+    {
+        if width + ch.len_utf8() > 75
+        {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(ch);
+        width += ch.len_utf8();
+    }
+
+    folded.push_str("\r\n");
+    folded
+}
+
+fn to_ics(deadlines: &[Deadline], calendar_name: &str) -> String
+{
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+
+    let mut body = String::from("BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//patent.rudn.ru//deadlines//RU\r\nCALSCALE:GREGORIAN\r\n");
+    body.push_str(&ics_line("X-WR-CALNAME", calendar_name));
+
+    for deadline in deadlines
+    {
+        body.push_str("BEGIN:VEVENT\r\n");
+        body.push_str(&format!("UID:{}-{}-{}@patent.rudn.ru\r\n", deadline.rid_id, deadline.kind, deadline.date.format("%Y%m%d")));
+        body.push_str(&format!("DTSTAMP:{}\r\n", stamp));
+        body.push_str(&format!("DTSTART;VALUE=DATE:{}\r\n", deadline.date.format("%Y%m%d")));
+        body.push_str(&ics_line("SUMMARY", &deadline.summary));
+        body.push_str(&ics_line("DESCRIPTION", &format!("{}\n{}", deadline.name, deadline.faculty)));
+        body.push_str(&ics_line("CATEGORIES", deadline.kind));
+        body.push_str("END:VEVENT\r\n");
+    }
+
+    body.push_str("END:VCALENDAR\r\n");
+    body
+}
+
+fn faculty_param(query: &HashMap<String, String>) -> Option<i32>
+{
+    query.get("faculty").and_then(|faculty| faculty.parse().ok())
+}
+
+fn days_param(query: &HashMap<String, String>, default: u64) -> u64
+{
+    query.get("days").and_then(|days| days.parse().ok()).unwrap_or(default).min(MAX_DAYS)
+}
+
+/// Faculties a feed token opens: `Some(None)` for all of them (the shared
+/// `ADMIN_TOKEN` or the link of a staff member), `Some(Some(id))` for the
+/// link of an editor, `None` for no valid token.
+async fn feed_scope(pool: &MySqlPool, query: &HashMap<String, String>) -> Result<Option<Option<i32>>, sqlx::Error>
+{
+    if admin::authorized(query)
+    {
+        return Ok(Some(None));
+    }
+
+    let Some(token) = query.get("token").filter(|token| !token.is_empty()) else { return Ok(None) };
+
+    let owner: Option<(String, Option<i32>)> = sqlx::query_as("select cast(role as char), idFaculty from Staff where calendar_token = ? and active")
+        .bind(token)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(owner.map(|(role, faculty)| match Role::parse(&role)
+    {
+        Some(Role::Editor) => faculty,
+        _ => None
+    }))
+}
+
+pub async fn calendar(query: web::Query<HashMap<String, String>>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let faculty = match feed_scope(pool.get_ref(), &query).await
+    {
+        Ok(Some(Some(faculty))) => Some(faculty),
+        Ok(Some(None)) => faculty_param(&query),
+        Ok(None) => return admin::forbidden(),
+        Err(err) => {
+            error!("{}: deadlines.ics", err);
+            return HttpResponse::ServiceUnavailable()
+                .content_type("text/html; charset=utf-8")
+                .body(err.to_string());
+        }
+    };
+
+    match upcoming(pool.get_ref(), faculty, days_param(&query, 730)).await
+    {
+        Ok(deadlines) => {
+            HttpResponse::Ok()
+                .content_type("text/calendar; charset=utf-8")
+                .body(to_ics(&deadlines, "Сроки РИД РУДН"))
+        },
+
+        Err(err) => {
+            error!("{}: deadlines.ics", err);
+            HttpResponse::ServiceUnavailable()
+                .content_type("text/html; charset=utf-8")
+                .body(err.to_string())
+        }
+    }
+}
+
+pub async fn admin_view(staff: Staff, query: web::Query<HashMap<String, String>>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let deadlines = match upcoming(pool.get_ref(), faculty_param(&query), days_param(&query, 365)).await
+    {
+        Ok(deadlines) => deadlines,
+        Err(err) => {
+            error!("{}: admin/deadlines", err);
+            return HttpResponse::ServiceUnavailable()
+                .content_type("text/html; charset=utf-8")
+                .body(err.to_string());
+        }
+    };
+
+    let token: Result<Option<String>, sqlx::Error> = sqlx::query_scalar("select calendar_token from Staff where id = ?")
+        .bind(staff.id)
+        .fetch_one(pool.get_ref())
+        .await;
+
+    // Calendar applications cannot sign in, so every staff member gets a
+    // link of their own; an editor's one lists only their faculty.
+    let feed = match token
+    {
+        Ok(Some(token)) => {
+            let mut feed = format!("/deadlines.ics?token={}", token);
+            if staff.role != Role::Editor
+                && let Some(faculty) = faculty_param(&query)
+            {
+                feed.push_str(&format!("&faculty={}", faculty));
+            }
+
+            format!(
+                r#"<p><a href="{}">Подписка в календаре (.ics)</a> — личная ссылка, не пересылайте её.</p>
+                <form method="post" action="/admin/deadlines/feed"><button type="submit">Выпустить новую ссылку</button> (старая перестанет работать)</form>"#,
+                feed
+            )
+        },
+        Ok(None) => r#"<form method="post" action="/admin/deadlines/feed"><button type="submit">Получить ссылку для календаря (.ics)</button></form>"#.to_string(),
+        Err(err) => {
+            error!("{}: admin/deadlines", err);
+            return HttpResponse::ServiceUnavailable()
+                .content_type("text/html; charset=utf-8")
+                .body(err.to_string());
+        }
+    };
+
+    let rows: Vec<Vec<String>> = deadlines
+        .iter()
+        .map(|deadline| vec![
+            deadline.date.format("%d.%m.%Y").to_string(),
+            admin::escape(&deadline.summary),
+            admin::escape(&deadline.name),
+            deadline.number.to_string(),
+            admin::escape(&deadline.faculty)
+        ])
+        .collect();
+
+    admin::page("Сроки по РИД", &format!(
+        "{}{}",
+        feed,
+        admin::table(&["Дата", "Событие", "РИД", "Номер", "Факультет"], &rows)
+    ))
+}
+
+/// Issues a new personal feed link for the signed-in staff member; the
+/// previous one stops working.
+pub async fn renew_feed(staff: Staff, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let token: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+
+    let stored = sqlx::query("update Staff set calendar_token = ? where id = ?")
+        .bind(&token)
+        .bind(staff.id)
+        .execute(pool.get_ref())
+        .await;
+
+    match stored
+    {
+        Ok(_) => HttpResponse::SeeOther()
+            .append_header((header::LOCATION, "/admin/deadlines"))
+            .finish(),
+        Err(err) => {
+            error!("{}: admin/deadlines/feed", err);
+            HttpResponse::ServiceUnavailable()
+                .content_type("text/html; charset=utf-8")
+                .body(err.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate
+    {
+        parse_date(text).unwrap()
+    }
+
+    fn rid(category: Option<&str>, protection_years: i32, filed: Option<&str>, granted: &str) -> ProtectedRid
+    {
+        ProtectedRid {
+            id: 1,
+            name: "Сплав".to_string(),
+            number: 123,
+            faculty: None,
+            rid_type: "Патент на изобретение".to_string(),
+            category: category.map(str::to_string),
+            protection_years,
+            filed: filed.map(str::to_string),
+            granted: granted.to_string()
+        }
+    }
+
+    fn dates(deadlines: &[Deadline]) -> Vec<(String, &'static str)>
+    {
+        deadlines.iter().map(|deadline| (deadline.date.to_string(), deadline.kind)).collect()
+    }
+
+    #[test]
+    fn ics_line_escapes_text()
+    {
+        assert_eq!(ics_line("SUMMARY", "a;b,c\\d\ne"), "SUMMARY:a\\;b\\,c\\\\d\\ne\r\n");
+    }
+
+    #[test]
+    fn ics_line_folds_at_75_octets()
+    {
+        let value = "Пошлина за 5 год действия: патент на изобретение № 2712345, факультет физико-математических и естественных наук";
+        let line = ics_line("SUMMARY", value);
+
+        let physical: Vec<&str> = line.trim_end_matches("\r\n").split("\r\n").collect();
+        assert!(physical.len() > 1);
+        assert!(physical.iter().all(|part| part.len() <= 75));
+        assert!(physical[1..].iter().all(|part| part.starts_with(' ')));
+        assert_eq!(line.trim_end_matches("\r\n").replace("\r\n ", ""), format!("SUMMARY:{}", value.replace(',', "\\,")));
+    }
+
+    #[test]
+    fn patent_fees_overdue_at_grant_are_due_after_it()
+    {
+        let patent = rid(Some("patent"), 20, Some("2020-03-01"), "2023-06-15");
+        let deadlines = rid_deadlines(&patent, date("2023-01-01"), date("2024-12-31"));
+
+        assert_eq!(dates(&deadlines), [("2024-03-01".to_string(), "fee"), ("2023-10-15".to_string(), "fee")]);
+        assert_eq!(deadlines[0].summary, "Пошлина за 5 год действия: Патент на изобретение № 123");
+    }
+
+    #[test]
+    fn patent_expires_after_its_term_from_filing()
+    {
+        let patent = rid(Some("patent"), 20, Some("2020-03-01"), "2023-06-15");
+
+        assert_eq!(dates(&rid_deadlines(&patent, date("2040-01-01"), date("2040-12-31"))), [("2040-03-01".to_string(), "expiry")]);
+    }
+
+    #[test]
+    fn trademark_renewal_counts_from_grant_without_filing()
+    {
+        let trademark = rid(Some("trademark"), 10, None, "2020-05-01");
+
+        assert_eq!(dates(&rid_deadlines(&trademark, date("2020-01-01"), date("2035-01-01"))), [("2030-05-01".to_string(), "renewal")]);
+    }
+
+    #[test]
+    fn unparsable_grant_has_no_deadlines()
+    {
+        let broken = rid(Some("patent"), 20, None, "");
+
+        assert!(rid_deadlines(&broken, date("2000-01-01"), date("2100-01-01")).is_empty());
+    }
+}
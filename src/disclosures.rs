@@ -0,0 +1,789 @@
+use std::collections::HashMap;
+
+use actix_multipart::Multipart;
+use actix_web::{http::header, web, HttpResponse, Responder};
+use futures_util::{StreamExt, TryStreamExt};
+use sqlx::mysql::{MySqlConnection, MySqlPool};
+use sqlx::Connection;
+use log::error;
+
+use crate::admin::{self, escape};
+use crate::attachments::{self, Storage};
+use crate::catalogue;
+use crate::import::Problem;
+use crate::inventors::Inventor;
+use crate::staff::{Role, Staff};
+use crate::workflow;
+
+/// Files attached to one disclosure at most.
+const MAX_FILES: usize = 10;
+
+#[derive(Debug, sqlx::FromRow)]
+struct Summary
+{
+    id: i32,
+    title: String,
+    submitted_at: String,
+    status: String,
+    rid: Option<i32>,
+    stage: Option<String>,
+    note: Option<String>,
+    inventor: String,
+    faculty: Option<String>,
+    rid_type: Option<String>
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct Disclosure
+{
+    id: i32,
+    title: String,
+    description: String,
+    coauthors: Option<String>,
+    faculty: Option<i32>,
+    rid_type: Option<i32>,
+    status: String,
+    rid: Option<i32>,
+    note: Option<String>,
+    reviewed_by: Option<String>,
+    submitted_at: String,
+    reviewed_at: Option<String>,
+    email: String,
+    name: Option<String>
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct File
+{
+    id: i32,
+    file_name: String,
+    mime: String,
+    size: i64
+}
+
+fn status_label(status: &str) -> &'static str
+{
+    match status
+    {
+        "submitted" => "На рассмотрении",
+        "converted" => "Оформлен РИД",
+        "declined" => "Отклонена",
+        _ => ""
+    }
+}
+
+fn server_error(err: impl std::fmt::Display, page: &str) -> HttpResponse
+{
+    error!("{}: {}", err, page);
+    HttpResponse::ServiceUnavailable()
+        .content_type("text/html; charset=utf-8")
+        .body(err.to_string())
+}
+
+fn not_found() -> HttpResponse
+{
+    HttpResponse::NotFound()
+        .content_type("text/html; charset=utf-8")
+        .body("<h1>Error 404</h1>")
+}
+
+fn bad_request(message: &str) -> HttpResponse
+{
+    HttpResponse::BadRequest()
+        .content_type("text/html; charset=utf-8")
+        .body(message.to_string())
+}
+
+fn redirect(location: String) -> HttpResponse
+{
+    HttpResponse::SeeOther()
+        .append_header((header::LOCATION, location))
+        .finish()
+}
+
+async fn choices(pool: &MySqlPool, table: &str) -> Result<Vec<(i32, String)>, sqlx::Error>
+{
+    sqlx::query_as(&format!("select id, cast(name as char) from {} order by name", table))
+        .fetch_all(pool)
+        .await
+}
+
+fn select(name: &str, choices: &[(i32, String)], selected: Option<i32>, required: bool) -> String
+{
+    let options: Vec<String> = choices
+        .iter()
+        .map(|(id, label)| format!(
+            r#"<option value="{}"{}>{}</option>"#,
+            id, if Some(*id) == selected { " selected" } else { "" }, escape(label)
+        ))
+        .collect();
+
+    format!(
+        r#"<select name="{}"{}><option value="">—</option>{}</select>"#,
+        name, if required { " required" } else { "" }, options.concat()
+    )
+}
+
+async fn exists(pool: &MySqlPool, table: &str, id: Option<i32>) -> Result<bool, sqlx::Error>
+{
+    let Some(id) = id else { return Ok(true) };
+
+    let count: i64 = sqlx::query_scalar(&format!("select count(*) from {} where id = ?", table))
+        .bind(id)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(count > 0)
+}
+
+/// The inventor's disclosures and the form for a new one.
+pub async fn form(inventor: Inventor, query: web::Query<HashMap<String, String>>, storage: web::Data<Storage>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let sent: Result<Vec<Summary>, sqlx::Error> = sqlx::query_as(r#"
+        select
+            Disclosure.id as id,
+            Disclosure.title as title,
+            date_format(Disclosure.submitted_at, '%d.%m.%Y') as submitted_at,
+            cast(Disclosure.status as char) as status,
+            Disclosure.idRID as rid,
+            cast(Workflow.state as char) as stage,
+            Disclosure.note as note,
+            '' as inventor,
+            Faculty.name as faculty,
+            Type.name as rid_type
+        from Disclosure
+
+        left join Faculty on (Faculty.id = Disclosure.idFaculty)
+        left join Type on (Type.id = Disclosure.idType)
+        left join Workflow on (Workflow.idRID = Disclosure.idRID)
+        where Disclosure.idInventor = ?
+        order by Disclosure.submitted_at desc
+        "#)
+        .bind(inventor.id)
+        .fetch_all(pool.get_ref())
+        .await;
+
+    let (sent, faculties, types) = match (sent, choices(pool.get_ref(), "Faculty").await, choices(pool.get_ref(), "Type").await)
+    {
+        (Ok(sent), Ok(faculties), Ok(types)) => (sent, faculties, types),
+        (Err(err), _, _) | (_, Err(err), _) | (_, _, Err(err)) => return server_error(err, "disclose")
+    };
+
+    let rows: Vec<Vec<String>> = sent
+        .iter()
+        .map(|disclosure| vec![
+            disclosure.submitted_at.clone(),
+            escape(&disclosure.title),
+            escape(disclosure.rid_type.as_deref().unwrap_or_default()),
+            match (disclosure.rid, &disclosure.note)
+            {
+                (Some(rid), _) => format!(
+                    r#"{} (<a href="/rids/{}">РИД {}</a>){}"#,
+                    status_label(&disclosure.status), rid, rid,
+                    disclosure.stage.as_deref().map(|stage| format!(": {}", workflow::label(stage))).unwrap_or_default()
+                ),
+                (None, Some(note)) => format!("{}: {}", status_label(&disclosure.status), escape(note)),
+                (None, None) => status_label(&disclosure.status).to_string()
+            }
+        ])
+        .collect();
+
+    admin::page("Заявка о РИД", &format!(
+        r#"<p>{} <form method="post" action="/disclose/logout" style="display: inline;"><button type="submit">Выйти</button></form></p>
+        {}{}
+        <h2>Новая заявка</h2>
+        <form method="post" action="/disclose" enctype="multipart/form-data">
+            <p><label>Ваши фамилия, имя и отчество<br><input name="name" value="{}" size="60" required></label></p>
+            <p><label>Название результата<br><input name="title" size="80" maxlength="1000" required></label></p>
+            <p><label>Описание: суть, новизна, где может применяться<br><textarea name="description" rows="10" cols="80" required></textarea></label></p>
+            <p><label>Соавторы — по одному в строке: Фамилия Имя Отчество (Страна)<br><textarea name="coauthors" rows="4" cols="80"></textarea></label></p>
+            <p><label>Факультет<br>{}</label></p>
+            <p><label>Предполагаемый вид РИД<br>{}</label></p>
+            <p><label>Файлы (PDF, изображения, документы Word и PowerPoint, не больше {}, до {} МБ каждый)<br><input type="file" name="files" multiple></label></p>
+            <p><button type="submit">Отправить</button></p>
+        </form>"#,
+        escape(&inventor.email),
+        if query.contains_key("sent") { r#"<p style="color: #1b5e20;">Заявка отправлена в патентный отдел.</p>"# } else { "" },
+        if rows.is_empty() { String::default() } else { format!("<h2>Ваши заявки</h2>{}", admin::table(&["Дата", "Название", "Вид", "Состояние"], &rows)) },
+        escape(inventor.name.as_deref().unwrap_or_default()),
+        select("faculty", &faculties, None, false),
+        select("type", &types, None, false),
+        MAX_FILES,
+        storage.max_size() / (1024 * 1024)
+    ))
+}
+
+/// Saves the disclosure with its files; nothing is kept if a file cannot be
+/// stored.
+pub async fn submit(inventor: Inventor, mut payload: Multipart, storage: web::Data<Storage>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let mut fields: HashMap<String, String> = HashMap::new();
+    let mut files: Vec<(String, Vec<u8>)> = Vec::new();
+
+    while let Ok(Some(mut field)) = payload.try_next().await
+    {
+        let name = field.name().unwrap_or_default().to_string();
+
+        if name == "files"
+        {
+            let file_name = field
+                .content_disposition()
+                .and_then(|disposition| disposition.get_filename())
+                .unwrap_or_default()
+                .to_string();
+
+            let mut data: Vec<u8> = Vec::new();
+            while let Some(chunk) = field.next().await
+            {
+                let Ok(chunk) = chunk else { return bad_request("Загрузка прервалась") };
+
+                if data.len() + chunk.len() > storage.max_size()
+                {
+                    return bad_request(&format!("Файл {} больше допустимого размера", escape(&file_name)));
+                }
+                data.extend_from_slice(&chunk);
+            }
+
+            // An empty file input still sends a part without a file.
+            if data.is_empty() && file_name.is_empty()
+            {
+                continue;
+            }
+            if attachments::accepted_type(&data).is_none()
+            {
+                return bad_request(&format!("Файлы такого типа не принимаются: {}", escape(&file_name)));
+            }
+            if files.len() == MAX_FILES
+            {
+                return bad_request(&format!("Не больше {} файлов", MAX_FILES));
+            }
+
+            files.push((file_name, data));
+        }
+        else
+        {
+            let Some(value) = attachments::text_field(&mut field).await else {
+                return bad_request(&format!("Поле не больше {} КБ", attachments::MAX_FIELD_SIZE / 1024));
+            };
+            fields.insert(name, value.trim().to_string());
+        }
+    }
+
+    let text = |name: &str| fields.get(name).filter(|value| !value.is_empty()).cloned();
+    let id = |name: &str| fields.get(name).and_then(|value| value.parse::<i32>().ok());
+
+    let (Some(name), Some(title), Some(description)) = (text("name"), text("title"), text("description")) else {
+        return bad_request("Заполните ФИО, название и описание");
+    };
+    if title.chars().count() > 1000
+    {
+        return bad_request("Название не длиннее 1000 символов");
+    }
+
+    let (faculty, rid_type) = (id("faculty"), id("type"));
+    match (exists(pool.get_ref(), "Faculty", faculty).await, exists(pool.get_ref(), "Type", rid_type).await)
+    {
+        (Ok(true), Ok(true)) => {},
+        (Ok(_), Ok(_)) => return bad_request("Выберите факультет и вид из списка"),
+        (Err(err), _) | (_, Err(err)) => return server_error(err, "disclose")
+    }
+
+    let mut tx = match pool.begin().await
+    {
+        Ok(tx) => tx,
+        Err(err) => return server_error(err, "disclose")
+    };
+
+    let saved = save(&mut tx, &inventor, &name, &title, &description, text("coauthors"), faculty, rid_type, &files).await;
+
+    let file_ids = match saved
+    {
+        Ok(file_ids) => file_ids,
+        Err(err) => return server_error(err, "disclose")
+    };
+
+    // The rows are only committed once every file is in the store.
+    for (id, (_, data)) in file_ids.iter().zip(files)
+    {
+        if let Err(err) = storage.put_disclosure_file(*id, data).await
+        {
+            return server_error(err, "disclose");
+        }
+    }
+
+    if let Err(err) = tx.commit().await
+    {
+        return server_error(err, "disclose");
+    }
+
+    redirect("/disclose?sent=1".to_string())
+}
+
+/// Inserts the disclosure and its file rows, returns the ids of the files.
+#[allow(clippy::too_many_arguments)]
+async fn save(
+    conn: &mut MySqlConnection,
+    inventor: &Inventor,
+    name: &str,
+    title: &str,
+    description: &str,
+    coauthors: Option<String>,
+    faculty: Option<i32>,
+    rid_type: Option<i32>,
+    files: &[(String, Vec<u8>)]
+) -> Result<Vec<u64>, sqlx::Error>
+{
+    sqlx::query("update Inventor set name = ? where id = ?")
+        .bind(name)
+        .bind(inventor.id)
+        .execute(&mut *conn)
+        .await?;
+
+    let disclosure = sqlx::query(r#"
+        insert into Disclosure (idInventor, title, description, coauthors, idFaculty, idType)
+        values (?, ?, ?, ?, ?, ?)
+        "#)
+        .bind(inventor.id)
+        .bind(title)
+        .bind(description)
+        .bind(coauthors)
+        .bind(faculty)
+        .bind(rid_type)
+        .execute(&mut *conn)
+        .await?
+        .last_insert_id();
+
+    let mut ids = Vec::new();
+    for (file_name, data) in files
+    {
+        let id = sqlx::query("insert into DisclosureFile (idDisclosure, file_name, mime, size) values (?, ?, ?, ?)")
+            .bind(disclosure)
+            .bind(file_name)
+            .bind(attachments::accepted_type(data).unwrap_or("application/octet-stream"))
+            .bind(data.len() as i64)
+            .execute(&mut *conn)
+            .await?
+            .last_insert_id();
+        ids.push(id);
+    }
+
+    Ok(ids)
+}
+
+/// Disclosures waiting for review, or the converted or declined ones.
+pub async fn list(_staff: Staff, query: web::Query<HashMap<String, String>>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let status = match query.get("status").map(|status| status.as_str())
+    {
+        Some("converted") => "converted",
+        Some("declined") => "declined",
+        _ => "submitted"
+    };
+
+    let rows: Result<Vec<Summary>, sqlx::Error> = sqlx::query_as(r#"
+        select
+            Disclosure.id as id,
+            Disclosure.title as title,
+            date_format(Disclosure.submitted_at, '%d.%m.%Y') as submitted_at,
+            cast(Disclosure.status as char) as status,
+            Disclosure.idRID as rid,
+            cast(Workflow.state as char) as stage,
+            Disclosure.note as note,
+            concat_ws(', ', Inventor.name, Inventor.email) as inventor,
+            Faculty.name as faculty,
+            Type.name as rid_type
+        from Disclosure
+
+        join Inventor on (Inventor.id = Disclosure.idInventor)
+        left join Faculty on (Faculty.id = Disclosure.idFaculty)
+        left join Type on (Type.id = Disclosure.idType)
+        left join Workflow on (Workflow.idRID = Disclosure.idRID)
+        where Disclosure.status = ?
+        order by Disclosure.submitted_at
+        "#)
+        .bind(status)
+        .fetch_all(pool.get_ref())
+        .await;
+
+    let rows = match rows
+    {
+        Ok(rows) => rows,
+        Err(err) => return server_error(err, "admin/disclosures")
+    };
+
+    let rows: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| vec![
+            row.submitted_at.clone(),
+            format!(r#"<a href="/admin/disclosures/{}">{}</a>"#, row.id, escape(&row.title)),
+            escape(&row.inventor),
+            escape(row.faculty.as_deref().unwrap_or_default()),
+            escape(row.rid_type.as_deref().unwrap_or_default()),
+            row.rid.map(|rid| format!(r#"<a href="/admin/rids/{}/edit">РИД {}</a>"#, rid, rid)).unwrap_or_default()
+        ])
+        .collect();
+
+    admin::page("Заявки изобретателей", &format!(
+        r#"<p><a href="/admin">Администрирование</a></p>
+        <p><a href="/admin/disclosures">На рассмотрении</a> | <a href="/admin/disclosures?status=converted">Оформлены</a> | <a href="/admin/disclosures?status=declined">Отклонены</a></p>{}"#,
+        admin::table(&["Дата", "Название", "Изобретатель", "Факультет", "Вид", ""], &rows)
+    ))
+}
+
+async fn disclosure(pool: &MySqlPool, id: i32) -> Result<Option<Disclosure>, sqlx::Error>
+{
+    sqlx::query_as(r#"
+        select
+            Disclosure.id as id,
+            Disclosure.title as title,
+            Disclosure.description as description,
+            Disclosure.coauthors as coauthors,
+            Disclosure.idFaculty as faculty,
+            Disclosure.idType as rid_type,
+            cast(Disclosure.status as char) as status,
+            Disclosure.idRID as rid,
+            cast(Workflow.state as char) as stage,
+            Disclosure.note as note,
+            Disclosure.reviewed_by as reviewed_by,
+            date_format(Disclosure.submitted_at, '%d.%m.%Y %H:%i') as submitted_at,
+            date_format(Disclosure.reviewed_at, '%d.%m.%Y %H:%i') as reviewed_at,
+            Inventor.email as email,
+            Inventor.name as name
+        from Disclosure
+
+        join Inventor on (Inventor.id = Disclosure.idInventor)
+        where Disclosure.id = ?
+        "#)
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+async fn files(pool: &MySqlPool, disclosure: i32) -> Result<Vec<File>, sqlx::Error>
+{
+    sqlx::query_as("select id, file_name, mime, size from DisclosureFile where idDisclosure = ? order by id")
+        .bind(disclosure)
+        .fetch_all(pool)
+        .await
+}
+
+/// Authors of the RID a disclosure becomes: the inventor, then the co-authors.
+fn authors(disclosure: &Disclosure) -> String
+{
+    [disclosure.name.as_deref().unwrap_or_default(), disclosure.coauthors.as_deref().unwrap_or_default()]
+        .iter()
+        .map(|lines| lines.trim())
+        .filter(|lines| !lines.is_empty())
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
+
+fn review_page(staff: &Staff, disclosure: &Disclosure, files: &[File], faculties: &[(i32, String)], types: &[(i32, String)], years: &[(i32, String)], message: &str) -> HttpResponse
+{
+    let file_links: Vec<String> = files
+        .iter()
+        .map(|file| format!(
+            r#"<li><a href="/admin/disclosures/files/{}">{}</a> ({} КБ)</li>"#,
+            file.id, escape(&file.file_name), file.size / 1024
+        ))
+        .collect();
+
+    let faculty_name = faculties.iter().find(|(id, _)| Some(*id) == disclosure.faculty).map(|(_, name)| name.as_str());
+    let type_name = types.iter().find(|(id, _)| Some(*id) == disclosure.rid_type).map(|(_, name)| name.as_str());
+
+    let facts = vec![
+        vec!["Изобретатель".to_string(), escape(&format!("{} <{}>", disclosure.name.as_deref().unwrap_or_default(), disclosure.email))],
+        vec!["Подана".to_string(), disclosure.submitted_at.clone()],
+        vec!["Факультет".to_string(), escape(faculty_name.unwrap_or("—"))],
+        vec!["Предполагаемый вид".to_string(), escape(type_name.unwrap_or("—"))],
+        vec!["Описание".to_string(), escape(&disclosure.description).replace('\n', "<br>")],
+        vec!["Соавторы".to_string(), escape(disclosure.coauthors.as_deref().unwrap_or("—")).replace('\n', "<br>")],
+        vec!["Файлы".to_string(), if file_links.is_empty() { "—".to_string() } else { format!("<ul>{}</ul>", file_links.concat()) }],
+        vec!["Состояние".to_string(), format!(
+            "{}{}{}",
+            status_label(&disclosure.status),
+            disclosure.reviewed_by.as_deref().map(|by| format!(", {} {}", escape(by), disclosure.reviewed_at.as_deref().unwrap_or_default())).unwrap_or_default(),
+            disclosure.note.as_deref().map(|note| format!("<br>{}", escape(note))).unwrap_or_default()
+        )]
+    ];
+
+    let actions = if disclosure.status != "submitted"
+    {
+        disclosure.rid.map(|rid| format!(r#"<p><a href="/admin/rids/{}/edit">РИД {}</a></p>"#, rid, rid)).unwrap_or_default()
+    }
+    else if !staff.may_edit(disclosure.faculty)
+    {
+        String::default()
+    }
+    else
+    {
+        // Editors keep the RID in their own faculty.
+        let faculties: Vec<(i32, String)> = faculties
+            .iter()
+            .filter(|(id, _)| staff.may_write() || Some(*id) == staff.faculty)
+            .cloned()
+            .collect();
+
+        format!(
+            r#"<h2>Оформить РИД</h2>
+            <form method="post" action="/admin/disclosures/{0}/convert">
+                <p><label>Вид<br>{1}</label></p>
+                <p><label>Факультет<br>{2}</label></p>
+                <p><label>Год<br>{3}</label></p>
+                <p><label>Авторы — по одному в строке: Фамилия Имя Отчество (Страна); #id в конце выбирает автора из базы<br><textarea name="authors" rows="5" cols="80">{4}</textarea></label></p>
+                <p><button type="submit">Создать РИД</button></p>
+            </form>
+            <h2>Отклонить</h2>
+            <form method="post" action="/admin/disclosures/{0}/decline">
+                <p><label>Причина (её увидит изобретатель)<br><textarea name="note" rows="3" cols="80" required></textarea></label></p>
+                <p><button type="submit">Отклонить</button></p>
+            </form>"#,
+            disclosure.id,
+            select("type", types, disclosure.rid_type, true),
+            select("faculty", &faculties, disclosure.faculty, staff.role == Role::Editor),
+            select("year", years, None, false),
+            escape(&authors(disclosure))
+        )
+    };
+
+    admin::page(&format!("Заявка {}: {}", disclosure.id, escape(&disclosure.title)), &format!(
+        r#"<p><a href="/admin">Администрирование</a> / <a href="/admin/disclosures">Заявки изобретателей</a></p>{}{}{}"#,
+        if message.is_empty() { String::default() } else { format!(r#"<p style="color: #b00020;">{}</p>"#, escape(message)) },
+        admin::table(&["", ""], &facts),
+        actions
+    ))
+}
+
+async fn show(pool: &MySqlPool, staff: &Staff, id: i32, message: &str) -> HttpResponse
+{
+    let page = format!("admin/disclosures/{}", id);
+
+    let disclosure = match disclosure(pool, id).await
+    {
+        Ok(Some(disclosure)) => disclosure,
+        Ok(None) => return not_found(),
+        Err(err) => return server_error(err, &page)
+    };
+
+    let years: Result<Vec<(i32, String)>, sqlx::Error> = sqlx::query_as("select id, cast(year as char) from Year order by year desc")
+        .fetch_all(pool)
+        .await;
+
+    match (files(pool, id).await, choices(pool, "Faculty").await, choices(pool, "Type").await, years)
+    {
+        (Ok(files), Ok(faculties), Ok(types), Ok(years)) => review_page(staff, &disclosure, &files, &faculties, &types, &years, message),
+        (Err(err), _, _, _) | (_, Err(err), _, _) | (_, _, Err(err), _) | (_, _, _, Err(err)) => server_error(err, &page)
+    }
+}
+
+pub async fn view(staff: Staff, id: web::Path<i32>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    show(pool.get_ref(), &staff, *id, "").await
+}
+
+pub async fn file(_staff: Staff, id: web::Path<u64>, storage: web::Data<Storage>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let file: Result<Option<File>, sqlx::Error> = sqlx::query_as("select id, file_name, mime, size from DisclosureFile where id = ?")
+        .bind(*id)
+        .fetch_optional(pool.get_ref())
+        .await;
+
+    let file = match file
+    {
+        Ok(Some(file)) => file,
+        Ok(None) => return not_found(),
+        Err(err) => return server_error(err, "admin/disclosures/files")
+    };
+
+    match storage.disclosure_file(file.id as u64).await
+    {
+        Ok(data) => HttpResponse::Ok()
+            .content_type(file.mime)
+            .append_header((header::CONTENT_DISPOSITION, format!(
+                "attachment; filename*=UTF-8''{}",
+                file.file_name.bytes().map(|byte| match byte
+                {
+                    b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'.' | b'-' | b'_' => (byte as char).to_string(),
+                    _ => format!("%{:02X}", byte)
+                }).collect::<String>()
+            )))
+            .body(data),
+        Err(err) => server_error(err, "admin/disclosures/files")
+    }
+}
+
+/// Creates the RID with its authors and marks the disclosure converted.
+async fn create_rid(conn: &mut MySqlConnection, staff: &Staff, disclosure: &Disclosure, form: &HashMap<String, String>) -> Result<i64, Problem>
+{
+    let id = |name: &str| form.get(name).and_then(|value| value.parse::<i32>().ok());
+
+    let mut tx = conn.begin().await?;
+
+    let rid = sqlx::query(r#"
+        insert into RID (name, description, numPotent, idType, idFaculty, idYear)
+        values (?, ?, 0, ?, ?, ?)
+        "#)
+        .bind(&disclosure.title)
+        .bind(&disclosure.description)
+        .bind(id("type"))
+        .bind(id("faculty"))
+        .bind(id("year"))
+        .execute(&mut *tx)
+        .await?
+        .last_insert_id() as i64;
+
+    let mut notes = Vec::new();
+    catalogue::set_authors(&mut tx, rid, form.get("authors").map(String::as_str).unwrap_or_default(), &mut notes).await?;
+
+    let updated = sqlx::query(r#"
+        update Disclosure
+        set status = 'converted', idRID = ?, reviewed_by = ?, reviewed_at = now()
+        where id = ? and status = 'submitted'
+        "#)
+        .bind(rid)
+        .bind(&staff.name)
+        .bind(disclosure.id)
+        .execute(&mut *tx)
+        .await?;
+
+    if updated.rows_affected() == 0
+    {
+        return Err(Problem::Invalid("заявка уже рассмотрена".to_string()));
+    }
+
+    // Registration starts with the office review, with whoever converted it.
+    let comment = format!("По заявке изобретателя {}", disclosure.id);
+    workflow::transition(&mut tx, rid as i32, None, "office_review", Some(staff.id), None, Some(&comment), &staff.name).await?;
+
+    tx.commit().await?;
+    Ok(rid)
+}
+
+pub async fn convert(
+    staff: Staff,
+    id: web::Path<i32>,
+    form: web::Form<HashMap<String, String>>,
+    storage: web::Data<Storage>,
+    pool: web::Data<MySqlPool>
+) -> impl Responder
+{
+    let page = format!("admin/disclosures/{}/convert", id);
+
+    let disclosure = match disclosure(pool.get_ref(), *id).await
+    {
+        Ok(Some(disclosure)) => disclosure,
+        Ok(None) => return not_found(),
+        Err(err) => return server_error(err, &page)
+    };
+
+    let faculty = form.get("faculty").and_then(|faculty| faculty.parse::<i32>().ok());
+    if !staff.may_edit(disclosure.faculty) || !staff.may_edit(faculty)
+    {
+        return staff.deny(&page);
+    }
+
+    let rid_type = form.get("type").and_then(|rid_type| rid_type.parse::<i32>().ok());
+    if rid_type.is_none()
+    {
+        return show(pool.get_ref(), &staff, *id, "Выберите вид РИД").await;
+    }
+
+    let mut conn = match pool.acquire().await
+    {
+        Ok(conn) => conn,
+        Err(err) => return server_error(err, &page)
+    };
+
+    // Names the change in the RID history (see migrations/0009_rid_history.sql).
+    let created = match sqlx::query("set @audit_actor = ?")
+        .bind(format!("{} (заявка {})", staff.name, disclosure.id))
+        .execute(&mut *conn)
+        .await
+    {
+        Ok(_) => create_rid(&mut conn, &staff, &disclosure, &form).await,
+        Err(err) => Err(Problem::Database(err))
+    };
+
+    // The RID exists from here on; a file that fails to copy stays with the
+    // disclosure and can be attached by hand.
+    if let Ok(rid) = created
+    {
+        match files(pool.get_ref(), disclosure.id).await
+        {
+            Ok(files) => {
+                for file in files
+                {
+                    let copied = match storage.disclosure_file(file.id as u64).await
+                    {
+                        Ok(data) => attachments::add(&mut conn, &storage, rid as i32, "other", &file.file_name, &file.file_name, data)
+                            .await
+                            .map(|_| ()),
+                        Err(err) => Err(err.into())
+                    };
+
+                    if let Err(err) = copied
+                    {
+                        error!("{}: {} file {}", err, page, file.id);
+                    }
+                }
+            },
+            Err(err) => error!("{}: {}", err, page)
+        }
+    }
+
+    if let Err(err) = sqlx::query("set @audit_actor = null").execute(&mut *conn).await
+    {
+        error!("{}: {}", err, page);
+        conn.close_on_drop();
+    }
+    drop(conn);
+
+    let rid = match created
+    {
+        Ok(rid) => rid,
+        Err(Problem::Invalid(problem)) => return show(pool.get_ref(), &staff, *id, &problem).await,
+        Err(Problem::Database(err)) => return server_error(err, &page)
+    };
+
+    workflow::notify(pool.get_ref(), rid as i32, "office_review", None).await;
+
+    redirect(format!("/admin/rids/{}/edit", rid))
+}
+
+pub async fn decline(staff: Staff, id: web::Path<i32>, form: web::Form<HashMap<String, String>>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let page = format!("admin/disclosures/{}/decline", id);
+
+    let disclosure = match disclosure(pool.get_ref(), *id).await
+    {
+        Ok(Some(disclosure)) => disclosure,
+        Ok(None) => return not_found(),
+        Err(err) => return server_error(err, &page)
+    };
+
+    if !staff.may_edit(disclosure.faculty)
+    {
+        return staff.deny(&page);
+    }
+
+    let Some(note) = form.get("note").map(|note| note.trim()).filter(|note| !note.is_empty()) else {
+        return show(pool.get_ref(), &staff, *id, "Укажите причину").await;
+    };
+
+    let updated = sqlx::query(r#"
+        update Disclosure
+        set status = 'declined', note = ?, reviewed_by = ?, reviewed_at = now()
+        where id = ? and status = 'submitted'
+        "#)
+        .bind(note)
+        .bind(&staff.name)
+        .bind(*id)
+        .execute(pool.get_ref())
+        .await;
+
+    match updated
+    {
+        Ok(_) => redirect("/admin/disclosures".to_string()),
+        Err(err) => server_error(err, &page)
+    }
+}
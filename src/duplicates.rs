@@ -0,0 +1,637 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::time::Duration;
+
+use actix_web::{http::header, web, HttpResponse, Responder};
+use sqlx::mysql::{MySqlConnection, MySqlPool};
+use sqlx::Connection;
+use log::error;
+
+use crate::admin;
+use crate::staff::Staff;
+
+/// Pairs scoring below this are not reported; `DUPLICATES_THRESHOLD` overrides it.
+const DEFAULT_THRESHOLD: f64 = 0.6;
+
+/// Words shorter than this carry no meaning for the comparison.
+const MIN_WORD_LENGTH: usize = 4;
+
+#[derive(Debug, sqlx::FromRow)]
+struct RidText
+{
+    id: i32,
+    name: String,
+    description: Option<String>,
+    number: Option<i32>,
+    id_type: Option<i32>
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct RidAuthor
+{
+    rid: i32,
+    author: i32
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct CandidateRow
+{
+    id: i32,
+    rid1: i32,
+    name1: Option<String>,
+    number1: Option<i32>,
+    rid2: i32,
+    name2: Option<String>,
+    number2: Option<i32>,
+    score: f64,
+    reasons: String,
+    status: String
+}
+
+/// What is compared of a RID, prepared once per scan.
+struct Fingerprint
+{
+    id: i32,
+    number: Option<i32>,
+    id_type: Option<i32>,
+    name_words: HashSet<String>,
+    name_trigrams: HashSet<String>,
+    description_words: HashSet<String>,
+    authors: HashSet<i32>
+}
+
+fn normalise(text: &str) -> String
+{
+    text.to_lowercase()
+        .replace('ё', "е")
+        .chars()
+        .map(|ch| if ch.is_alphanumeric() { ch } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+fn words(text: &str) -> HashSet<String>
+{
+    normalise(text)
+        .split(' ')
+        .filter(|word| word.chars().count() >= MIN_WORD_LENGTH)
+        .map(|word| word.to_string())
+        .collect()
+}
+
+fn trigrams(text: &str) -> HashSet<String>
+{
+    let chars: Vec<char> = format!(" {} ", normalise(text)).chars().collect();
+    chars.windows(3).map(|window| window.iter().collect()).collect()
+}
+
+fn jaccard<T: Eq + std::hash::Hash>(a: &HashSet<T>, b: &HashSet<T>) -> f64
+{
+    if a.is_empty() || b.is_empty()
+    {
+        return 0.0;
+    }
+
+    a.intersection(b).count() as f64 / a.union(b).count() as f64
+}
+
+/// Likelihood that two RIDs are the same record, with the signals behind it.
+fn score(a: &Fingerprint, b: &Fingerprint) -> (f64, Vec<&'static str>)
+{
+    let mut reasons = Vec::new();
+
+    let name = jaccard(&a.name_trigrams, &b.name_trigrams);
+    let description = jaccard(&a.description_words, &b.description_words);
+    let authors = jaccard(&a.authors, &b.authors);
+    let same_type = a.id_type.is_some() && a.id_type == b.id_type;
+    let same_number = a.number.is_some_and(|number| number > 0) && a.number == b.number;
+
+    if name >= 0.5
+    {
+        reasons.push("название");
+    }
+    if description >= 0.5
+    {
+        reasons.push("описание");
+    }
+    if authors >= 0.5
+    {
+        reasons.push("авторы");
+    }
+    if same_number
+    {
+        reasons.push("номер");
+    }
+
+    let mut score = 0.45 * name + 0.2 * description + 0.25 * authors + if same_type { 0.1 } else { 0.0 };
+
+    // The same registration number of the same kind is one document entered twice.
+    if same_number && same_type
+    {
+        score = score.max(0.95);
+    }
+
+    (score, reasons)
+}
+
+/// Pairs that share a name word, a number or an author; only these are scored.
+fn candidate_pairs(fingerprints: &[Fingerprint]) -> HashSet<(usize, usize)>
+{
+    let mut index: HashMap<String, Vec<usize>> = HashMap::new();
+
+    for (i, fingerprint) in fingerprints.iter().enumerate()
+    {
+        let keys = fingerprint.name_words.iter().map(|word| format!("w:{}", word))
+            .chain(fingerprint.number.filter(|number| *number > 0).map(|number| format!("n:{}", number)))
+            .chain(fingerprint.authors.iter().map(|author| format!("a:{}", author)));
+
+        for key in keys
+        {
+            index.entry(key).or_default().push(i);
+        }
+    }
+
+    let mut pairs = HashSet::new();
+
+    // Words found in a large share of the records do not single out duplicates.
+    let limit = (fingerprints.len() / 20).max(50);
+
+    for members in index.values().filter(|members| members.len() <= limit)
+    {
+        for (n, &i) in members.iter().enumerate()
+        {
+            for &j in &members[n + 1..]
+            {
+                pairs.insert((i.min(j), i.max(j)));
+            }
+        }
+    }
+
+    pairs
+}
+
+fn threshold() -> f64
+{
+    env::var("DUPLICATES_THRESHOLD")
+        .ok()
+        .and_then(|threshold| threshold.parse().ok())
+        .unwrap_or(DEFAULT_THRESHOLD)
+}
+
+/// Scores all RIDs against each other and stores pairs above the threshold.
+/// Returns the number of pairs reported.
+pub async fn scan(pool: &MySqlPool) -> Result<usize, sqlx::Error>
+{
+    let rids: Vec<RidText> = sqlx::query_as(r#"
+        select
+            RID.id as id,
+            RID.name as name,
+            RID.description as description,
+            RID.numPotent as number,
+            RID.idType as id_type
+        from RID
+        "#)
+        .fetch_all(pool)
+        .await?;
+
+    let rid_authors: Vec<RidAuthor> = sqlx::query_as("select idRID as rid, idAuthor as author from AUTHORxRID")
+        .fetch_all(pool)
+        .await?;
+
+    let mut authors: HashMap<i32, HashSet<i32>> = HashMap::new();
+    for row in rid_authors
+    {
+        authors.entry(row.rid).or_default().insert(row.author);
+    }
+
+    let threshold = threshold();
+
+    let found = web::block(move || {
+        let fingerprints: Vec<Fingerprint> = rids
+            .into_iter()
+            .map(|rid| Fingerprint {
+                id: rid.id,
+                number: rid.number,
+                id_type: rid.id_type,
+                name_words: words(&rid.name),
+                name_trigrams: trigrams(&rid.name),
+                description_words: words(rid.description.as_deref().unwrap_or_default()),
+                authors: authors.remove(&rid.id).unwrap_or_default()
+            })
+            .collect();
+
+        candidate_pairs(&fingerprints)
+            .into_iter()
+            .filter_map(|(i, j)| {
+                let (a, b) = (&fingerprints[i], &fingerprints[j]);
+                let (score, reasons) = score(a, b);
+                (score >= threshold).then(|| (a.id.min(b.id), a.id.max(b.id), score, reasons.join(", ")))
+            })
+            .collect::<Vec<(i32, i32, f64, String)>>()
+    })
+    .await
+    .map_err(|err| sqlx::Error::Protocol(err.to_string()))?;
+
+    let mut tx = pool.begin().await?;
+
+    for (rid1, rid2, score, reasons) in &found
+    {
+        sqlx::query(r#"
+            insert into DuplicateCandidate (idRID1, idRID2, score, reasons)
+            values (?, ?, ?, ?)
+            on duplicate key update score = values(score), reasons = values(reasons)
+            "#)
+            .bind(rid1)
+            .bind(rid2)
+            .bind(score)
+            .bind(reasons)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(found.len())
+}
+
+/// Runs the scan every `DUPLICATES_SCAN_HOURS` hours (24 by default, 0 disables it).
+pub async fn schedule(pool: MySqlPool)
+{
+    let hours: u64 = env::var("DUPLICATES_SCAN_HOURS")
+        .ok()
+        .and_then(|hours| hours.parse().ok())
+        .unwrap_or(24);
+
+    if hours == 0
+    {
+        return;
+    }
+
+    loop
+    {
+        if let Err(err) = scan(&pool).await
+        {
+            error!("{}: duplicates scan", err);
+        }
+
+        actix_web::rt::time::sleep(Duration::from_secs(hours * 3600)).await;
+    }
+}
+
+fn status_label(status: &str) -> &str
+{
+    match status
+    {
+        "open" => "Не рассмотрено",
+        "merged" => "Объединено",
+        "dismissed" => "Не дубликат",
+        _ => status
+    }
+}
+
+fn server_error(err: impl std::fmt::Display, page: &str) -> HttpResponse
+{
+    error!("{}: {}", err, page);
+    HttpResponse::ServiceUnavailable()
+        .content_type("text/html; charset=utf-8")
+        .body(err.to_string())
+}
+
+fn not_found() -> HttpResponse
+{
+    HttpResponse::NotFound()
+        .content_type("text/html; charset=utf-8")
+        .body("<h1>Error 404</h1>")
+}
+
+fn back() -> HttpResponse
+{
+    HttpResponse::SeeOther()
+        .append_header((header::LOCATION, "/admin/duplicates"))
+        .finish()
+}
+
+pub async fn report(_staff: Staff, query: web::Query<HashMap<String, String>>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let status = match query.get("status").map(|status| status.as_str())
+    {
+        Some("merged") => "merged",
+        Some("dismissed") => "dismissed",
+        _ => "open"
+    };
+
+    let rows: Result<Vec<CandidateRow>, sqlx::Error> = sqlx::query_as(r#"
+        select
+            DuplicateCandidate.id as id,
+            DuplicateCandidate.idRID1 as rid1,
+            First.name as name1,
+            First.numPotent as number1,
+            DuplicateCandidate.idRID2 as rid2,
+            Second.name as name2,
+            Second.numPotent as number2,
+            DuplicateCandidate.score as score,
+            DuplicateCandidate.reasons as reasons,
+            cast(DuplicateCandidate.status as char) as status
+        from DuplicateCandidate
+
+        left join RID as First on (First.id = DuplicateCandidate.idRID1)
+        left join RID as Second on (Second.id = DuplicateCandidate.idRID2)
+        where DuplicateCandidate.status = ?
+        order by DuplicateCandidate.score desc
+        "#)
+        .bind(status)
+        .fetch_all(pool.get_ref())
+        .await;
+
+    let rows = match rows
+    {
+        Ok(rows) => rows,
+        Err(err) => return server_error(err, "admin/duplicates")
+    };
+
+    // A RID merged into the other one is gone, its history still shows it.
+    let rid = |id: i32, name: Option<&str>, number: Option<i32>| format!(
+        r#"{} <a href="/admin/rids/{}/history">[{}]</a><br>№ {}"#,
+        admin::escape(name.unwrap_or("(удалён)")), id, id, number.map(|number| number.to_string()).unwrap_or_default()
+    );
+
+    let button = |id: i32, action: &str, label: &str| format!(
+        r#"<form method="post" action="/admin/duplicates/{}/{}"><button type="submit">{}</button></form>"#,
+        id, action, label
+    );
+
+    let rows: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| vec![
+            format!("{:.2}", row.score),
+            rid(row.rid1, row.name1.as_deref(), row.number1),
+            rid(row.rid2, row.name2.as_deref(), row.number2),
+            admin::escape(&row.reasons),
+            status_label(&row.status).to_string(),
+            if row.status == "open"
+            {
+                format!(
+                    "{}{}{}",
+                    button(row.id, &format!("merge/{}", row.rid1), &format!("Оставить {}", row.rid1)),
+                    button(row.id, &format!("merge/{}", row.rid2), &format!("Оставить {}", row.rid2)),
+                    button(row.id, "dismiss", "Не дубликат")
+                )
+            }
+            else
+            {
+                String::default()
+            }
+        ])
+        .collect();
+
+    admin::page("Возможные дубликаты РИД", &format!(
+        r#"<form method="post" action="/admin/duplicates/scan"><button type="submit">Проверить сейчас</button></form>
+        <p><a href="/admin/duplicates">Не рассмотренные</a> | <a href="/admin/duplicates?status=merged">Объединённые</a> | <a href="/admin/duplicates?status=dismissed">Отклонённые</a></p>{}"#,
+        admin::table(&["Сходство", "РИД", "РИД", "Совпадает", "Статус", ""], &rows)
+    ))
+}
+
+pub async fn run(staff: Staff, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    if !staff.may_write()
+    {
+        return staff.deny("admin/duplicates/scan");
+    }
+
+    match scan(pool.get_ref()).await
+    {
+        Ok(_) => back(),
+        Err(err) => server_error(err, "admin/duplicates/scan")
+    }
+}
+
+pub async fn dismiss(staff: Staff, id: web::Path<i32>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    if !staff.may_write()
+    {
+        return staff.deny("admin/duplicates/dismiss");
+    }
+
+    let updated = sqlx::query("update DuplicateCandidate set status = 'dismissed', resolved_at = now() where id = ? and status = 'open'")
+        .bind(*id)
+        .execute(pool.get_ref())
+        .await;
+
+    match updated
+    {
+        Ok(result) if result.rows_affected() == 0 => not_found(),
+        Ok(_) => back(),
+        Err(err) => server_error(err, "admin/duplicates/dismiss")
+    }
+}
+
+/// Moves everything attached to `removed` over to `kept`, fills the empty
+/// columns of `kept` from `removed` and deletes `removed`.
+async fn merge_rids(conn: &mut MySqlConnection, candidate: i32, kept: i32, removed: i32) -> Result<(), sqlx::Error>
+{
+    let mut tx = conn.begin().await?;
+
+    sqlx::query("update DuplicateCandidate set status = 'merged', resolved_at = now() where id = ?")
+        .bind(candidate)
+        .execute(&mut *tx)
+        .await?;
+
+    // Other open pairs with the removed RID can no longer be acted upon.
+    sqlx::query("delete from DuplicateCandidate where status = 'open' and (idRID1 = ? or idRID2 = ?)")
+        .bind(removed)
+        .bind(removed)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query(r#"
+        update RID as Kept
+        join RID as Removed on (Removed.id = ?)
+        set
+            Kept.name_en = coalesce(nullif(Kept.name_en, ''), Removed.name_en),
+            Kept.description = coalesce(nullif(Kept.description, ''), Removed.description),
+            Kept.description_en = coalesce(nullif(Kept.description_en, ''), Removed.description_en),
+            Kept.link = coalesce(nullif(Kept.link, ''), Removed.link),
+            Kept.trl = coalesce(Kept.trl, Removed.trl),
+            Kept.partnership_terms = coalesce(nullif(Kept.partnership_terms, ''), Removed.partnership_terms)
+        where Kept.id = ?
+        "#)
+        .bind(removed)
+        .bind(kept)
+        .execute(&mut *tx)
+        .await?;
+
+    // The authors only the removed RID has follow those of the kept one, in
+    // their own order; unnumbered ones stay unnumbered.
+    let last: i64 = sqlx::query_scalar("select cast(coalesce(max(position), 0) as signed) from AUTHORxRID where idRID = ?")
+        .bind(kept)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    sqlx::query(r#"
+        insert into AUTHORxRID (idRID, idAuthor, position)
+        select ?, idAuthor, case when position is null then null else ? + row_number() over (order by position is null, position, idAuthor) end
+        from AUTHORxRID as Removed
+        where Removed.idRID = ?
+          and not exists (select 1 from AUTHORxRID as Kept where Kept.idRID = ? and Kept.idAuthor = Removed.idAuthor)
+        "#)
+        .bind(kept)
+        .bind(last)
+        .bind(removed)
+        .bind(kept)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("insert ignore into AGREEMENTxRID (idAgreement, idRID) select idAgreement, ? from AGREEMENTxRID where idRID = ?")
+        .bind(kept)
+        .bind(removed)
+        .execute(&mut *tx)
+        .await?;
+
+    for table in ["StatusHistory", "Attachment", "Disclosure"]
+    {
+        sqlx::query(&format!("update {} set idRID = ? where idRID = ?", table))
+            .bind(kept)
+            .bind(removed)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    // The kept RID keeps its own workflow if it has one; the steps of both
+    // stay in its log.
+    sqlx::query("update ignore Workflow set idRID = ? where idRID = ?")
+        .bind(kept)
+        .bind(removed)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("update WorkflowStep set idRID = ? where idRID = ?")
+        .bind(kept)
+        .bind(removed)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("delete from AUTHORxRID where idRID = ?").bind(removed).execute(&mut *tx).await?;
+    sqlx::query("delete from RID where id = ?").bind(removed).execute(&mut *tx).await?;
+
+    tx.commit().await
+}
+
+pub async fn merge(staff: Staff, path: web::Path<(i32, i32)>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    if !staff.may_write()
+    {
+        return staff.deny("admin/duplicates/merge");
+    }
+
+    let (candidate, kept) = path.into_inner();
+
+    let pair: Result<Option<(i32, i32)>, sqlx::Error> = sqlx::query_as("select idRID1, idRID2 from DuplicateCandidate where id = ? and status = 'open'")
+        .bind(candidate)
+        .fetch_optional(pool.get_ref())
+        .await;
+
+    let removed = match pair
+    {
+        Ok(Some((rid1, rid2))) if kept == rid1 => rid2,
+        Ok(Some((rid1, rid2))) if kept == rid2 => rid1,
+        Ok(_) => return not_found(),
+        Err(err) => return server_error(err, "admin/duplicates/merge")
+    };
+
+    let mut conn = match pool.acquire().await
+    {
+        Ok(conn) => conn,
+        Err(err) => return server_error(err, "admin/duplicates/merge")
+    };
+
+    // Names the change in the RID history (see migrations/0009_rid_history.sql).
+    let merged = match sqlx::query("set @audit_actor = ?")
+        .bind(format!("{} (объединение РИД {} с {})", staff.name, removed, kept))
+        .execute(&mut *conn)
+        .await
+    {
+        Ok(_) => merge_rids(&mut conn, candidate, kept, removed).await,
+        Err(err) => Err(err)
+    };
+
+    if let Err(err) = sqlx::query("set @audit_actor = null").execute(&mut *conn).await
+    {
+        error!("{}: admin/duplicates/merge", err);
+        conn.close_on_drop();
+    }
+
+    match merged
+    {
+        Ok(()) => back(),
+        Err(err) => server_error(err, "admin/duplicates/merge")
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn fingerprint(id: i32, name: &str, description: &str, number: Option<i32>, id_type: Option<i32>, authors: &[i32]) -> Fingerprint
+    {
+        Fingerprint {
+            id,
+            number,
+            id_type,
+            name_words: words(name),
+            name_trigrams: trigrams(name),
+            description_words: words(description),
+            authors: authors.iter().copied().collect()
+        }
+    }
+
+    #[test]
+    fn score_same_record_retyped()
+    {
+        let a = fingerprint(1, "Способ получения жаропрочного сплава", "Сплав на основе никеля для лопаток турбин", None, Some(1), &[10, 11]);
+        let b = fingerprint(2, "Способ получения жаропрочного  сплава.", "Сплав на основе никеля для лопаток турбин", None, Some(1), &[10, 11]);
+
+        let (score, reasons) = score(&a, &b);
+
+        assert!((score - 1.0).abs() < 1e-9);
+        assert_eq!(reasons, ["название", "описание", "авторы"]);
+    }
+
+    #[test]
+    fn score_same_number_and_type()
+    {
+        let a = fingerprint(1, "База данных штаммов", "", Some(2021620001), Some(3), &[]);
+        let b = fingerprint(2, "Программа учёта реактивов", "", Some(2021620001), Some(3), &[]);
+
+        let (score, reasons) = score(&a, &b);
+
+        assert_eq!(score, 0.95);
+        assert_eq!(reasons, ["номер"]);
+    }
+
+    #[test]
+    fn score_number_zero_is_not_a_match()
+    {
+        let a = fingerprint(1, "База данных штаммов", "", Some(0), Some(3), &[]);
+        let b = fingerprint(2, "Программа учёта реактивов", "", Some(0), Some(3), &[]);
+
+        let (score, reasons) = score(&a, &b);
+
+        assert!(score < DEFAULT_THRESHOLD);
+        assert!(reasons.is_empty());
+    }
+
+    #[test]
+    fn score_unrelated_records()
+    {
+        let a = fingerprint(1, "Способ получения жаропрочного сплава", "Никель", Some(1), Some(1), &[10]);
+        let b = fingerprint(2, "Программа для ЭВМ учёта реактивов", "Склад", Some(2), Some(2), &[20]);
+
+        let (score, reasons) = score(&a, &b);
+
+        assert!(score < 0.1);
+        assert!(reasons.is_empty());
+    }
+}
@@ -0,0 +1,472 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use actix_web::{error, http::header, web, HttpResponse, Responder};
+use futures_util::stream;
+use serde_json::Value;
+use sqlx::mysql::MySqlPool;
+use tokio::sync::mpsc;
+use zip::write::{SimpleFileOptions, StreamWriter};
+use zip::{CompressionMethod, ZipWriter};
+use log::error;
+
+use crate::agreements;
+use crate::authors::{self, Author, NameStyle};
+use crate::handlers;
+use crate::i18n::Locale;
+use crate::registry;
+
+/// RIDs whose authors are loaded and written out together.
+const BATCH: usize = 500;
+
+const COLUMNS: [&str; 20] = [
+    "ID",
+    "Название",
+    "Название (англ.)",
+    "Описание",
+    "Описание (англ.)",
+    "Номер",
+    "Факультет",
+    "Вид",
+    "Год",
+    "Предметная область",
+    "Ссылка",
+    "Статус",
+    "Дата статуса",
+    "Договор",
+    "TRL",
+    "Доступно для лицензирования",
+    "Условия партнёрства",
+    "Авторы",
+    "Авторы (id)",
+    "Страны авторов",
+];
+
+#[derive(Debug, sqlx::FromRow)]
+struct ExportedRid
+{
+    id: i32,
+    name: String,
+    name_en: Option<String>,
+    description: Option<String>,
+    description_en: Option<String>,
+    number: i32,
+    faculty: Option<String>,
+    rid_type: Option<String>,
+    year: Option<String>,
+    sub_area: Option<String>,
+    link: Option<String>,
+    registry_link: Option<String>,
+    status: Option<String>,
+    status_date: Option<String>,
+    agreement_kind: Option<String>,
+    trl: Option<i8>,
+    available: bool,
+    partnership_terms: Option<String>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format
+{
+    Csv,
+    Xlsx
+}
+
+enum Cell
+{
+    Number(i64),
+    Text(String)
+}
+
+fn text(value: Option<&str>) -> Cell
+{
+    Cell::Text(value.unwrap_or_default().to_string())
+}
+
+fn cells(rid: &ExportedRid, authors: &[Author]) -> Vec<Cell>
+{
+    let join = |parts: Vec<String>| Cell::Text(parts.join("; "));
+
+    vec![
+        Cell::Number(rid.id.into()),
+        Cell::Text(rid.name.clone()),
+        text(rid.name_en.as_deref()),
+        text(rid.description.as_deref()),
+        text(rid.description_en.as_deref()),
+        Cell::Number(rid.number.into()),
+        text(rid.faculty.as_deref()),
+        text(rid.rid_type.as_deref()),
+        text(rid.year.as_deref()),
+        text(rid.sub_area.as_deref()),
+        text(registry::link(rid.link.as_deref(), rid.registry_link.as_deref(), rid.number).as_deref()),
+        text(rid.status.as_deref().map(|status| handlers::status_label(Locale::Ru, status))),
+        text(rid.status_date.as_deref()),
+        text(rid.agreement_kind.as_deref().map(agreements::kind_label)),
+        match rid.trl
+        {
+            Some(trl) => Cell::Number(trl.into()),
+            None => text(None)
+        },
+        Cell::Text(if rid.available { "да" } else { "нет" }.to_string()),
+        text(rid.partnership_terms.as_deref()),
+        join(authors.iter().map(|author| authors::name(author, NameStyle::FULL)).collect()),
+        join(authors.iter().map(|author| author.id.to_string()).collect()),
+        join(authors.iter().map(|author| author.country.clone().unwrap_or_default()).collect()),
+    ]
+}
+
+/// Buffer the output is written to, handed to the response after every batch.
+/// Shared so that the XLSX archive can keep writing into it.
+#[derive(Clone, Default)]
+struct Buffer(Rc<RefCell<Vec<u8>>>);
+
+impl Buffer
+{
+    fn take(&self) -> Vec<u8>
+    {
+        std::mem::take(&mut *self.0.borrow_mut())
+    }
+}
+
+impl Write for Buffer
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize>
+    {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()>
+    {
+        Ok(())
+    }
+}
+
+fn csv_field(cell: &Cell) -> String
+{
+    let value = match cell
+    {
+        Cell::Number(number) => return number.to_string(),
+        Cell::Text(value) => value
+    };
+
+    // Spreadsheets run fields starting with these as formulas.
+    let value = if value.starts_with(['=', '+', '-', '@'])
+    {
+        format!("'{}", value)
+    }
+    else
+    {
+        value.clone()
+    };
+
+    if value.contains([',', '"', '\r', '\n'])
+    {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    }
+    else
+    {
+        value
+    }
+}
+
+pub fn xml_escape(text: &str) -> String
+{
+    let mut escaped = String::with_capacity(text.len());
+
+    for ch in text.chars()
+    {
+        match ch
+        {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            // Other control characters are not allowed in XML at all.
+            '\t' | '\n' | '\r' => escaped.push(ch),
+            ch if ch.is_control() => {},
+            _ => escaped.push(ch)
+        }
+    }
+
+    escaped
+}
+
+fn xlsx_row(cells: &[Cell]) -> String
+{
+    let cells: Vec<String> = cells
+        .iter()
+        .map(|cell| match cell
+        {
+            Cell::Number(number) => format!("<c><v>{}</v></c>", number),
+            Cell::Text(value) => format!(r#"<c t="inlineStr"><is><t xml:space="preserve">{}</t></is></c>"#, xml_escape(value))
+        })
+        .collect();
+
+    format!("<row>{}</row>", cells.concat())
+}
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/></Types>"#;
+
+const ROOT_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#;
+
+const WORKBOOK: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="РИД" sheetId="1" r:id="rId1"/></sheets></workbook>"#;
+
+const WORKBOOK_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/></Relationships>"#;
+
+const SHEET_START: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetViews><sheetView workbookViewId="0"><pane ySplit="1" topLeftCell="A2" activePane="bottomLeft" state="frozen"/></sheetView></sheetViews><sheetData>"#;
+
+const SHEET_END: &str = "</sheetData></worksheet>";
+
+/// A CSV file or an XLSX workbook being written row by row. The workbook is a
+/// zip archive written as a stream, so the sheet never has to fit in memory.
+struct Table
+{
+    buffer: Buffer,
+    archive: Option<ZipWriter<StreamWriter<Buffer>>>
+}
+
+impl Table
+{
+    fn new(format: Format) -> Result<Table, Box<dyn Error>>
+    {
+        let mut buffer = Buffer::default();
+
+        let archive = match format
+        {
+            Format::Csv => {
+                // Byte order mark, for Excel to read the file as UTF-8.
+                buffer.write_all("\u{feff}".as_bytes())?;
+                None
+            },
+            Format::Xlsx => {
+                let mut archive = ZipWriter::new_stream(buffer.clone());
+                let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+                for (name, content) in [
+                    ("[Content_Types].xml", CONTENT_TYPES),
+                    ("_rels/.rels", ROOT_RELS),
+                    ("xl/workbook.xml", WORKBOOK),
+                    ("xl/_rels/workbook.xml.rels", WORKBOOK_RELS),
+                ]
+                {
+                    archive.start_file(name, options)?;
+                    archive.write_all(content.as_bytes())?;
+                }
+
+                archive.start_file("xl/worksheets/sheet1.xml", options)?;
+                archive.write_all(SHEET_START.as_bytes())?;
+                Some(archive)
+            }
+        };
+
+        let mut table = Table { buffer, archive };
+        let header: Vec<Cell> = COLUMNS.iter().map(|column| Cell::Text(column.to_string())).collect();
+        table.row(&header)?;
+
+        Ok(table)
+    }
+
+    fn row(&mut self, cells: &[Cell]) -> io::Result<()>
+    {
+        match &mut self.archive
+        {
+            Some(archive) => archive.write_all(xlsx_row(cells).as_bytes()),
+            None => {
+                let fields: Vec<String> = cells.iter().map(csv_field).collect();
+                self.buffer.write_all(fields.join(",").as_bytes())?;
+                self.buffer.write_all(b"\r\n")
+            }
+        }
+    }
+
+    fn finish(self) -> Result<Vec<u8>, Box<dyn Error>>
+    {
+        if let Some(mut archive) = self.archive
+        {
+            archive.write_all(SHEET_END.as_bytes())?;
+            archive.finish()?;
+        }
+
+        Ok(self.buffer.take())
+    }
+}
+
+type Chunk = Result<web::Bytes, actix_web::Error>;
+
+/// Writes the table for `query` into `sender` one batch at a time. The
+/// query takes the last RID id written and the batch size, so that no
+/// connection is held while a slow client reads. Stops early, without an
+/// error, once the client has gone.
+async fn write(pool: &MySqlPool, query: &str, format: Format, sender: &mpsc::Sender<Chunk>) -> Result<(), Box<dyn Error>>
+{
+    let mut table = Table::new(format)?;
+    let mut last = 0;
+
+    loop
+    {
+        let batch: Vec<ExportedRid> = sqlx::query_as(query)
+            .bind(last)
+            .bind(BATCH as i64)
+            .fetch_all(pool)
+            .await?;
+        let done = batch.len() < BATCH;
+
+        let ids: Vec<i32> = batch.iter().map(|rid| rid.id).collect();
+        let authors = authors::for_rids(pool, &ids).await?;
+        last = ids.last().copied().unwrap_or(last);
+
+        for rid in batch
+        {
+            table.row(&cells(&rid, authors.get(&rid.id).map(|authors| authors.as_slice()).unwrap_or_default()))?;
+        }
+
+        if done
+        {
+            let _ = sender.send(Ok(web::Bytes::from(table.finish()?))).await;
+            return Ok(());
+        }
+
+        if sender.send(Ok(web::Bytes::from(table.buffer.take()))).await.is_err()
+        {
+            return Ok(());
+        }
+    }
+}
+
+/// Everything `/find` returns for the same request body as one table,
+/// `?format=csv|xlsx` (CSV by default), in RID id order. Rows are streamed
+/// as they are read.
+#[utoipa::path(
+    post,
+    path = "/export",
+    tag = "search",
+    params(("format" = Option<String>, Query, description = "`csv` (default) or `xlsx`")),
+    request_body(content = Object, description = "The same filters as for `/find`"),
+    responses(
+        (status = 200, description = "One row per RID", content(
+            (String = "text/csv"),
+            (Vec<u8> = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet")
+        )),
+        (status = 400, description = "Unknown format")
+    )
+)]
+pub async fn search(
+    query: web::Query<HashMap<String, String>>,
+    search_request: web::Json<HashMap<String, Value>>,
+    pool: web::Data<MySqlPool>
+) -> impl Responder
+{
+    let format = match query.get("format").map(|format| format.as_str()).unwrap_or("csv")
+    {
+        "csv" => Format::Csv,
+        "xlsx" => Format::Xlsx,
+        _ => {
+            return HttpResponse::BadRequest()
+                .content_type("text/html; charset=utf-8")
+                .body("Unknown format, use csv or xlsx");
+        }
+    };
+
+    let filter = handlers::filter(search_request.into_inner());
+    let query = format!(r#"
+        select
+            RID.id as id,
+            RID.name as name,
+            RID.name_en as name_en,
+            RID.description as description,
+            RID.description_en as description_en,
+            RID.numPotent as number,
+            Faculty.name as faculty,
+            Type.name as rid_type,
+            cast(Year.year as char) as year,
+            SubjectArea.name as sub_area,
+            RID.link as link,
+            Type.registry_link as registry_link,
+            cast(CurrentStatus.status as char) as status,
+            date_format(CurrentStatus.date, '%d.%m.%Y') as status_date,
+            (select cast(Agreement.kind as char) from Agreement
+                join AGREEMENTxRID on (AGREEMENTxRID.idAgreement = Agreement.id)
+                where AGREEMENTxRID.idRID = RID.id and Agreement.status = 'active' and Agreement.public = 1
+                order by Agreement.date_signed desc limit 1) as agreement_kind,
+            RID.trl as trl,
+            RID.available as available,
+            RID.partnership_terms as partnership_terms
+        {}
+        {}
+        {} RID.id > ?
+        order by RID.id
+        limit ?
+        "#, handlers::FILTERED_RIDS, filter, if filter.is_empty() { "where" } else { "and" });
+
+    // A few batches in flight at most: a slow client holds the next batch
+    // back instead of the rows piling up in memory.
+    let (sender, receiver) = mpsc::channel::<Chunk>(2);
+    let pool = pool.get_ref().clone();
+
+    actix_web::rt::spawn(async move {
+        if let Err(err) = write(&pool, &query, format, &sender).await
+        {
+            error!("{}: export", err);
+            // Aborts the response, so that a cut-off file is not taken for a whole one.
+            let _ = sender.send(Err(error::ErrorServiceUnavailable(err.to_string()))).await;
+        }
+    });
+
+    let (content_type, extension) = match format
+    {
+        Format::Csv => ("text/csv; charset=utf-8", "csv"),
+        Format::Xlsx => ("application/vnd.openxmlformats-officedocument.spreadsheetml.sheet", "xlsx")
+    };
+
+    HttpResponse::Ok()
+        .content_type(content_type)
+        .append_header((header::CONTENT_DISPOSITION, format!("attachment; filename=\"rid.{}\"", extension)))
+        .streaming(stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|chunk| (chunk, receiver))
+        }))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn text_field(value: &str) -> String
+    {
+        csv_field(&Cell::Text(value.to_string()))
+    }
+
+    #[test]
+    fn csv_field_plain_values()
+    {
+        assert_eq!(csv_field(&Cell::Number(-42)), "-42");
+        assert_eq!(text_field("Сплав"), "Сплав");
+        assert_eq!(text_field(""), "");
+    }
+
+    #[test]
+    fn csv_field_quotes_separators()
+    {
+        assert_eq!(text_field("a, b"), "\"a, b\"");
+        assert_eq!(text_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(text_field("two\r\nlines"), "\"two\r\nlines\"");
+    }
+
+    #[test]
+    fn csv_field_defuses_formulas()
+    {
+        assert_eq!(text_field("=HYPERLINK(\"x\")"), "\"'=HYPERLINK(\"\"x\"\")\"");
+        assert_eq!(text_field("+7 495"), "'+7 495");
+        assert_eq!(text_field("-1"), "'-1");
+        assert_eq!(text_field("@SUM"), "'@SUM");
+    }
+}
@@ -0,0 +1,308 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Deserialize;
+use utoipa::IntoParams;
+use sqlx::mysql::MySqlPool;
+use log::error;
+
+use crate::authors::{self, Author, NameStyle};
+use crate::citations::HOLDER;
+use crate::export::xml_escape;
+use crate::handlers;
+use crate::i18n::Locale;
+
+/// Entries in a feed, the most recent first.
+const ENTRIES: u32 = 50;
+
+/// `?faculty=&area=&type=` with the ids used on the faculty pages and in
+/// the search filters; all of them are optional.
+#[derive(Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FeedQuery
+{
+    /// Faculty id
+    faculty: Option<i32>,
+    /// Subject area id
+    area: Option<i32>,
+    /// Type id
+    #[serde(rename = "type")]
+    rid_type: Option<i32>
+}
+
+/// A RID was added (its first RIDHistory version) or granted a title of
+/// protection (a `granted` entry in StatusHistory).
+#[derive(Debug, sqlx::FromRow)]
+struct Event
+{
+    event: String,
+    time: i64,
+    id: i32,
+    name: String,
+    description: Option<String>,
+    rid_type: Option<String>,
+    faculty: Option<String>,
+    sub_area: Option<String>
+}
+
+fn server_error(err: impl std::fmt::Display, page: &str) -> HttpResponse
+{
+    error!("{}: {}", err, page);
+    HttpResponse::ServiceUnavailable()
+        .content_type("text/html; charset=utf-8")
+        .body(err.to_string())
+}
+
+fn title(event: &Event) -> String
+{
+    let label = match event.event.as_str()
+    {
+        "granted" => handlers::status_label(Locale::Ru, "granted"),
+        _ => "Новый РИД"
+    };
+
+    format!("{}: {}", label, event.name.trim())
+}
+
+fn time(unix: i64) -> DateTime<Utc>
+{
+    DateTime::from_timestamp(unix, 0).unwrap_or_default()
+}
+
+async fn events(pool: &MySqlPool, query: &FeedQuery) -> Result<Vec<Event>, sqlx::Error>
+{
+    let mut conditions: Vec<&str> = Vec::new();
+    let mut values: Vec<i32> = Vec::new();
+
+    for (condition, value) in [
+        ("RID.idFaculty = ?", query.faculty),
+        ("RID.idSubjectArea = ?", query.area),
+        ("RID.idType = ?", query.rid_type)
+    ]
+    {
+        if let Some(value) = value
+        {
+            conditions.push(condition);
+            values.push(value);
+        }
+    }
+
+    let sql = format!(r#"
+        select
+            Event.event as event,
+            Event.time as time,
+            RID.id as id,
+            RID.name as name,
+            RID.description as description,
+            Type.name as rid_type,
+            Faculty.name as faculty,
+            SubjectArea.name as sub_area
+        {}
+        join (
+            select 'added' as event, idRID, cast(unix_timestamp(min(changed_at)) as signed) as time
+            from RIDHistory
+            where action = 'insert'
+            group by idRID
+            union all
+            select 'granted', idRID, cast(unix_timestamp(date) as signed)
+            from StatusHistory
+            where status = 'granted'
+        ) as Event on (Event.idRID = RID.id)
+        {}
+        order by Event.time desc, RID.id desc
+        limit {}
+        "#,
+        handlers::FILTERED_RIDS,
+        if conditions.is_empty() { String::default() } else { format!("where {}", conditions.join(" and ")) },
+        ENTRIES);
+
+    let mut events = sqlx::query_as(&sql);
+    for value in values
+    {
+        events = events.bind(value);
+    }
+
+    events.fetch_all(pool).await
+}
+
+/// Address of the feed itself, with the filters it was requested with.
+fn self_link(req: &HttpRequest) -> String
+{
+    match req.query_string()
+    {
+        "" => format!("{}{}", handlers::base_url(req), req.path()),
+        query => format!("{}{}?{}", handlers::base_url(req), req.path(), query)
+    }
+}
+
+/// Newly added and newly granted RIDs as Atom.
+#[utoipa::path(
+    get,
+    path = "/feed.atom",
+    tag = "feeds",
+    params(FeedQuery),
+    responses((status = 200, description = "The latest entries", content_type = "application/atom+xml"))
+)]
+pub async fn atom(req: HttpRequest, query: web::Query<FeedQuery>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let events = match events(pool.get_ref(), &query).await
+    {
+        Ok(events) => events,
+        Err(err) => return server_error(err, "feed.atom")
+    };
+
+    let ids: Vec<i32> = events.iter().map(|event| event.id).collect();
+    let authors = match authors::for_rids(pool.get_ref(), &ids).await
+    {
+        Ok(authors) => authors,
+        Err(err) => return server_error(err, "feed.atom")
+    };
+
+    let base = handlers::base_url(&req);
+    let link = self_link(&req);
+    let updated = events.first().map(|event| time(event.time)).unwrap_or_else(Utc::now);
+
+    let mut body = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom" xml:lang="ru">
+<id>{0}</id>
+<title>Новые результаты интеллектуальной деятельности РУДН</title>
+<link rel="self" type="application/atom+xml" href="{0}"/>
+<link rel="alternate" type="text/html" href="{1}/portfolio"/>
+<updated>{2}</updated>
+<author><name>{3}</name></author>
+"#,
+        xml_escape(&link),
+        base,
+        updated.to_rfc3339_opts(SecondsFormat::Secs, true),
+        HOLDER
+    );
+
+    for event in &events
+    {
+        let url = format!("{}/rids/{}", base, event.id);
+
+        body.push_str(&format!(
+            r#"<entry>
+<id>{0}#{1}</id>
+<title>{2}</title>
+<link rel="alternate" type="text/html" href="{0}"/>
+<updated>{3}</updated>
+"#,
+            url,
+            event.event,
+            xml_escape(&title(event)),
+            time(event.time).to_rfc3339_opts(SecondsFormat::Secs, true)
+        ));
+
+        for author in authors.get(&event.id).map(Vec::as_slice).unwrap_or_default()
+        {
+            body.push_str(&format!("<author><name>{}</name><uri>{}/authors/{}</uri></author>\n", xml_escape(&authors::name(author, NameStyle::FULL)), base, author.id));
+        }
+        for category in [&event.rid_type, &event.faculty, &event.sub_area].into_iter().flatten()
+        {
+            body.push_str(&format!("<category term=\"{}\"/>\n", xml_escape(category)));
+        }
+        if let Some(description) = event.description.as_deref().map(str::trim).filter(|description| !description.is_empty())
+        {
+            body.push_str(&format!("<summary type=\"text\">{}</summary>\n", xml_escape(description)));
+        }
+
+        body.push_str("</entry>\n");
+    }
+
+    body.push_str("</feed>\n");
+
+    HttpResponse::Ok()
+        .content_type("application/atom+xml; charset=utf-8")
+        .body(body)
+}
+
+/// The same entries as RSS 2.0.
+#[utoipa::path(
+    get,
+    path = "/feed.rss",
+    tag = "feeds",
+    params(FeedQuery),
+    responses((status = 200, description = "The latest entries", content_type = "application/rss+xml"))
+)]
+pub async fn rss(req: HttpRequest, query: web::Query<FeedQuery>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let events = match events(pool.get_ref(), &query).await
+    {
+        Ok(events) => events,
+        Err(err) => return server_error(err, "feed.rss")
+    };
+
+    let ids: Vec<i32> = events.iter().map(|event| event.id).collect();
+    let authors = match authors::for_rids(pool.get_ref(), &ids).await
+    {
+        Ok(authors) => authors,
+        Err(err) => return server_error(err, "feed.rss")
+    };
+
+    let base = handlers::base_url(&req);
+    let updated = events.first().map(|event| time(event.time)).unwrap_or_else(Utc::now);
+
+    let mut body = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom" xmlns:dc="http://purl.org/dc/elements/1.1/">
+<channel>
+<title>Новые результаты интеллектуальной деятельности РУДН</title>
+<link>{0}/portfolio</link>
+<atom:link rel="self" type="application/rss+xml" href="{1}"/>
+<description>Новые и получившие охранные документы РИД: {2}</description>
+<language>ru</language>
+<lastBuildDate>{3}</lastBuildDate>
+"#,
+        base,
+        xml_escape(&self_link(&req)),
+        HOLDER,
+        updated.to_rfc2822()
+    );
+
+    for event in &events
+    {
+        let url = format!("{}/rids/{}", base, event.id);
+
+        body.push_str(&format!(
+            r#"<item>
+<title>{0}</title>
+<link>{1}</link>
+<guid isPermaLink="false">{1}#{2}</guid>
+<pubDate>{3}</pubDate>
+"#,
+            xml_escape(&title(event)),
+            url,
+            event.event,
+            time(event.time).to_rfc2822()
+        ));
+
+        let names: Vec<String> = authors
+            .get(&event.id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(|author: &Author| authors::name(author, NameStyle::FULL))
+            .collect();
+        if !names.is_empty()
+        {
+            body.push_str(&format!("<dc:creator>{}</dc:creator>\n", xml_escape(&names.join(", "))));
+        }
+        for category in [&event.rid_type, &event.faculty, &event.sub_area].into_iter().flatten()
+        {
+            body.push_str(&format!("<category>{}</category>\n", xml_escape(category)));
+        }
+        if let Some(description) = event.description.as_deref().map(str::trim).filter(|description| !description.is_empty())
+        {
+            body.push_str(&format!("<description>{}</description>\n", xml_escape(description)));
+        }
+
+        body.push_str("</item>\n");
+    }
+
+    body.push_str("</channel>\n</rss>\n");
+
+    HttpResponse::Ok()
+        .content_type("application/rss+xml; charset=utf-8")
+        .body(body)
+}
@@ -0,0 +1,805 @@
+use std::collections::HashMap;
+use std::env;
+use std::sync::Arc;
+
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use async_graphql::dataloader::{DataLoader, Loader};
+use async_graphql::{BatchRequest, ComplexObject, Context, EmptyMutation, EmptySubscription, Object, Result, Schema, SimpleObject};
+use sqlx::mysql::MySqlPool;
+use log::error;
+
+use crate::authors::{self, Author, NameStyle};
+use crate::registry;
+
+pub type RidSchema = Schema<Query, EmptyMutation, EmptySubscription>;
+
+/// Most RIDs or authors a list query returns at once.
+const MAX_LIMIT: i32 = 500;
+
+/// Complexity of a nested list counts as this many of its items.
+const LIST: usize = 5;
+
+const RID_COLUMNS: &str = r#"
+            RID.id as id,
+            RID.name as name,
+            RID.name_en as name_en,
+            RID.description as description,
+            RID.description_en as description_en,
+            RID.numPotent as number,
+            RID.idFaculty as faculty_id,
+            RID.idType as type_id,
+            RID.idYear as year_id,
+            RID.idSubjectArea as subject_area_id,
+            RID.link as link,
+            Type.registry_link as registry_link,
+            cast(CurrentStatus.status as char) as status,
+            date_format(CurrentStatus.date, '%Y-%m-%d') as status_date,
+            RID.trl as trl,
+            RID.available as available,
+            RID.partnership_terms as partnership_terms
+        from RID
+        left join Type on (Type.id = RID.idType)
+        left join Year on (Year.id = RID.idYear)
+        left join CurrentStatus on (CurrentStatus.idRID = RID.id)
+"#;
+
+#[derive(Debug, Clone, sqlx::FromRow, SimpleObject)]
+#[graphql(complex, name = "Rid")]
+struct RidRow
+{
+    id: i32,
+    name: String,
+    name_en: Option<String>,
+    description: Option<String>,
+    description_en: Option<String>,
+    /// Registration number, 0 if there is none yet.
+    number: i32,
+    #[graphql(skip)]
+    faculty_id: Option<i32>,
+    #[graphql(skip)]
+    type_id: Option<i32>,
+    #[graphql(skip)]
+    year_id: Option<i32>,
+    #[graphql(skip)]
+    subject_area_id: Option<i32>,
+    #[graphql(skip)]
+    link: Option<String>,
+    #[graphql(skip)]
+    registry_link: Option<String>,
+    /// `filed`, `published`, `granted`, `in_force`, `lapsed` or `expired`.
+    status: Option<String>,
+    /// YYYY-MM-DD
+    status_date: Option<String>,
+    trl: Option<i8>,
+    available: bool,
+    partnership_terms: Option<String>
+}
+
+#[derive(Debug, Clone, sqlx::FromRow, SimpleObject)]
+#[graphql(complex)]
+struct Faculty
+{
+    id: i32,
+    name: String
+}
+
+#[derive(Debug, Clone, sqlx::FromRow, SimpleObject)]
+#[graphql(complex, name = "Type")]
+struct RidType
+{
+    id: i32,
+    name: String,
+    /// `patent`, `registration` or `trademark`.
+    category: Option<String>
+}
+
+#[derive(Debug, Clone, sqlx::FromRow, SimpleObject)]
+#[graphql(complex)]
+struct Year
+{
+    id: i32,
+    year: i64
+}
+
+#[derive(Debug, Clone, sqlx::FromRow, SimpleObject)]
+#[graphql(complex)]
+struct SubjectArea
+{
+    id: i32,
+    name: String
+}
+
+#[derive(Debug, Clone, sqlx::FromRow, SimpleObject)]
+struct YearCount
+{
+    year: Option<i64>,
+    count: i64
+}
+
+#[derive(Debug, Clone, sqlx::FromRow, SimpleObject)]
+#[graphql(complex, name = "BestRid")]
+struct BestRid
+{
+    name: String,
+    nomination: Option<String>,
+    #[graphql(skip)]
+    subject_area_id: Option<i32>,
+    #[graphql(skip)]
+    rid_id: Option<i32>
+}
+
+/// An author; `position` is the place in the contribution order when the
+/// author is reached from a RID.
+#[derive(Debug, Clone)]
+struct AuthorNode(Author);
+
+/// A row that belongs to the key it was loaded for.
+#[derive(Debug, sqlx::FromRow)]
+struct Owned<T>
+{
+    owner: i32,
+    #[sqlx(flatten)]
+    row: T
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct RidId(i32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct AuthorId(i32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct FacultyId(i32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct TypeId(i32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct YearId(i32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct SubjectAreaId(i32);
+
+/// Authors of a RID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct AuthorsOf(i32);
+
+/// Numbers of RIDs of a faculty per year.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct YearCountsOf(i32);
+
+/// RIDs of an author, faculty, type, year or subject area.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum RidsOf
+{
+    Author(i32),
+    Faculty(i32),
+    Type(i32),
+    Year(i32),
+    SubjectArea(i32)
+}
+
+impl RidsOf
+{
+    /// Column holding the owner id and the join it needs.
+    fn column(self) -> (&'static str, &'static str, i32)
+    {
+        match self
+        {
+            RidsOf::Author(id) => ("AUTHORxRID.idAuthor", "join AUTHORxRID on (AUTHORxRID.idRID = RID.id)", id),
+            RidsOf::Faculty(id) => ("RID.idFaculty", "", id),
+            RidsOf::Type(id) => ("RID.idType", "", id),
+            RidsOf::Year(id) => ("RID.idYear", "", id),
+            RidsOf::SubjectArea(id) => ("RID.idSubjectArea", "", id)
+        }
+    }
+}
+
+/// Batches the lookups of one request: every key asked for while resolving
+/// a level of the query is loaded with a single `in (...)` select.
+pub struct Database
+{
+    pool: MySqlPool
+}
+
+type Loaders = Arc<DataLoader<Database>>;
+
+fn failed(err: sqlx::Error) -> Arc<sqlx::Error>
+{
+    error!("{}: graphql", err);
+    Arc::new(err)
+}
+
+fn id_list(ids: impl Iterator<Item = i32>) -> String
+{
+    ids.map(|id| id.to_string()).collect::<Vec<String>>().join(", ")
+}
+
+impl Loader<RidId> for Database
+{
+    type Value = RidRow;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(&self, keys: &[RidId]) -> Result<HashMap<RidId, RidRow>, Self::Error>
+    {
+        let rows: Vec<RidRow> = sqlx::query_as(&format!("select {} where RID.id in ({})", RID_COLUMNS, id_list(keys.iter().map(|key| key.0))))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(failed)?;
+
+        Ok(rows.into_iter().map(|row| (RidId(row.id), row)).collect())
+    }
+}
+
+impl Loader<AuthorId> for Database
+{
+    type Value = Author;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(&self, keys: &[AuthorId]) -> Result<HashMap<AuthorId, Author>, Self::Error>
+    {
+        let rows: Vec<Author> = sqlx::query_as(&format!(r#"
+            select
+                ConnectionAuthorCountry.id as rid,
+                ConnectionAuthorCountry.id as id,
+                coalesce(FIO.surname, '') as surname,
+                FIO.name as name,
+                FIO.lastname as patronymic,
+                AuthorCountry.name as country,
+                null as position
+            from ConnectionAuthorCountry
+            join FIO on (FIO.id = ConnectionAuthorCountry.idFIO)
+            left join AuthorCountry on (AuthorCountry.id = ConnectionAuthorCountry.idCountry)
+            where ConnectionAuthorCountry.id in ({})
+            "#, id_list(keys.iter().map(|key| key.0))))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(failed)?;
+
+        Ok(rows.into_iter().map(|row| (AuthorId(row.id), row)).collect())
+    }
+}
+
+impl Loader<FacultyId> for Database
+{
+    type Value = Faculty;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(&self, keys: &[FacultyId]) -> Result<HashMap<FacultyId, Faculty>, Self::Error>
+    {
+        let rows: Vec<Faculty> = sqlx::query_as(&format!("select id, name from Faculty where id in ({})", id_list(keys.iter().map(|key| key.0))))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(failed)?;
+
+        Ok(rows.into_iter().map(|row| (FacultyId(row.id), row)).collect())
+    }
+}
+
+impl Loader<TypeId> for Database
+{
+    type Value = RidType;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(&self, keys: &[TypeId]) -> Result<HashMap<TypeId, RidType>, Self::Error>
+    {
+        let rows: Vec<RidType> = sqlx::query_as(&format!(
+            "select id, name, cast(category as char) as category from Type where id in ({})",
+            id_list(keys.iter().map(|key| key.0))
+        ))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(failed)?;
+
+        Ok(rows.into_iter().map(|row| (TypeId(row.id), row)).collect())
+    }
+}
+
+impl Loader<YearId> for Database
+{
+    type Value = Year;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(&self, keys: &[YearId]) -> Result<HashMap<YearId, Year>, Self::Error>
+    {
+        let rows: Vec<Year> = sqlx::query_as(&format!(
+            "select id, cast(year as signed) as year from Year where id in ({})",
+            id_list(keys.iter().map(|key| key.0))
+        ))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(failed)?;
+
+        Ok(rows.into_iter().map(|row| (YearId(row.id), row)).collect())
+    }
+}
+
+impl Loader<SubjectAreaId> for Database
+{
+    type Value = SubjectArea;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(&self, keys: &[SubjectAreaId]) -> Result<HashMap<SubjectAreaId, SubjectArea>, Self::Error>
+    {
+        let rows: Vec<SubjectArea> = sqlx::query_as(&format!("select id, name from SubjectArea where id in ({})", id_list(keys.iter().map(|key| key.0))))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(failed)?;
+
+        Ok(rows.into_iter().map(|row| (SubjectAreaId(row.id), row)).collect())
+    }
+}
+
+impl Loader<AuthorsOf> for Database
+{
+    type Value = Vec<Author>;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(&self, keys: &[AuthorsOf]) -> Result<HashMap<AuthorsOf, Vec<Author>>, Self::Error>
+    {
+        let rids: Vec<i32> = keys.iter().map(|key| key.0).collect();
+        let authors = authors::for_rids(&self.pool, &rids).await.map_err(failed)?;
+
+        Ok(authors.into_iter().map(|(rid, authors)| (AuthorsOf(rid), authors)).collect())
+    }
+}
+
+impl Loader<YearCountsOf> for Database
+{
+    type Value = Vec<YearCount>;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(&self, keys: &[YearCountsOf]) -> Result<HashMap<YearCountsOf, Vec<YearCount>>, Self::Error>
+    {
+        let rows: Vec<Owned<YearCount>> = sqlx::query_as(&format!(r#"
+            select RID.idFaculty as owner, cast(Year.year as signed) as year, count(RID.id) as count
+            from RID
+            left join Year on (Year.id = RID.idYear)
+            where RID.idFaculty in ({})
+            group by RID.idFaculty, Year.year
+            order by Year.year desc
+            "#, id_list(keys.iter().map(|key| key.0))))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(failed)?;
+
+        let mut counts: HashMap<YearCountsOf, Vec<YearCount>> = HashMap::new();
+        for row in rows
+        {
+            counts.entry(YearCountsOf(row.owner)).or_default().push(row.row);
+        }
+
+        Ok(counts)
+    }
+}
+
+impl Loader<RidsOf> for Database
+{
+    type Value = Vec<RidRow>;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(&self, keys: &[RidsOf]) -> Result<HashMap<RidsOf, Vec<RidRow>>, Self::Error>
+    {
+        // One select per kind of owner.
+        let mut owners: HashMap<(&str, &str), Vec<(i32, RidsOf)>> = HashMap::new();
+        for key in keys
+        {
+            let (column, join, id) = key.column();
+            owners.entry((column, join)).or_default().push((id, *key));
+        }
+
+        let mut rids: HashMap<RidsOf, Vec<RidRow>> = HashMap::new();
+        for ((column, join), ids) in owners
+        {
+            let rows: Vec<Owned<RidRow>> = sqlx::query_as(&format!(
+                "select {} as owner, {} {} where {} in ({}) order by Year.year desc, RID.id",
+                column,
+                RID_COLUMNS,
+                join,
+                column,
+                id_list(ids.iter().map(|(id, _)| *id))
+            ))
+                .fetch_all(&self.pool)
+                .await
+                .map_err(failed)?;
+
+            let keys: HashMap<i32, RidsOf> = ids.into_iter().collect();
+            for row in rows
+            {
+                if let Some(key) = keys.get(&row.owner)
+                {
+                    rids.entry(*key).or_default().push(row.row);
+                }
+            }
+        }
+
+        Ok(rids)
+    }
+}
+
+fn loaders<'a>(ctx: &Context<'a>) -> &'a Loaders
+{
+    ctx.data_unchecked::<Loaders>()
+}
+
+async fn rids_of(ctx: &Context<'_>, owner: RidsOf) -> Result<Vec<RidRow>>
+{
+    Ok(loaders(ctx).load_one(owner).await?.unwrap_or_default())
+}
+
+#[ComplexObject]
+impl RidRow
+{
+    /// The registry record: the link entered for the RID, otherwise the one
+    /// built from its type's pattern and number.
+    async fn link(&self) -> Option<String>
+    {
+        registry::link(self.link.as_deref(), self.registry_link.as_deref(), self.number)
+    }
+
+    async fn faculty(&self, ctx: &Context<'_>) -> Result<Option<Faculty>>
+    {
+        match self.faculty_id
+        {
+            Some(id) => Ok(loaders(ctx).load_one(FacultyId(id)).await?),
+            None => Ok(None)
+        }
+    }
+
+    #[graphql(name = "type")]
+    async fn rid_type(&self, ctx: &Context<'_>) -> Result<Option<RidType>>
+    {
+        match self.type_id
+        {
+            Some(id) => Ok(loaders(ctx).load_one(TypeId(id)).await?),
+            None => Ok(None)
+        }
+    }
+
+    async fn year(&self, ctx: &Context<'_>) -> Result<Option<Year>>
+    {
+        match self.year_id
+        {
+            Some(id) => Ok(loaders(ctx).load_one(YearId(id)).await?),
+            None => Ok(None)
+        }
+    }
+
+    async fn subject_area(&self, ctx: &Context<'_>) -> Result<Option<SubjectArea>>
+    {
+        match self.subject_area_id
+        {
+            Some(id) => Ok(loaders(ctx).load_one(SubjectAreaId(id)).await?),
+            None => Ok(None)
+        }
+    }
+
+    /// In contribution order.
+    #[graphql(complexity = "LIST * child_complexity")]
+    async fn authors(&self, ctx: &Context<'_>) -> Result<Vec<AuthorNode>>
+    {
+        let authors = loaders(ctx).load_one(AuthorsOf(self.id)).await?.unwrap_or_default();
+
+        Ok(authors.into_iter().map(AuthorNode).collect())
+    }
+}
+
+#[Object(name = "Author")]
+impl AuthorNode
+{
+    async fn id(&self) -> i32
+    {
+        self.0.id
+    }
+
+    async fn surname(&self) -> &str
+    {
+        &self.0.surname
+    }
+
+    async fn name(&self) -> Option<&str>
+    {
+        self.0.name.as_deref()
+    }
+
+    async fn patronymic(&self) -> Option<&str>
+    {
+        self.0.patronymic.as_deref()
+    }
+
+    async fn country(&self) -> Option<&str>
+    {
+        self.0.country.as_deref()
+    }
+
+    async fn position(&self) -> Option<i32>
+    {
+        self.0.position
+    }
+
+    /// Surname, name and patronymic.
+    async fn full_name(&self) -> String
+    {
+        authors::name(&self.0, NameStyle::FULL)
+    }
+
+    #[graphql(complexity = "LIST * child_complexity")]
+    async fn rids(&self, ctx: &Context<'_>) -> Result<Vec<RidRow>>
+    {
+        rids_of(ctx, RidsOf::Author(self.0.id)).await
+    }
+}
+
+#[ComplexObject]
+impl Faculty
+{
+    #[graphql(complexity = "LIST * child_complexity")]
+    async fn rids(&self, ctx: &Context<'_>) -> Result<Vec<RidRow>>
+    {
+        rids_of(ctx, RidsOf::Faculty(self.id)).await
+    }
+
+    /// Number of RIDs per year, the latest first.
+    async fn counts_by_year(&self, ctx: &Context<'_>) -> Result<Vec<YearCount>>
+    {
+        Ok(loaders(ctx).load_one(YearCountsOf(self.id)).await?.unwrap_or_default())
+    }
+}
+
+#[ComplexObject]
+impl RidType
+{
+    #[graphql(complexity = "LIST * child_complexity")]
+    async fn rids(&self, ctx: &Context<'_>) -> Result<Vec<RidRow>>
+    {
+        rids_of(ctx, RidsOf::Type(self.id)).await
+    }
+}
+
+#[ComplexObject]
+impl Year
+{
+    #[graphql(complexity = "LIST * child_complexity")]
+    async fn rids(&self, ctx: &Context<'_>) -> Result<Vec<RidRow>>
+    {
+        rids_of(ctx, RidsOf::Year(self.id)).await
+    }
+}
+
+#[ComplexObject]
+impl SubjectArea
+{
+    #[graphql(complexity = "LIST * child_complexity")]
+    async fn rids(&self, ctx: &Context<'_>) -> Result<Vec<RidRow>>
+    {
+        rids_of(ctx, RidsOf::SubjectArea(self.id)).await
+    }
+}
+
+#[ComplexObject]
+impl BestRid
+{
+    async fn subject_area(&self, ctx: &Context<'_>) -> Result<Option<SubjectArea>>
+    {
+        match self.subject_area_id
+        {
+            Some(id) => Ok(loaders(ctx).load_one(SubjectAreaId(id)).await?),
+            None => Ok(None)
+        }
+    }
+
+    /// The RID with the same number, if it is in the base.
+    async fn rid(&self, ctx: &Context<'_>) -> Result<Option<RidRow>>
+    {
+        match self.rid_id
+        {
+            Some(id) => Ok(loaders(ctx).load_one(RidId(id)).await?),
+            None => Ok(None)
+        }
+    }
+}
+
+pub struct Query;
+
+#[Object]
+impl Query
+{
+    async fn rid(&self, ctx: &Context<'_>, id: i32) -> Result<Option<RidRow>>
+    {
+        Ok(loaders(ctx).load_one(RidId(id)).await?)
+    }
+
+    /// RIDs, the newest first. `year` is a calendar year, the other filters
+    /// are ids; `search` looks in names and descriptions.
+    #[graphql(complexity = "limit.clamp(0, MAX_LIMIT) as usize * child_complexity")]
+    #[allow(clippy::too_many_arguments)]
+    async fn rids(
+        &self,
+        ctx: &Context<'_>,
+        faculty: Option<i32>,
+        #[graphql(name = "type")] rid_type: Option<i32>,
+        year: Option<i32>,
+        subject_area: Option<i32>,
+        search: Option<String>,
+        #[graphql(default = 50)] limit: i32,
+        #[graphql(default = 0)] offset: i32
+    ) -> Result<Vec<RidRow>>
+    {
+        let mut conditions: Vec<&str> = Vec::new();
+        let mut values: Vec<i32> = Vec::new();
+
+        for (condition, value) in [
+            ("RID.idFaculty = ?", faculty),
+            ("RID.idType = ?", rid_type),
+            ("Year.year = ?", year),
+            ("RID.idSubjectArea = ?", subject_area)
+        ]
+        {
+            if let Some(value) = value
+            {
+                conditions.push(condition);
+                values.push(value);
+            }
+        }
+
+        let search = search.map(|text| format!("%{}%", text.trim()));
+        if search.is_some()
+        {
+            conditions.push("(RID.name like ? or RID.description like ? or RID.name_en like ? or RID.description_en like ?)");
+        }
+
+        let sql = format!(
+            "select {} {} order by Year.year desc, RID.id limit {} offset {}",
+            RID_COLUMNS,
+            if conditions.is_empty() { String::default() } else { format!("where {}", conditions.join(" and ")) },
+            limit.clamp(0, MAX_LIMIT),
+            offset.max(0)
+        );
+
+        let mut query = sqlx::query_as(&sql);
+        for value in values
+        {
+            query = query.bind(value);
+        }
+        if let Some(search) = &search
+        {
+            query = query.bind(search).bind(search).bind(search).bind(search);
+        }
+
+        Ok(query.fetch_all(&loaders(ctx).loader().pool).await.map_err(failed)?)
+    }
+
+    async fn author(&self, ctx: &Context<'_>, id: i32) -> Result<Option<AuthorNode>>
+    {
+        Ok(loaders(ctx).load_one(AuthorId(id)).await?.map(AuthorNode))
+    }
+
+    /// Authors by surname; `search` is the beginning of the surname.
+    #[graphql(complexity = "limit.clamp(0, MAX_LIMIT) as usize * child_complexity")]
+    async fn authors(
+        &self,
+        ctx: &Context<'_>,
+        search: Option<String>,
+        #[graphql(default = 50)] limit: i32,
+        #[graphql(default = 0)] offset: i32
+    ) -> Result<Vec<AuthorNode>>
+    {
+        let ids: Vec<i32> = sqlx::query_scalar(&format!(
+            r#"
+            select ConnectionAuthorCountry.id
+            from ConnectionAuthorCountry
+            join FIO on (FIO.id = ConnectionAuthorCountry.idFIO)
+            where FIO.surname like ?
+            order by FIO.surname, FIO.name, ConnectionAuthorCountry.id
+            limit {} offset {}
+            "#,
+            limit.clamp(0, MAX_LIMIT),
+            offset.max(0)
+        ))
+            .bind(format!("{}%", search.as_deref().unwrap_or_default().trim()))
+            .fetch_all(&loaders(ctx).loader().pool)
+            .await
+            .map_err(failed)?;
+
+        let mut authors = loaders(ctx).load_many(ids.iter().map(|id| AuthorId(*id))).await?;
+
+        Ok(ids.iter().filter_map(|id| authors.remove(&AuthorId(*id))).map(AuthorNode).collect())
+    }
+
+    async fn faculty(&self, ctx: &Context<'_>, id: i32) -> Result<Option<Faculty>>
+    {
+        Ok(loaders(ctx).load_one(FacultyId(id)).await?)
+    }
+
+    #[graphql(complexity = "LIST * child_complexity")]
+    async fn faculties(&self, ctx: &Context<'_>) -> Result<Vec<Faculty>>
+    {
+        Ok(sqlx::query_as("select id, name from Faculty order by name")
+            .fetch_all(&loaders(ctx).loader().pool)
+            .await
+            .map_err(failed)?)
+    }
+
+    #[graphql(complexity = "LIST * child_complexity")]
+    async fn types(&self, ctx: &Context<'_>) -> Result<Vec<RidType>>
+    {
+        Ok(sqlx::query_as("select id, name, cast(category as char) as category from Type order by name")
+            .fetch_all(&loaders(ctx).loader().pool)
+            .await
+            .map_err(failed)?)
+    }
+
+    #[graphql(complexity = "LIST * child_complexity")]
+    async fn years(&self, ctx: &Context<'_>) -> Result<Vec<Year>>
+    {
+        Ok(sqlx::query_as("select id, cast(year as signed) as year from Year order by year desc")
+            .fetch_all(&loaders(ctx).loader().pool)
+            .await
+            .map_err(failed)?)
+    }
+
+    #[graphql(complexity = "LIST * child_complexity")]
+    async fn subject_areas(&self, ctx: &Context<'_>) -> Result<Vec<SubjectArea>>
+    {
+        Ok(sqlx::query_as("select id, name from SubjectArea order by name")
+            .fetch_all(&loaders(ctx).loader().pool)
+            .await
+            .map_err(failed)?)
+    }
+
+    /// The featured RIDs of the main page.
+    #[graphql(complexity = "LIST * child_complexity")]
+    async fn best(&self, ctx: &Context<'_>) -> Result<Vec<BestRid>>
+    {
+        Ok(sqlx::query_as(r#"
+            select
+                BestRID.name as name,
+                BestRID.notes as nomination,
+                BestRID.idSubjectArea as subject_area_id,
+                (select min(RID.id) from RID where RID.numPotent = BestRID.numPotent) as rid_id
+            from BestRID
+            "#)
+            .fetch_all(&loaders(ctx).loader().pool)
+            .await
+            .map_err(failed)?)
+    }
+}
+
+/// The read-only schema. `GRAPHQL_MAX_DEPTH` (10 by default) and
+/// `GRAPHQL_MAX_COMPLEXITY` (3000) bound what a single query may ask for;
+/// list fields count `limit` or five times their items.
+pub fn schema() -> RidSchema
+{
+    let limit = |name: &str, default: usize| env::var(name).ok().and_then(|value| value.parse().ok()).unwrap_or(default);
+
+    Schema::build(Query, EmptyMutation, EmptySubscription)
+        .limit_depth(limit("GRAPHQL_MAX_DEPTH", 10))
+        .limit_complexity(limit("GRAPHQL_MAX_COMPLEXITY", 3000))
+        .finish()
+}
+
+/// Loaders live for one HTTP request, so nothing is cached between requests.
+fn request_loaders(pool: &MySqlPool) -> Loaders
+{
+    Arc::new(DataLoader::new(Database { pool: pool.clone() }, tokio::spawn))
+}
+
+/// Queries as JSON, one or a batch of several.
+pub async fn post(schema: web::Data<RidSchema>, batch: web::Json<BatchRequest>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let batch = batch.into_inner().data(request_loaders(pool.get_ref()));
+
+    HttpResponse::Ok().json(schema.execute_batch(batch).await)
+}
+
+/// A query in the query string: `?query=...&variables=...`.
+pub async fn get(req: HttpRequest, schema: web::Data<RidSchema>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    match async_graphql::http::parse_query_string(req.query_string())
+    {
+        Ok(request) => HttpResponse::Ok().json(schema.execute(request.data(request_loaders(pool.get_ref()))).await),
+        Err(err) => HttpResponse::BadRequest()
+            .content_type("text/html; charset=utf-8")
+            .body(err.to_string())
+    }
+}
@@ -1,15 +1,23 @@
 use std::collections::HashMap;
+use std::env;
 
-use actix_web::{web,HttpResponse, Responder};
+use actix_web::{http::header, web, HttpRequest, HttpResponse, Responder};
 use serde::Serialize;
+use utoipa::ToSchema;
 use sqlx::mysql::MySqlPool;
 use regex::Regex;
 use log::error;
 
+use crate::admin;
+use crate::agreements;
+use crate::authors::{self, Author, NameStyle};
+use crate::i18n::{self, t, Locale};
+use crate::registry;
 
 
-#[derive(Serialize, Debug, sqlx::FromRow)] 
-struct BestRID 
+
+#[derive(Serialize, Debug, sqlx::FromRow, ToSchema)]
+pub struct BestRID 
 { 
     name: String, 
     sub_area: String
@@ -20,42 +28,115 @@ struct NominatedRID
 { 
     name: String, 
     nomination: String,
-    authors: String
+    rid: Option<i32>
 } 
 
-#[derive(Serialize, Debug, sqlx::FromRow, Clone)]
-struct SubjectAreaFilter
+#[derive(Serialize, Debug, sqlx::FromRow)]
+struct FactRID
+{
+    id: i32,
+    name: String,
+    number: i32,
+    year: String,
+    rid_type: String
+}
+
+#[derive(Serialize, Debug, sqlx::FromRow, Clone, ToSchema)]
+pub struct SubjectAreaFilter
 {
     sub_area: String,
     sub_area_count: i64
 }
 
-#[derive(Serialize, Debug, sqlx::FromRow, Clone)]
-struct YearFilter
+#[derive(Serialize, Debug, sqlx::FromRow, Clone, ToSchema)]
+pub struct YearFilter
 {
     year: String,
     year_count: i64
 }
 
-#[derive(Serialize, Debug, sqlx::FromRow, Clone)]
-struct FacultyFilter
+#[derive(Serialize, Debug, sqlx::FromRow, Clone, ToSchema)]
+pub struct FacultyFilter
 {
     faculty: String,
     faculty_count: i64
 }
 
-#[derive(Serialize, Debug, sqlx::FromRow, Clone)] 
-struct Rid 
+#[derive(Serialize, Debug, sqlx::FromRow, Clone, ToSchema)]
+pub struct TypeFilter
+{
+    id: i32,
+    rid_type: String,
+    type_count: i64
+}
+
+#[derive(Serialize, Debug, sqlx::FromRow, Clone, ToSchema)]
+pub struct StatusFilter
+{
+    status: String,
+    status_count: i64
+}
+
+#[derive(Serialize, Debug, sqlx::FromRow, Clone, ToSchema)]
+pub struct TrlFilter
+{
+    trl: String,
+    trl_count: i64
+}
+
+/// Search filters with the number of RIDs under each value, as `/counters`
+/// returns them to JSON clients.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct Counters
+{
+    sub_areas: Vec<SubjectAreaFilter>,
+    years: Vec<YearFilter>,
+    faculties: Vec<FacultyFilter>,
+    types: Vec<TypeFilter>,
+    statuses: Vec<StatusFilter>,
+    trls: Vec<TrlFilter>,
+    available: i64
+}
+
+#[derive(Serialize, Debug, sqlx::FromRow)]
+struct TypeDefinition
+{
+    id: i32,
+    name: String,
+    description: Option<String>,
+    legal_basis: Option<String>,
+    protection_term: Option<String>,
+    icon: Option<String>
+}
+
+/// A search result. Names and descriptions are in the language of the request.
+#[derive(Serialize, Debug, sqlx::FromRow, Clone, ToSchema)]
+pub struct Rid 
 { 
+    id: i32,
     name: String, 
     description: String, 
+    /// Registration number, 0 if there is none yet.
     number: i32, 
     faculty: String, 
     rid_type: String, 
     year: String, 
     sub_area: String, 
     link: Option<String>, 
-    authors: String 
+    /// Registry address pattern of the type, with `{number}` in it.
+    registry_link: Option<String>,
+    /// `filed`, `published`, `granted`, `in_force`, `lapsed` or `expired`.
+    status: Option<String>,
+    /// DD.MM.YYYY
+    status_date: Option<String>,
+    agreement_kind: Option<String>,
+    trl: Option<i8>,
+    available: bool,
+    partnership_terms: Option<String>,
+    /// Attachment with a thumbnail, `/attachments/{preview}/thumbnail`.
+    preview: Option<u64>,
+    /// One `id|title` line per attachment.
+    documents: Option<String>
 }
 
 pub async fn favicon() -> impl Responder
@@ -66,7 +147,7 @@ pub async fn favicon() -> impl Responder
 }
 
 
-pub async fn index(name: web::Path<String>, path: web::Data<String>) -> impl Responder
+pub async fn index(req: HttpRequest, name: web::Path<String>, path: web::Data<String>) -> impl Responder
 {
     let body = std::fs::read_to_string(format!("{}/{}/{}.html", path.get_ref(), &name, &name));
     match body
@@ -74,7 +155,9 @@ pub async fn index(name: web::Path<String>, path: web::Data<String>) -> impl Res
         Ok(body) => {
             HttpResponse::Ok()
             .content_type("text/html; charset=utf-8")
-            .body(body)
+            .insert_header(("Content-Language", i18n::locale(&req).code()))
+            .insert_header(("Vary", "Accept-Language"))
+            .body(i18n::page(&req, body, path.get_ref()))
         },
         
         Err(err) => {
@@ -86,7 +169,7 @@ pub async fn index(name: web::Path<String>, path: web::Data<String>) -> impl Res
     }
 }
 
-pub async fn main_page(path: web::Data<String>) -> impl Responder
+pub async fn main_page(req: HttpRequest, path: web::Data<String>) -> impl Responder
 {
     let body = std::fs::read_to_string(format!("{}/main/main.html", path.get_ref()));
     match body
@@ -94,7 +177,9 @@ pub async fn main_page(path: web::Data<String>) -> impl Responder
         Ok(body) => {
             HttpResponse::Ok()
             .content_type("text/html; charset=utf-8")
-            .body(body)
+            .insert_header(("Content-Language", i18n::locale(&req).code()))
+            .insert_header(("Vary", "Accept-Language"))
+            .body(i18n::page(&req, body, path.get_ref()))
         },
         
         Err(err) => {
@@ -173,12 +258,38 @@ pub async fn scripts(name: web::Path<String>, path: web::Data<String>) -> impl R
 //     .body(body)
 // }
 
-pub async fn best(pool: web::Data<MySqlPool>) -> impl Responder 
+/// Whether the client asked for JSON rather than the HTML fragments the pages
+/// insert.
+fn wants_json(req: &HttpRequest) -> bool
+{
+    req.headers()
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("application/json"))
+}
+
+#[utoipa::path(
+    get,
+    path = "/best",
+    tag = "search",
+    responses(
+        (status = 200, description = "Featured RIDs", content(
+            (Vec<BestRID> = "application/json"),
+            (String = "text/html")
+        ))
+    )
+)]
+pub async fn best(req: HttpRequest, pool: web::Data<MySqlPool>) -> impl Responder 
 { 
     let rows:Vec<BestRID> = sqlx::query_as("select BestRID.name as name, SubjectArea.name as sub_area from BestRID left join SubjectArea on (BestRID.idSubjectArea = SubjectArea.id)") 
         .fetch_all(pool.get_ref()) 
         .await 
         .unwrap(); 
+
+    if wants_json(&req)
+    {
+        return HttpResponse::Ok().json(rows);
+    }
  
     let mut body: String = String::default(); 
  
@@ -205,27 +316,26 @@ pub async fn best(pool: web::Data<MySqlPool>) -> impl Responder
     .body(body) 
 }
 
-pub async fn nominated(pool: web::Data<MySqlPool>) -> impl Responder 
+pub async fn nominated(req: HttpRequest, pool: web::Data<MySqlPool>) -> impl Responder 
 { 
     let rows:Vec<NominatedRID> = sqlx::query_as(r#"
         select
             BestRID.name as name,
             BestRID.notes as nomination,
-            group_concat(FIO.surname, ' ', FIO.name, '.', FIO.lastname, '.', ' (', AuthorCountry.name, ')' SEPARATOR ', ') as authors
+            min(RID.id) as rid
         from BestRID
 
-        left join SubjectArea on (BestRID.idSubjectArea = SubjectArea.id)
         left join RID on (BestRID.numPotent = RID.numPotent)
-        left join AUTHORxRID on (AUTHORxRID.idRID = RID.id)
-        left join ConnectionAuthorCountry on (ConnectionAuthorCountry.id = AUTHORxRID.idAuthor)
-        left join AuthorCountry on (AuthorCountry.id = ConnectionAuthorCountry.idCountry)
-        left join FIO on (FIO.id = ConnectionAuthorCountry.idFIO)
-        group by name;
+        group by BestRID.name, BestRID.notes;
         "#) 
         .fetch_all(pool.get_ref()) 
         .await 
         .unwrap(); 
  
+    let rids: Vec<i32> = rows.iter().filter_map(|row| row.rid).collect();
+    let author_map = authors::for_rids(pool.get_ref(), &rids).await.unwrap();
+    let style = NameStyle::for_locale(i18n::locale(&req));
+
     let mut body: String = String::default(); 
  
     for row in rows 
@@ -247,7 +357,7 @@ pub async fn nominated(pool: web::Data<MySqlPool>) -> impl Responder
                 </div>
             "#,
             row.nomination,
-            row.authors,
+            authors::list(row.rid.and_then(|rid| author_map.get(&rid)), style),
             row.name
         )) 
     } 
@@ -256,9 +366,322 @@ pub async fn nominated(pool: web::Data<MySqlPool>) -> impl Responder
     .content_type("text/html, charset=utf-8") 
     .body(body) 
 }
- 
-pub async fn counters(pool: web::Data<MySqlPool>) -> impl Responder 
+
+pub async fn facts(req: HttpRequest, category: web::Path<String>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let locale = i18n::locale(&req);
+
+    let (forms, caption, kinds) = match category.as_str()
+    {
+        "patent" => (
+            ["патент", "патента", "патентов"],
+            " поддерживает РУДН",
+            "изобретения, полезные модели, промышленные образцы"
+        ),
+        "registration" => (
+            ["свидетельство о регистрации", "свидетельства о регистрации", "свидетельств о регистрации"],
+            "",
+            "программ для ЭВМ и баз данных"
+        ),
+        "trademark" => (
+            ["товарный знак", "товарных знака", "товарных знаков"],
+            "",
+            "на русском и английском языках"
+        ),
+        _ => {
+            return HttpResponse::NotFound()
+                .content_type("text/html; charset=utf-8")
+                .body("<h1>Error 404</h1>");
+        }
+    };
+
+    // English names are optional, Russian ones are the fallback.
+    let name = match locale
+    {
+        Locale::En => "coalesce(nullif(RID.name_en, ''), RID.name)",
+        Locale::Ru => "RID.name"
+    };
+
+    let rows: Result<Vec<FactRID>, sqlx::Error> = sqlx::query_as(&format!(r#"
+        select
+            RID.id as id,
+            {} as name,
+            RID.numPotent as number,
+            cast(Year.year as char) as year,
+            Type.name as rid_type
+        from RID
+
+        join Type on (RID.idType = Type.id)
+        join Year on (RID.idYear = Year.id)
+        where Type.category = ?
+        order by Year.year desc, Type.name, RID.numPotent
+        "#, name))
+        .bind(category.as_str())
+        .fetch_all(pool.get_ref())
+        .await;
+
+    let rows = match rows
+    {
+        Ok(rows) => rows,
+        Err(err) => {
+            error!("{}: facts/{}", err, category.as_str());
+            return HttpResponse::ServiceUnavailable()
+                .content_type("text/html, charset=utf-8")
+                .body(err.to_string());
+        }
+    };
+
+    let rids: Vec<i32> = rows.iter().map(|row| row.id).collect();
+    let author_map = match authors::for_rids(pool.get_ref(), &rids).await
+    {
+        Ok(author_map) => author_map,
+        Err(err) => {
+            error!("{}: facts/{}", err, category.as_str());
+            return HttpResponse::ServiceUnavailable()
+                .content_type("text/html, charset=utf-8")
+                .body(err.to_string());
+        }
+    };
+    let style = NameStyle::for_locale(locale);
+
+    let mut years: Vec<(String, Vec<&FactRID>)> = Vec::new();
+
+    for row in &rows
+    {
+        match years.last_mut()
+        {
+            Some((year, rids)) if *year == row.year => rids.push(row),
+            _ => years.push((row.year.clone(), vec![row]))
+        }
+    }
+
+    let total = rows.len() as i64;
+
+    let mut body: String = format!(
+        r#"
+            <div style="display: flex; flex-direction: row; align-items: center; gap: 1rem; justify-content: center;">
+                <span style="background: linear-gradient(to right, #1b2fa3, #a331a3, #ff6f61) text; color: transparent; font-size: 3rem; font-weight: 900;">{0}</span>
+                <p><span style="font-weight: 900;">{1}{2}</span><br>{3}</p>
+            </div>
+        "#,
+        total,
+        i18n::plural(locale, total, forms),
+        t(locale, caption),
+        t(locale, kinds)
+    );
+
+    for (year, rids) in years
+    {
+        // Counts per type, in the order the rows came sorted.
+        let mut by_type: Vec<(&str, i64)> = Vec::new();
+        for row in &rids
+        {
+            match by_type.last_mut()
+            {
+                Some((rid_type, count)) if *rid_type == row.rid_type => *count += 1,
+                _ => by_type.push((&row.rid_type, 1))
+            }
+        }
+
+        let breakdown: Vec<String> = by_type
+            .iter()
+            .map(|(rid_type, count)| format!("{} — {}", count, admin::escape(rid_type)))
+            .collect();
+
+        body.push_str(&format!(
+            r#"
+                <h2 style="background: linear-gradient(to right, #1b2fa3, #a331a3, #ff6f61) text; color: transparent; font-weight: 900; text-align: center; margin: 1rem 0;">{0}</h2>
+                <div style="display: flex; flex-direction: row; align-items: center; gap: 1rem; justify-content: center;">
+                    <span style="background: linear-gradient(to right, #1b2fa3, #a331a3, #ff6f61) text; color: transparent; font-size: 3rem; font-weight: 900;">{1}</span>
+                    <p><span style="font-weight: 900;">{2}</span><br>{3}</p>
+                </div>
+                <div style="display: flex; flex-direction: row; flex-wrap: wrap; justify-content: center; gap: 1rem; margin: 1rem 0;">
+            "#,
+            year,
+            rids.len(),
+            i18n::plural(locale, rids.len() as i64, forms),
+            breakdown.join(", ")
+        ));
+
+        for row in rids
+        {
+            body.push_str(&format!(
+                r#"
+                    <div class="card fade" style="height: auto;">
+                        <div style="margin: 0 0 2rem 0; display: flex; flex-direction: row; justify-content: left; gap: .5rem;">
+                            <h2 style="font-size: .9rem; margin: 0;">
+                                {0} № {1}
+                            </h2>
+                        </div>
+                        <p style="margin: 0 .3rem .5rem 0; font-size: .9rem;">
+                            {2}
+                        </p>
+                        <p class="name-placeholder" style="margin: 0 .3rem .5rem 0; font-size: .9rem;">
+                            <a href="/rids/{3}">{4}</a>
+                        </p>
+                    </div>
+                "#,
+                admin::escape(&row.rid_type),
+                row.number,
+                authors::list(author_map.get(&row.id), style),
+                row.id,
+                admin::escape(&row.name)
+            ));
+        }
+
+        body.push_str("</div>");
+    }
+
+    HttpResponse::Ok()
+        .content_type("text/html, charset=utf-8")
+        .body(body)
+}
+
+pub async fn types(req: HttpRequest, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let locale = i18n::locale(&req);
+
+    let rows: Result<Vec<TypeDefinition>, sqlx::Error> = sqlx::query_as(r#"
+        select
+            Type.id as id,
+            Type.name as name,
+            Type.description as description,
+            Type.legal_basis as legal_basis,
+            Type.protection_term as protection_term,
+            Type.icon as icon
+        from Type
+        order by Type.id
+        "#)
+        .fetch_all(pool.get_ref())
+        .await;
+
+    let rows = match rows
+    {
+        Ok(rows) => rows,
+        Err(err) => {
+            error!("{}: types", err);
+            return HttpResponse::ServiceUnavailable()
+                .content_type("text/html, charset=utf-8")
+                .body(err.to_string());
+        }
+    };
+
+    let mut body: String = String::default();
+
+    for row in rows
+    {
+        let icon = match row.icon
+        {
+            Some(icon) => format!(
+                r#"<img style="block-size: 3rem;" src="https://lib.obs.ru-moscow-1.hc.sbercloud.ru:443/PATENTS/patent-types/{}">"#,
+                icon
+            ),
+            None => String::default()
+        };
+
+        let mut terms: Vec<String> = Vec::new();
+        if let Some(legal_basis) = row.legal_basis
+        {
+            terms.push(legal_basis);
+        }
+        if let Some(protection_term) = row.protection_term
+        {
+            terms.push(format!("{} {}", t(locale, "срок охраны:"), protection_term));
+        }
+
+        body.push_str(&format!(
+            r#"
+                <div class="card fade" style="display: none; height: auto; min-height: 17rem;">
+                    <div style="margin: 0 0 2rem 0; display: flex; flex-direction: row; justify-content: left; gap: 1rem; align-items: center;">
+                        {0}
+                        <h2 style="font-size: 1.2rem; margin: 0;">
+                            <a href="{5}/portfolio?t={1}" style="color: inherit; text-decoration: none;">
+                                {2}
+                            </a>
+                        </h2>
+                    </div>
+                    <p style="margin: 0 .3rem .5rem 0; font-size: 1rem;">
+                        {3}
+                    </p>
+                    <p style="margin: 0 .3rem .5rem 0; font-size: .9rem; color: #5f5f5f;">
+                        {4}
+                    </p>
+                </div>
+            "#,
+            icon,
+            row.id,
+            row.name,
+            row.description.unwrap_or_default(),
+            terms.join("<br>"),
+            i18n::prefix(&req)
+        ));
+    }
+
+    HttpResponse::Ok()
+        .content_type("text/html, charset=utf-8")
+        .body(body)
+}
+
+/// Appends a collapsed filter block to the `counters` sidebar. Every item is
+/// `(value sent to /find, label, count)`; `key` is the `/find` filter key.
+fn push_facet(body: &mut String, index: usize, title: &str, prefix: &str, key: &str, items: &[(String, String, i64)])
+{
+    body.push_str(&format!(
+        r#"
+        <div class="category-type">
+            <button type="button" class="category-header" aria-expanded="true" onclick="hideCategoryList('category-{0}')">
+                {1}<img style="block-size: 1rem; transform: rotate(180deg);" id="category-{0}" src="https://lib.obs.ru-moscow-1.hc.sbercloud.ru:443/PATENTS/expand.svg">
+            </button>
+
+            <div id="category-list-{0}" class="category-list" style="display: none;">
+        "#,
+        index,
+        title
+    ));
+
+    for (i, (value, label, count)) in items.iter().enumerate()
+    {
+        body.push_str(&format!(
+            r#"
+                <div class="category">
+                        <div>
+                            <input type="checkbox" id="{0}-{1}" name="check" onclick="addQuery('{2}','{3}')"/>
+                            <label for="{0}-{1}">{4}</label>
+                        </div>
+                    <div class="category-count">{5}</div>
+                </div>
+            "#,
+            prefix,
+            i,
+            key,
+            value,
+            label,
+            count
+        ));
+    }
+
+    body.push_str(r#"
+            </div>
+        </div>
+    "#);
+}
+
+#[utoipa::path(
+    get,
+    path = "/counters",
+    tag = "search",
+    params(("Accept-Language" = Option<String>, Header, description = "Language of the HTML labels")),
+    responses(
+        (status = 200, description = "Values of every search filter with the number of RIDs", content(
+            (Counters = "application/json"),
+            (String = "text/html")
+        ))
+    )
+)]
+pub async fn counters(req: HttpRequest, pool: web::Data<MySqlPool>) -> impl Responder 
 { 
+    let locale = i18n::locale(&req);
+
     let sub_area_count_map:Vec<SubjectAreaFilter> = sqlx::query_as(r#" 
         select 
             SubjectArea.name as sub_area, 
@@ -307,14 +730,80 @@ pub async fn counters(pool: web::Data<MySqlPool>) -> impl Responder
         .await 
         .unwrap(); 
  
-    let mut body: String = String::from(r#" 
+    let type_count_map:Vec<TypeFilter> = sqlx::query_as(r#"
+        select
+            Type.id as id,
+            Type.name as rid_type,
+            COUNT(RID.id) AS type_count
+        from RID
+
+        join Type on (RID.idType = Type.id)
+        GROUP BY
+            Type.id, Type.name
+        order by
+            type_count desc
+        "#)
+        .fetch_all(pool.get_ref())
+        .await
+        .unwrap();
+
+    let status_count_map:Vec<StatusFilter> = sqlx::query_as(r#"
+        select
+            cast(CurrentStatus.status as char) as status,
+            COUNT(RID.id) AS status_count
+        from RID
+
+        join CurrentStatus on (CurrentStatus.idRID = RID.id)
+        GROUP BY
+            CurrentStatus.status
+        order by
+            status_count desc
+        "#)
+        .fetch_all(pool.get_ref())
+        .await
+        .unwrap();
+
+    let trl_count_map:Vec<TrlFilter> = sqlx::query_as(r#"
+        select
+            cast(RID.trl as char) as trl,
+            COUNT(RID.id) AS trl_count
+        from RID
+        where RID.trl is not null
+        GROUP BY
+            RID.trl
+        order by
+            RID.trl
+        "#)
+        .fetch_all(pool.get_ref())
+        .await
+        .unwrap();
+
+    let available_count: i64 = sqlx::query_scalar("select COUNT(RID.id) from RID where RID.available = 1")
+        .fetch_one(pool.get_ref())
+        .await
+        .unwrap();
+
+    if wants_json(&req)
+    {
+        return HttpResponse::Ok().json(Counters {
+            sub_areas: sub_area_count_map,
+            years: year_count_map,
+            faculties: faculty_count_map,
+            types: type_count_map,
+            statuses: status_count_map,
+            trls: trl_count_map,
+            available: available_count
+        });
+    }
+
+    let mut body: String = format!(r#" 
         <div class="category-type"> 
             <button type="button" class="category-header" onclick="hideCategoryList('category-1')"> 
-                Предметная область<img style="block-size: 1rem; transform: rotate(0);" id="category-1" src="https://lib.obs.ru-moscow-1.hc.sbercloud.ru:443/PATENTS/expand.svg"> 
+                {}<img style="block-size: 1rem; transform: rotate(0);" id="category-1" src="https://lib.obs.ru-moscow-1.hc.sbercloud.ru:443/PATENTS/expand.svg"> 
             </button> 
  
             <div id="category-list-1" class="category-list"> 
-    "#); 
+    "#, t(locale, "Предметная область")); 
  
     for (i, item) in sub_area_count_map.iter().enumerate() 
     { 
@@ -334,17 +823,17 @@ pub async fn counters(pool: web::Data<MySqlPool>) -> impl Responder
         )); 
     } 
  
-    body.push_str(r#" 
+    body.push_str(&format!(r#" 
             </div> 
         </div> 
  
         <div class="category-type"> 
             <button type="button" class="category-header" onclick="hideCategoryList('category-2')"> 
-                Год<img style="block-size: 1rem; transform: rotate(180deg);" id="category-2" src="https://lib.obs.ru-moscow-1.hc.sbercloud.ru:443/PATENTS/expand.svg">
+                {}<img style="block-size: 1rem; transform: rotate(180deg);" id="category-2" src="https://lib.obs.ru-moscow-1.hc.sbercloud.ru:443/PATENTS/expand.svg">
                 </button> 
  
             <div id="category-list-2" class="category-list" style="display:none;"> 
-    "#); 
+    "#, t(locale, "Год"))); 
  
     for (i, item) in year_count_map.iter().enumerate() 
     { 
@@ -364,17 +853,17 @@ pub async fn counters(pool: web::Data<MySqlPool>) -> impl Responder
         )); 
     } 
  
-    body.push_str(r#" 
+    body.push_str(&format!(r#" 
             </div> 
         </div> 
  
-        <div class="category-type" style="margin-bottom: 10rem;"> 
+        <div class="category-type"> 
             <button type="button" class="category-header" aria-expanded="true" onclick="hideCategoryList('category-3')"> 
-                Факультеты<img style="block-size: 1rem; transform: rotate(180deg);" id="category-3" src="https://lib.obs.ru-moscow-1.hc.sbercloud.ru:443/PATENTS/expand.svg"> 
+                {}<img style="block-size: 1rem; transform: rotate(180deg);" id="category-3" src="https://lib.obs.ru-moscow-1.hc.sbercloud.ru:443/PATENTS/expand.svg"> 
             </button> 
  
             <div id="category-list-3" class="category-list" style="display: none;"> 
-    "#); 
+    "#, t(locale, "Факультеты"))); 
  
     for (i, item) in faculty_count_map.iter().enumerate() 
     { 
@@ -398,233 +887,407 @@ pub async fn counters(pool: web::Data<MySqlPool>) -> impl Responder
             </div> 
         </div> 
     "#); 
+
+    let type_items: Vec<(String, String, i64)> = type_count_map
+        .into_iter()
+        .map(|item| (item.id.to_string(), item.rid_type, item.type_count))
+        .collect();
+
+    push_facet(&mut body, 4, t(locale, "Вид"), "type", "t", &type_items);
+
+    let status_items: Vec<(String, String, i64)> = status_count_map
+        .into_iter()
+        .map(|item| (item.status.clone(), status_label(locale, &item.status).to_string(), item.status_count))
+        .collect();
+
+    push_facet(&mut body, 5, t(locale, "Статус"), "status", "st", &status_items);
+
+    let trl_items: Vec<(String, String, i64)> = trl_count_map
+        .into_iter()
+        .map(|item| (item.trl.clone(), format!("TRL {}", item.trl), item.trl_count))
+        .collect();
+
+    push_facet(&mut body, 6, t(locale, "Уровень готовности"), "trl", "trl", &trl_items);
+
+    push_facet(&mut body, 7, t(locale, "Лицензирование"), "available", "av", &[
+        ("1".to_string(), t(locale, "Доступно для лицензирования и партнёрства").to_string(), available_count)
+    ]);
+
+    body.push_str(r#"<div style="margin-bottom: 10rem;"></div>"#);
      
     HttpResponse::Ok() 
         .content_type("text/html, charset=utf-8") 
         .body(body) 
 } 
  
-pub async fn find(search_request: web::Json<HashMap<String, serde_json::Value>>, pool: web::Data<MySqlPool>) -> impl Responder 
-{ 
-    let mut basepart: String = String::from(r#" 
-        select
-            RID.name as name,
-            RID.description as description,
-            RID.numPotent as number,
-            Faculty.name as faculty, 
-            Type.name as rid_type, cast(Year.year as char) as year,
-            SubjectArea.name as sub_area,
-            RID.link as link, 
-            group_concat(FIO.surname, ' ', FIO.name, '.', FIO.lastname, '.', ' (', AuthorCountry.name, ')' SEPARATOR ', ') as authors
+pub fn status_label(locale: Locale, status: &str) -> &str
+{
+    let label = match status
+    {
+        "filed" => "Заявка подана",
+        "published" => "Заявка опубликована",
+        "granted" => "Выдан охранный документ",
+        "in_force" => "Действует",
+        "lapsed" => "Прекратил действие",
+        "expired" => "Срок действия истёк",
+        _ => return status
+    };
+
+    t(locale, label)
+}
+
+/// Card tag with the current legal status, empty if none was recorded yet.
+fn status_tag(locale: Locale, status: Option<&str>, date: Option<&str>) -> String
+{
+    match (status, date)
+    {
+        (Some(status), Some(date)) => format!(r#"<a href="" class="category-tag">{} {} {}</a>"#, status_label(locale, status), t(locale, "с"), date),
+        (Some(status), None) => format!(r#"<a href="" class="category-tag">{}</a>"#, status_label(locale, status)),
+        _ => String::default()
+    }
+}
+
+/// TRL and availability tags shown next to the type, area and faculty tags.
+fn readiness_tags(locale: Locale, trl: Option<i8>, available: bool) -> String
+{
+    let mut tags = String::default();
+
+    if let Some(trl) = trl
+    {
+        tags.push_str(&format!(r#"<a href="" class="category-tag">TRL {}</a>"#, trl));
+    }
+    if available
+    {
+        tags.push_str(&format!(r#"<a href="" class="category-tag">{}</a>"#, t(locale, "Доступно для лицензирования")));
+    }
+
+    tags
+}
+
+fn partnership_terms(locale: Locale, available: bool, terms: Option<&str>) -> String
+{
+    match (available, terms)
+    {
+        (true, Some(terms)) => format!(
+            r#"<br><br><span style="font-weight: 900;">{}</span> {}"#,
+            t(locale, "Условия лицензирования и партнёрства:"),
+            admin::escape(terms)
+        ),
+        _ => String::default()
+    }
+}
+
+/// Tag linking to the registry record when no link was entered by hand.
+fn registry_tag(locale: Locale, manual: Option<&str>, pattern: Option<&str>, number: i32) -> String
+{
+    if manual.is_some_and(|link| !link.trim().is_empty())
+    {
+        return String::default();
+    }
+
+    match registry::generated(pattern, number)
+    {
+        Some(link) => format!(r#"<a href="{}" class="category-tag" target="_blank">{}</a>"#, link, t(locale, "Запись в реестре")),
+        None => String::default()
+    }
+}
+
+fn citation_links(locale: Locale, id: i32) -> String
+{
+    format!(
+        r#"<br><br><a href="/rids/{1}" target="_blank">{2}</a><br><span style="font-weight: 900;">{0}</span> <a href="/rids/{1}/cite" target="_blank">ГОСТ</a> | <a href="/rids/{1}/cite?format=bibtex">BibTeX</a> | <a href="/rids/{1}/cite?format=ris">RIS</a> | <a href="/rids/{1}/cite?format=csl">CSL-JSON</a>"#,
+        t(locale, "Цитировать:"),
+        id,
+        t(locale, "Страница РИД")
+    )
+}
+
+fn preview(attachment: Option<u64>) -> String
+{
+    match attachment
+    {
+        Some(id) => format!(
+            r#"<img src="/attachments/{}/thumbnail" alt="" style="max-block-size: 10rem; max-inline-size: 100%; margin: 0 0 1rem 0; border-radius: 7px;">"#,
+            id
+        ),
+        None => String::default()
+    }
+}
+
+/// Download links for the attachments listed as `id|title` lines.
+fn documents(locale: Locale, list: Option<&str>) -> String
+{
+    let links: Vec<String> = list
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_once('|'))
+        .map(|(id, title)| format!(r#"<a href="/attachments/{}">{}</a>"#, id, admin::escape(title)))
+        .collect();
+
+    if links.is_empty()
+    {
+        return String::default();
+    }
+
+    format!(r#"<br><br><span style="font-weight: 900;">{}</span><br>{}"#, t(locale, "Документы:"), links.join("<br>"))
+}
+
+/// Builds `(column = v1 or column = v2 ...)` from a JSON array of filter values,
+/// stripping every value with `sanitizer` first. Returns None if nothing is left.
+fn any_of(column: &str, values: &serde_json::Value, sanitizer: &Regex, quoted: bool) -> Option<String>
+{
+    let values: Vec<String> = values
+        .as_array()?
+        .iter()
+        .filter_map(|value| value.as_str())
+        .map(|value| sanitizer.replace_all(value, "").to_string())
+        .filter(|value| !value.is_empty())
+        .map(|value| if quoted { format!("{} = '{}'", column, value) } else { format!("{} = {}", column, value) })
+        .collect();
+
+    if values.is_empty()
+    {
+        return None;
+    }
+
+    Some(format!("({})", values.join(" or ")))
+}
+
+/// One `/find` result. Everything entered by hand is escaped: names and
+/// descriptions also come from inventors through converted disclosures.
+fn card(locale: Locale, i: usize, row: &Rid, authors: Option<&Vec<Author>>) -> String
+{
+    format!( 
+        r#" 
+            <div class="card-container" style="display:none;"> 
+                <div class="card-content"> 
+                    {14}
+                    <h1 style="font-size: 1.1rem; margin: 0 0 .5rem 0;"> 
+                        {3} 
+                    </h1> 
+                    <h2 id="tech-name" style="font-size: 1.3rem; margin: 0 0 1rem 0;"> 
+                        <a class="tech-name-title" href="{8}"> 
+                            {1} 
+                        </a> 
+                    </h2> 
+                    <p style="font-size: 1.1rem; margin: 0 0 .3rem 0;"> 
+                        {9} 
+                    </p> 
+                    <p style="font-size: .9rem; margin: 0 0 1rem 0; color: #5f5f5f;"> 
+                        {6} 
+                    </p>
+                    <div class="category-tags"> 
+                        <a href="" class="category-tag">{5}</a> 
+                        <a href="" class="category-tag">{7}</a> 
+                        <a href="" class="category-tag">{4}</a> 
+                        {10}
+                        {11}
+                        {12}
+                        {17}
+                    </div> 
+                     
+                    <button type="button" class="description-toggle" aria-expanded="true" onclick="showDescribtion('patent-{0}')" style="margin-top: 1rem;"> 
+                        {16}<img style="block-size: 1rem; transform: rotate(180deg);" id="patent-{0}" src="https://lib.obs.ru-moscow-1.hc.sbercloud.ru:443/PATENTS/expand.svg"> 
+                    </button> 
+
+                    <p id="patent-{0}-desc" class="category-list" style="display: none;"> 
+                        {2} 
+                        {13}
+                        {15}
+                        {18}
+                    </p> 
+                </div> 
+            </div> 
+        "#, 
+        i, 
+        admin::escape(&row.name), // 1 
+        admin::escape(&row.description), // 2 
+        row.number, // 3 
+        admin::escape(&row.faculty), // 4 
+        admin::escape(&row.rid_type), // 5 
+        admin::escape(&row.year), // 6 
+        admin::escape(&row.sub_area), // 7 
+        registry::link(row.link.as_deref(), row.registry_link.as_deref(), row.number).unwrap_or_default(), // 8 
+        admin::escape(&authors::list(authors, NameStyle::for_locale(locale))), // 9
+        status_tag(locale, row.status.as_deref(), row.status_date.as_deref()), // 10
+        agreements::badge(locale, row.agreement_kind.as_deref()), // 11
+        readiness_tags(locale, row.trl, row.available), // 12
+        partnership_terms(locale, row.available, row.partnership_terms.as_deref()), // 13
+        preview(row.preview), // 14
+        documents(locale, row.documents.as_deref()), // 15
+        t(locale, "Описание"), // 16
+        registry_tag(locale, row.link.as_deref(), row.registry_link.as_deref(), row.number), // 17
+        citation_links(locale, row.id) // 18 
+    )
+}
+
+/// Address of the site for links read elsewhere (harvesters, feeds, search
+/// engines): `PUBLIC_URL` if set, otherwise the scheme and host of the request.
+pub fn base_url(req: &HttpRequest) -> String
+{
+    match env::var("PUBLIC_URL")
+    {
+        Ok(url) if !url.is_empty() => url.trim_end_matches('/').to_string(),
+        _ => {
+            let info = req.connection_info();
+            format!("{}://{}", info.scheme(), info.host())
+        }
+    }
+}
+
+/// Tables the `/find` filters refer to, to follow the selected columns.
+pub const FILTERED_RIDS: &str = r#"
         from RID
 
+        left join CurrentStatus on (CurrentStatus.idRID = RID.id)
         left join Faculty on (RID.idFaculty = Faculty.id)
         left join Type on (RID.idType = Type.id)
         left join Year on (RID.idYear = Year.id)
         left join SubjectArea on (RID.idSubjectArea = SubjectArea.id)
-        left join AUTHORxRID on (AUTHORxRID.idRID = RID.id)
-        left join ConnectionAuthorCountry on (ConnectionAuthorCountry.id = AUTHORxRID.idAuthor)
-        left join AuthorCountry on (AuthorCountry.id = ConnectionAuthorCountry.idCountry)
-        left join FIO on (FIO.id = ConnectionAuthorCountry.idFIO)
-    "#); 
- 
-    let mut strpart: String = String::default(); 
-    let mut ypart: String = String::default(); 
-    let mut spart: String = String::default(); 
-    let mut dpart: String = String::default(); 
- 
-    for (key, val) in search_request.into_inner() 
+"#;
+
+/// The ` where ...` part for a `/find` request body, empty without filters.
+/// Endpoints exporting search results take the same body.
+pub fn filter(search_request: HashMap<String, serde_json::Value>) -> String
+{
+    let mut conditions: Vec<String> = Vec::new();
+    let literals = Regex::new(r"[^а-яА-Яa-zA-Z0-9 ]").unwrap();
+    let numericals = Regex::new(r"[^0-9]").unwrap();
+    let codes = Regex::new(r"[^a-z_]").unwrap();
+
+    for (key, val) in search_request
     {
-        let literals = Regex::new(r"[^а-яА-Яa-zA-Z0-9 ]").unwrap();
-        let numericals = Regex::new(r"[^0-9]").unwrap();
-        match key.as_str() { 
-            "str" =>  
-            {
-                strpart.push_str(&format!(" ((lower(RID.name) like lower('%{0}%')) or (lower(RID.description) like lower('%{0}%')))", literals.replace_all(val.as_str().unwrap(), ""))); 
-            }, 
- 
-            "y" =>
+        let condition = match key.as_str()
+        {
+            "str" =>
             {
-                ypart.push_str(&format!(" (Year.year = {}", numericals.replace_all(val.as_array().unwrap()[0].as_str().unwrap(), ""))); 
- 
-                if val.as_array().unwrap().len() == 1 
-                {
-                    ypart.push(')');
-                    continue; 
-                }
- 
-                for year in &val.as_array().unwrap()[1..] 
-                { 
-                    ypart.push_str(&format!(" or Year.year = {}", numericals.replace_all(year.as_str().unwrap(), ""))); 
-                }
-                ypart.push(')');
-
-            }, 
- 
-            "s" => 
-            { 
-                spart.push_str(&format!(" (SubjectArea.name = '{}'", literals.replace_all(val.as_array().unwrap()[0].as_str().unwrap(), ""))); 
- 
-                if val.as_array().unwrap().len() == 1 
-                {
-                    spart.push(')');
-                    continue; 
-                } 
- 
-                for sub_area in &val.as_array().unwrap()[1..] 
-                { 
-                    spart.push_str(&format!(" or SubjectArea.name = '{}'", literals.replace_all(sub_area.as_str().unwrap(), ""))); 
-                }
-                spart.push(')');
-            }, 
- 
-            "d" => 
-            { 
-                dpart.push_str(&format!(" (Faculty.name = '{}'", literals.replace_all(val.as_array().unwrap()[0].as_str().unwrap(), ""))); 
- 
-                if val.as_array().unwrap().len() == 1 
-                {
-                    dpart.push(')');
-                    continue; 
-                } 
- 
-                for faculty in &val.as_array().unwrap()[1..] 
-                { 
-                    dpart.push_str(&format!(" or Faculty.name = '{}'", literals.replace_all(faculty.as_str().unwrap(), ""))); 
-                }
-                dpart.push(')');
-            }, 
- 
+                val.as_str().map(|text| format!(
+                    "((lower(RID.name) like lower('%{0}%')) or (lower(RID.description) like lower('%{0}%')) or (lower(RID.name_en) like lower('%{0}%')) or (lower(RID.description_en) like lower('%{0}%')))",
+                    literals.replace_all(text, "")
+                ))
+            },
+            "y" => any_of("Year.year", &val, &numericals, false),
+            "s" => any_of("SubjectArea.name", &val, &literals, true),
+            "d" => any_of("Faculty.name", &val, &literals, true),
+            "t" => any_of("Type.id", &val, &numericals, false),
+            "st" => any_of("CurrentStatus.status", &val, &codes, true),
+            "trl" => any_of("RID.trl", &val, &numericals, false),
+            "av" => any_of("RID.available", &val, &numericals, false),
             _ => continue,
-        } 
-    } 
- 
-    let mut is_first: bool = true; 
- 
-    if !dpart.trim().is_empty() 
-    { 
-        is_first = if is_first {
-            basepart.push_str(" where");
-            false
-        } 
-        else
-        { 
-            basepart.push_str(" and"); 
-            false 
-        }; 
-        basepart.push_str(&dpart); 
-    } 
- 
-    if !ypart.trim().is_empty() 
-    { 
-        is_first = if is_first {
-            basepart.push_str(" where");
-            false
-        } 
-        else
-        { 
-            basepart.push_str(" and"); 
-            false 
         };
-        basepart.push_str(&ypart); 
-    } 
- 
-    if !spart.trim().is_empty() 
-    { 
-        is_first = if is_first {
-            basepart.push_str(" where");
-            false
-        } 
-        else
-        { 
-            basepart.push_str(" and"); 
-            false 
-        };
-        basepart.push_str(&spart); 
-    } 
- 
-    if !strpart.trim().is_empty() 
-    {
-        if is_first
+
+        if let Some(condition) = condition
         {
-            basepart.push_str(" where");
-        } 
-        else
-        { 
-            basepart.push_str(" and");  
+            conditions.push(condition);
         }
-        basepart.push_str(&strpart);
-    } 
+    }
+
+    if conditions.is_empty()
+    {
+        return String::default();
+    }
+
+    format!(" where {}", conditions.join(" and "))
+}
+
+#[utoipa::path(
+    post,
+    path = "/find",
+    tag = "search",
+    params(("Accept-Language" = Option<String>, Header, description = "`en` for English names and descriptions where they exist")),
+    request_body(
+        content = Object,
+        description = "Filters, all optional: `str` is a text to look for in names and descriptions; the others are lists matching any of their values. `y` years, `s` subject areas, `d` faculties (names), `t` type ids, `st` statuses, `trl` readiness levels, `av` `[\"1\"]` for RIDs available for licensing.",
+        example = json!({ "str": "сплав", "y": ["2023", "2024"], "t": ["1"] })
+    ),
+    responses(
+        (status = 200, description = "Matching RIDs, the newest first", content(
+            (Vec<Rid> = "application/json"),
+            (String = "text/html")
+        )),
+        (status = 503, description = "Database error")
+    )
+)]
+pub async fn find(req: HttpRequest, search_request: web::Json<HashMap<String, serde_json::Value>>, pool: web::Data<MySqlPool>) -> impl Responder 
+{ 
+    let locale = i18n::locale(&req);
+
+    // English names and descriptions are optional, Russian ones are the fallback.
+    let (name, description) = match locale
+    {
+        Locale::En => ("coalesce(nullif(RID.name_en, ''), RID.name)", "coalesce(nullif(RID.description_en, ''), RID.description)"),
+        Locale::Ru => ("RID.name", "RID.description")
+    };
+
+    let mut basepart: String = format!(r#" 
+        select
+            RID.id as id,
+            {} as name,
+            {} as description,
+            RID.numPotent as number,
+            Faculty.name as faculty, 
+            Type.name as rid_type, cast(Year.year as char) as year,
+            SubjectArea.name as sub_area,
+            RID.link as link, 
+            Type.registry_link as registry_link,
+            cast(CurrentStatus.status as char) as status,
+            date_format(CurrentStatus.date, '%d.%m.%Y') as status_date,
+            (select cast(Agreement.kind as char) from Agreement
+                join AGREEMENTxRID on (AGREEMENTxRID.idAgreement = Agreement.id)
+                where AGREEMENTxRID.idRID = RID.id and Agreement.status = 'active' and Agreement.public = 1
+                order by Agreement.date_signed desc limit 1) as agreement_kind,
+            RID.trl as trl,
+            RID.available as available,
+            RID.partnership_terms as partnership_terms,
+            (select min(Attachment.id) from Attachment
+                where Attachment.idRID = RID.id and Attachment.has_thumbnail = 1) as preview,
+            (select group_concat(Attachment.id, '|', Attachment.title order by Attachment.id SEPARATOR '\n') from Attachment
+                where Attachment.idRID = RID.id) as documents
+        {}
+    "#, name, description, FILTERED_RIDS); 
  
-    basepart.push_str(" group by RID.name order by Year.year desc");
+    basepart.push_str(&filter(search_request.into_inner()));
+    basepart.push_str(" order by Year.year desc");
 
     // return HttpResponse::Ok() 
     //                 .content_type("text/html, charset=utf-8") 
     //                 .body(basepart); 
  
     let rows:Result<Vec<Rid>, sqlx::Error>  = sqlx::query_as(&basepart) 
+
         .fetch_all(pool.get_ref()) 
         .await; 
  
     match rows 
     { 
         Ok(rows) => { 
+            if wants_json(&req)
+            {
+                return HttpResponse::Ok().json(rows);
+            }
+
             if rows.is_empty() 
             { 
                 return HttpResponse::Ok() 
                     .content_type("text/html, charset=utf-8") 
-                    .body("<h2> По запросу нет подходящих результатов </h2>"); 
+                    .body(format!("<h2> {} </h2>", t(locale, "По запросу нет подходящих результатов"))); 
             } 
  
+            let rids: Vec<i32> = rows.iter().map(|row| row.id).collect();
+            let author_map = match authors::for_rids(pool.get_ref(), &rids).await
+            {
+                Ok(author_map) => author_map,
+                Err(error) => {
+                    return HttpResponse::ServiceUnavailable()
+                        .content_type("text/html, charset=utf-8")
+                        .body(error.to_string());
+                }
+            };
             let mut body: String = String::default(); 
  
             for (i, row) in rows.iter().enumerate() 
             { 
-                body.push_str(&format!( 
-                    r#" 
-                        <div class="card-container" style="display:none;"> 
-                            <div class="card-content"> 
-                                <h1 style="font-size: 1.1rem; margin: 0 0 .5rem 0;"> 
-                                    {3} 
-                                </h1> 
-                                <h2 id="tech-name" style="font-size: 1.3rem; margin: 0 0 1rem 0;"> 
-                                    <a class="tech-name-title" href="{8}"> 
-                                        {1} 
-                                    </a> 
-                                </h2> 
-                                <p style="font-size: 1.1rem; margin: 0 0 .3rem 0;"> 
-                                    {9} 
-                                </p> 
-                                <p style="font-size: .9rem; margin: 0 0 1rem 0; color: #5f5f5f;"> 
-                                    {6} 
-                                </p>
-                                <div class="category-tags"> 
-                                    <a href="" class="category-tag">{5}</a> 
-                                    <a href="" class="category-tag">{7}</a> 
-                                    <a href="" class="category-tag">{4}</a> 
-                                </div> 
-                                 
-                                <button type="button" class="description-toggle" aria-expanded="true" onclick="showDescribtion('patent-{0}')" style="margin-top: 1rem;"> 
-                                    Описание<img style="block-size: 1rem; transform: rotate(180deg);" id="patent-{0}" src="https://lib.obs.ru-moscow-1.hc.sbercloud.ru:443/PATENTS/expand.svg"> 
-                                </button> 
- 
-                                <p id="patent-{0}-desc" class="category-list" style="display: none;"> 
-                                    {2} 
-                                </p> 
-                            </div> 
-                        </div> 
-                    "#, 
-                    i, 
-                    row.name, // 1 
-                    row.description, // 2 
-                    row.number, // 3 
-                    row.faculty, // 4 
-                    row.rid_type, // 5 
-                    row.year, // 6 
-                    row.sub_area, // 7 
-                    row.clone().link.unwrap_or("".to_string()).to_string(), // 8 
-                    row.authors // 9 
-                )); 
+                body.push_str(&card(locale, i, row, author_map.get(&row.id)));
             } 
  
             return HttpResponse::Ok() 
@@ -681,4 +1344,46 @@ pub async fn not_found() -> impl Responder
     HttpResponse::NotFound() 
         .content_type("text/html; charset=utf-8") 
         .body("<h1>Error 404</h1>") 
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    /// A RID as a converted disclosure leaves it: title, description and file
+    /// names are the inventor's.
+    fn disclosed(title: &str) -> Rid
+    {
+        Rid {
+            id: 1,
+            name: title.to_string(),
+            description: format!("{} description", title),
+            number: 0,
+            faculty: "Faculty".to_string(),
+            rid_type: "Type".to_string(),
+            year: "2026".to_string(),
+            sub_area: "Area".to_string(),
+            link: None,
+            registry_link: None,
+            status: None,
+            status_date: None,
+            agreement_kind: None,
+            trl: None,
+            available: true,
+            partnership_terms: Some(title.to_string()),
+            preview: None,
+            documents: Some(format!("7|{}.pdf", title))
+        }
+    }
+
+    #[test]
+    fn card_escapes_disclosed_text()
+    {
+        let card = card(Locale::Ru, 0, &disclosed("<script>alert(1)</script>"), None);
+
+        assert!(!card.contains("<script>"));
+        assert!(card.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+        assert!(card.contains(r#"<a href="/attachments/7">&lt;script&gt;alert(1)&lt;/script&gt;.pdf</a>"#));
+    }
+}
@@ -0,0 +1,423 @@
+use std::collections::HashMap;
+
+use actix_web::{http::header, web, HttpResponse, Responder};
+use serde_json::Value;
+use sqlx::mysql::{MySqlConnection, MySqlPool};
+use sqlx::Connection;
+use log::error;
+
+use crate::admin::{self, escape};
+use crate::handlers;
+use crate::i18n::Locale;
+use crate::staff::{self, Staff};
+
+/// RID columns kept in the snapshots of `rid_snapshot()`, with their labels.
+const FIELDS: [(&str, &str); 13] = [
+    ("name", "Название"),
+    ("name_en", "Название (англ.)"),
+    ("description", "Описание"),
+    ("description_en", "Описание (англ.)"),
+    ("numPotent", "Номер"),
+    ("idFaculty", "Факультет (id)"),
+    ("idType", "Вид (id)"),
+    ("idYear", "Год (id)"),
+    ("idSubjectArea", "Предметная область (id)"),
+    ("link", "Ссылка"),
+    ("trl", "TRL"),
+    ("available", "Доступно для лицензирования"),
+    ("partnership_terms", "Условия партнёрства"),
+];
+
+#[derive(Debug, sqlx::FromRow)]
+struct Version
+{
+    id: u64,
+    changed_at: String,
+    actor: String,
+    action: String,
+    snapshot: String
+}
+
+fn action_label(action: &str) -> &str
+{
+    match action
+    {
+        "insert" => "Создание",
+        "update" => "Изменение",
+        "delete" => "Удаление",
+        "rollback" => "Откат",
+        _ => action
+    }
+}
+
+fn server_error(err: impl std::fmt::Display, page: &str) -> HttpResponse
+{
+    error!("{}: {}", err, page);
+    HttpResponse::ServiceUnavailable()
+        .content_type("text/html; charset=utf-8")
+        .body(err.to_string())
+}
+
+fn not_found() -> HttpResponse
+{
+    HttpResponse::NotFound()
+        .content_type("text/html; charset=utf-8")
+        .body("<h1>Error 404</h1>")
+}
+
+/// A snapshot value as it is bound back into SQL.
+fn sql_value(value: &Value) -> Option<String>
+{
+    match value
+    {
+        Value::Null => None,
+        Value::String(text) => Some(text.clone()),
+        Value::Bool(flag) => Some(if *flag { "1" } else { "0" }.to_string()),
+        other => Some(other.to_string())
+    }
+}
+
+/// Author entries of a snapshot in contribution order, unnumbered ones last.
+fn snapshot_authors(snapshot: &Value) -> Vec<&Value>
+{
+    let mut authors: Vec<&Value> = snapshot["authors"].as_array().map(|authors| authors.iter().collect()).unwrap_or_default();
+
+    authors.sort_by_key(|author| (
+        author["position"].as_i64().unwrap_or(i64::MAX),
+        author["id"].as_i64().unwrap_or_default()
+    ));
+
+    authors
+}
+
+/// Every compared line of a snapshot as escaped HTML: the RID columns, then
+/// authors, agreements, status entries and attachments.
+fn lines(snapshot: &Value) -> Vec<(&'static str, String)>
+{
+    let mut lines: Vec<(&'static str, String)> = FIELDS
+        .iter()
+        .map(|(key, label)| (*label, escape(&sql_value(&snapshot[*key]).unwrap_or_default())))
+        .collect();
+
+    let authors: Vec<String> = snapshot_authors(snapshot)
+        .iter()
+        .map(|author| format!("{} [{}]", escape(author["name"].as_str().unwrap_or_default()), author["id"]))
+        .collect();
+    lines.push(("Авторы", authors.join("<br>")));
+
+    let mut agreements: Vec<i64> = snapshot["agreements"]
+        .as_array()
+        .map(|agreements| agreements.iter().filter_map(|id| id.as_i64()).collect())
+        .unwrap_or_default();
+    agreements.sort();
+    lines.push(("Договоры (id)", agreements.iter().map(|id| id.to_string()).collect::<Vec<String>>().join(", ")));
+
+    let mut statuses: Vec<(&str, i64, String)> = snapshot["statuses"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|entry| (
+            entry["date"].as_str().unwrap_or_default(),
+            entry["id"].as_i64().unwrap_or_default(),
+            format!(
+                "{} — {} {}",
+                entry["date"].as_str().unwrap_or_default(),
+                handlers::status_label(Locale::Ru, entry["status"].as_str().unwrap_or_default()),
+                escape(entry["notes"].as_str().unwrap_or_default())
+            )
+        ))
+        .collect();
+    statuses.sort();
+    lines.push(("Правовой статус", statuses.into_iter().map(|(_, _, line)| line).collect::<Vec<String>>().join("<br>")));
+
+    let mut attachments: Vec<(i64, String)> = snapshot["attachments"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|file| (file["id"].as_i64().unwrap_or_default(), escape(file["title"].as_str().unwrap_or_default())))
+        .collect();
+    attachments.sort();
+    lines.push(("Вложения", attachments.into_iter().map(|(id, title)| format!("{} [{}]", title, id)).collect::<Vec<String>>().join("<br>")));
+
+    lines
+}
+
+fn parse(version: &Version) -> Value
+{
+    serde_json::from_str(&version.snapshot).unwrap_or(Value::Null)
+}
+
+/// Labels of the lines that differ between two snapshots.
+fn changed(old: &Value, new: &Value) -> Vec<&'static str>
+{
+    lines(old)
+        .into_iter()
+        .zip(lines(new))
+        .filter(|((_, old), (_, new))| old != new)
+        .map(|((label, _), _)| label)
+        .collect()
+}
+
+async fn versions(pool: &MySqlPool, rid: i32) -> Result<Vec<Version>, sqlx::Error>
+{
+    sqlx::query_as(r#"
+        select
+            id,
+            date_format(changed_at, '%d.%m.%Y %H:%i') as changed_at,
+            actor,
+            cast(action as char) as action,
+            cast(snapshot as char) as snapshot
+        from RIDHistory
+        where idRID = ?
+        order by id
+        "#)
+        .bind(rid)
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn list(_staff: Staff, rid: web::Path<i32>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let versions = match versions(pool.get_ref(), *rid).await
+    {
+        Ok(versions) => versions,
+        Err(err) => return server_error(err, "admin/rids/history")
+    };
+
+    if versions.is_empty()
+    {
+        return not_found();
+    }
+
+    let snapshots: Vec<Value> = versions.iter().map(parse).collect();
+
+    let rows: Vec<Vec<String>> = versions
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(i, version)| {
+            let (changes, compare) = match i
+            {
+                0 => (String::default(), String::default()),
+                _ => (
+                    changed(&snapshots[i - 1], &snapshots[i]).join(", "),
+                    format!(
+                        r#"<a href="/admin/rids/{}/history/compare?from={}&to={}">сравнить с предыдущей</a>"#,
+                        rid, versions[i - 1].id, version.id
+                    )
+                )
+            };
+
+            // The latest version is the current state of the record.
+            let rollback = if i + 1 == versions.len()
+            {
+                String::default()
+            }
+            else
+            {
+                format!(
+                    r#"<form method="post" action="/admin/rids/{}/history/{}/rollback" onsubmit="return confirm('Откатить запись к версии {}?')"><button type="submit">откатить</button></form>"#,
+                    rid, version.id, version.id
+                )
+            };
+
+            vec![
+                version.id.to_string(),
+                version.changed_at.clone(),
+                escape(&version.actor),
+                action_label(&version.action).to_string(),
+                changes,
+                compare,
+                rollback
+            ]
+        })
+        .collect();
+
+    admin::page(&format!("История РИД {}", rid), &format!(
+        r#"<form action="/admin/rids/{}/history/compare">Сравнить версии <input name="from" size="6"> и <input name="to" size="6"> <button type="submit">Сравнить</button></form>{}"#,
+        rid,
+        admin::table(&["Версия", "Дата", "Кто", "Действие", "Изменено", "", ""], &rows)
+    ))
+}
+
+pub async fn compare(_staff: Staff, rid: web::Path<i32>, query: web::Query<HashMap<String, String>>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let versions = match versions(pool.get_ref(), *rid).await
+    {
+        Ok(versions) => versions,
+        Err(err) => return server_error(err, "admin/rids/history/compare")
+    };
+
+    let pick = |key: &str| query
+        .get(key)
+        .and_then(|id| id.parse::<u64>().ok())
+        .and_then(|id| versions.iter().find(|version| version.id == id));
+
+    let (Some(from), Some(to)) = (pick("from"), pick("to")) else { return not_found() };
+
+    let rows: Vec<Vec<String>> = lines(&parse(from))
+        .into_iter()
+        .zip(lines(&parse(to)))
+        .map(|((label, old), (_, new))| {
+            let style = if old == new { "" } else { "background-color: #fff3b0;" };
+            vec![
+                label.to_string(),
+                format!(r#"<div style="{}">{}</div>"#, style, old),
+                format!(r#"<div style="{}">{}</div>"#, style, new)
+            ]
+        })
+        .collect();
+
+    let header_from = format!("Версия {} ({}, {})", from.id, from.changed_at, escape(&from.actor));
+    let header_to = format!("Версия {} ({}, {})", to.id, to.changed_at, escape(&to.actor));
+
+    admin::page(&format!("История РИД {}: сравнение версий", rid), &admin::table(
+        &["Поле", &header_from, &header_to],
+        &rows
+    ))
+}
+
+/// Writes the snapshot of `version` back into RID, AUTHORxRID, AGREEMENTxRID
+/// and StatusHistory and records the result as one 'rollback' version. A
+/// deleted RID is recreated. Attachments stay as they are, and so do the
+/// status entries when the snapshot predates their versioning.
+async fn restore(conn: &mut MySqlConnection, rid: i32, version: u64, actor: &str) -> Result<bool, sqlx::Error>
+{
+    let snapshot: Option<String> = sqlx::query_scalar("select cast(snapshot as char) from RIDHistory where id = ? and idRID = ?")
+        .bind(version)
+        .bind(rid)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    let Some(snapshot) = snapshot else { return Ok(false) };
+    let snapshot: Value = serde_json::from_str(&snapshot).map_err(|err| sqlx::Error::Decode(Box::new(err)))?;
+
+    sqlx::query("set @audit_actor = ?, @audit_skip = 1")
+        .bind(actor)
+        .execute(&mut *conn)
+        .await?;
+
+    let mut tx = conn.begin().await?;
+
+    let exists: i64 = sqlx::query_scalar("select count(*) from RID where id = ?")
+        .bind(rid)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    let columns: Vec<&str> = FIELDS.iter().map(|(key, _)| *key).collect();
+    let statement = if exists > 0
+    {
+        format!("update RID set {} = ? where id = ?", columns.join(" = ?, "))
+    }
+    else
+    {
+        format!("insert into RID ({}, id) values ({}?)", columns.join(", "), "?, ".repeat(columns.len()))
+    };
+
+    let mut record = sqlx::query(&statement);
+    for key in &columns
+    {
+        record = record.bind(sql_value(&snapshot[*key]));
+    }
+    record.bind(rid).execute(&mut *tx).await?;
+
+    sqlx::query("delete from AUTHORxRID where idRID = ?").bind(rid).execute(&mut *tx).await?;
+    for author in snapshot_authors(&snapshot)
+    {
+        sqlx::query("insert into AUTHORxRID (idRID, idAuthor, position) values (?, ?, ?)")
+            .bind(rid)
+            .bind(author["id"].as_i64())
+            .bind(author["position"].as_i64())
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    sqlx::query("delete from AGREEMENTxRID where idRID = ?").bind(rid).execute(&mut *tx).await?;
+    for agreement in snapshot["agreements"].as_array().into_iter().flatten()
+    {
+        sqlx::query("insert into AGREEMENTxRID (idAgreement, idRID) values (?, ?)")
+            .bind(agreement.as_i64())
+            .bind(rid)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    if let Some(statuses) = snapshot["statuses"].as_array()
+    {
+        sqlx::query("delete from StatusHistory where idRID = ?").bind(rid).execute(&mut *tx).await?;
+        for entry in statuses
+        {
+            sqlx::query("insert into StatusHistory (id, idRID, status, date, notes) values (?, ?, ?, ?, ?)")
+                .bind(entry["id"].as_i64())
+                .bind(rid)
+                .bind(entry["status"].as_str())
+                .bind(entry["date"].as_str())
+                .bind(entry["notes"].as_str())
+                .execute(&mut *tx)
+                .await?;
+        }
+    }
+
+    sqlx::query("insert into RIDHistory (idRID, actor, action, snapshot) values (?, ?, 'rollback', rid_snapshot(?))")
+        .bind(rid)
+        .bind(actor)
+        .bind(rid)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+pub async fn rollback(staff: Staff, path: web::Path<(i32, u64)>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let (rid, version) = path.into_inner();
+
+    let versions = match versions(pool.get_ref(), rid).await
+    {
+        Ok(versions) => versions,
+        Err(err) => return server_error(err, "admin/rids/history/rollback")
+    };
+    let Some(target) = versions.iter().find(|target| target.id == version) else { return not_found() };
+
+    let current = match staff::rid_faculty(pool.get_ref(), rid).await
+    {
+        Ok(current) => current,
+        Err(err) => return server_error(err, "admin/rids/history/rollback")
+    };
+
+    // Both the record as it is and as it will be must be within reach.
+    let restored_faculty = parse(target)["idFaculty"].as_i64().map(|faculty| faculty as i32);
+    if !staff.may_edit(restored_faculty) || current.is_some_and(|faculty| !staff.may_edit(faculty))
+    {
+        return staff.deny("admin/rids/history/rollback");
+    }
+
+    let mut conn = match pool.acquire().await
+    {
+        Ok(conn) => conn,
+        Err(err) => return server_error(err, "admin/rids/history/rollback")
+    };
+
+    let restored = restore(&mut conn, rid, version, &format!("{} (откат к версии {})", staff.name, version)).await;
+
+    // The variables live as long as the pooled connection, so they are
+    // cleared whatever happened above.
+    if let Err(err) = sqlx::query("set @audit_actor = null, @audit_skip = null").execute(&mut *conn).await
+    {
+        error!("{}: admin/rids/history/rollback", err);
+        conn.close_on_drop();
+    }
+
+    match restored
+    {
+        Ok(true) => {
+            HttpResponse::SeeOther()
+                .append_header((header::LOCATION, format!("/admin/rids/{}/history", rid)))
+                .finish()
+        },
+        Ok(false) => not_found(),
+        Err(err) => server_error(err, "admin/rids/history/rollback")
+    }
+}
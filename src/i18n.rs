@@ -0,0 +1,429 @@
+use std::collections::HashMap;
+
+use actix_web::HttpRequest;
+use log::error;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Locale
+{
+    Ru,
+    En
+}
+
+impl Locale
+{
+    pub fn code(self) -> &'static str
+    {
+        match self
+        {
+            Locale::Ru => "ru",
+            Locale::En => "en"
+        }
+    }
+}
+
+/// Locale given by an explicit `/en/…` or `/ru/…` route, if any.
+fn route_locale(req: &HttpRequest) -> Option<Locale>
+{
+    let path = req.path();
+
+    if path == "/en" || path.starts_with("/en/")
+    {
+        Some(Locale::En)
+    }
+    else if path == "/ru" || path.starts_with("/ru/")
+    {
+        Some(Locale::Ru)
+    }
+    else
+    {
+        None
+    }
+}
+
+/// The most preferred of the supported languages listed in Accept-Language.
+fn accepted_locale(req: &HttpRequest) -> Option<Locale>
+{
+    let header = req.headers().get("Accept-Language")?.to_str().ok()?;
+    let mut best: Option<(f32, Locale)> = None;
+
+    for item in header.split(',')
+    {
+        let mut parts = item.trim().split(';');
+        let tag = parts.next().unwrap_or_default().trim().to_lowercase();
+        let quality: f32 = parts
+            .find_map(|param| param.trim().strip_prefix("q="))
+            .and_then(|q| q.parse().ok())
+            .unwrap_or(1.0);
+
+        let locale = match tag.split('-').next()
+        {
+            Some("ru") => Locale::Ru,
+            Some("en") => Locale::En,
+            _ => continue
+        };
+
+        if quality > 0.0 && best.is_none_or(|(q, _)| quality > q)
+        {
+            best = Some((quality, locale));
+        }
+    }
+
+    best.map(|(_, locale)| locale)
+}
+
+/// The route prefix wins over Accept-Language; Russian is the default.
+pub fn locale(req: &HttpRequest) -> Locale
+{
+    route_locale(req)
+        .or_else(|| accepted_locale(req))
+        .unwrap_or(Locale::Ru)
+}
+
+/// Prefix that keeps links and fragment requests on the explicitly chosen locale.
+pub fn prefix(req: &HttpRequest) -> &'static str
+{
+    match route_locale(req)
+    {
+        Some(Locale::En) => "/en",
+        Some(Locale::Ru) => "/ru",
+        None => ""
+    }
+}
+
+/// Translation of a UI string built by the handlers, the Russian text itself
+/// if there is none.
+pub fn t(locale: Locale, text: &'static str) -> &'static str
+{
+    if locale == Locale::Ru
+    {
+        return text;
+    }
+
+    match text
+    {
+        "Предметная область" => "Subject area",
+        "Год" => "Year",
+        "Факультеты" => "Faculties",
+        "Вид" => "Kind",
+        "Статус" => "Status",
+        "Уровень готовности" => "Readiness level",
+        "Лицензирование" => "Licensing",
+        "Доступно для лицензирования и партнёрства" => "Available for licensing and partnership",
+        "Доступно для лицензирования" => "Available for licensing",
+        "Условия лицензирования и партнёрства:" => "Licensing and partnership terms:",
+        "Описание" => "Description",
+        "Документы:" => "Documents:",
+        "Запись в реестре" => "Registry record",
+        "Цитировать:" => "Cite:",
+        "Страница РИД" => "RID page",
+        "По запросу нет подходящих результатов" => "No results match your query",
+        "Заявка подана" => "Application filed",
+        "Заявка опубликована" => "Application published",
+        "Выдан охранный документ" => "Title of protection granted",
+        "Действует" => "In force",
+        "Прекратил действие" => "Lapsed",
+        "Срок действия истёк" => "Expired",
+        "с" => "since",
+        "Лицензирован" => "Licensed",
+        "Права переданы" => "Rights assigned",
+        "Внедрён в МИП" => "Used by a spin-off",
+        "срок охраны:" => "term of protection:",
+        "патент" => "patent",
+        "патента" | "патентов" => "patents",
+        "свидетельство о регистрации" => "registration certificate",
+        "свидетельства о регистрации" | "свидетельств о регистрации" => "registration certificates",
+        "товарный знак" => "trademark",
+        "товарных знака" | "товарных знаков" => "trademarks",
+        " поддерживает РУДН" => " maintained by RUDN",
+        "изобретения, полезные модели, промышленные образцы" => "inventions, utility models, industrial designs",
+        "программ для ЭВМ и баз данных" => "for computer programs and databases",
+        "на русском и английском языках" => "in Russian and English",
+        _ => text
+    }
+}
+
+/// Plural form of a noun for `n`, translated for `locale`. `forms` are the
+/// Russian forms for 1, 2 and 5.
+pub fn plural(locale: Locale, n: i64, forms: [&'static str; 3]) -> &'static str
+{
+    let form = match locale
+    {
+        Locale::En if n == 1 => forms[0],
+        Locale::En => forms[2],
+        Locale::Ru => match (n % 10, n % 100)
+        {
+            (_, 11..=14) => forms[2],
+            (1, _) => forms[0],
+            (2..=4, _) => forms[1],
+            _ => forms[2]
+        }
+    };
+
+    t(locale, form)
+}
+
+/// Attributes whose values are shown to the user and so get translated.
+const TRANSLATED_ATTRIBUTES: [&str; 4] = ["placeholder", "aria-label", "alt", "title"];
+
+fn catalogue(path: &str, locale: Locale) -> HashMap<String, String>
+{
+    let file = format!("{}/i18n/{}.json", path, locale.code());
+
+    match std::fs::read_to_string(&file).map(|body| serde_json::from_str(&body))
+    {
+        Ok(Ok(catalogue)) => catalogue,
+        Ok(Err(err)) => {
+            error!("{}: {}", err, file);
+            HashMap::new()
+        },
+        Err(err) => {
+            error!("{}: {}", err, file);
+            HashMap::new()
+        }
+    }
+}
+
+/// Replaces `text` with its translation, keeping the surrounding whitespace.
+/// Catalogue keys have their inner whitespace collapsed to single spaces.
+fn translate_text(text: &str, catalogue: &HashMap<String, String>) -> Option<String>
+{
+    let key = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+    let translation = catalogue.get(&key)?;
+
+    let start = text.len() - text.trim_start().len();
+    let end = text.trim_end().len();
+
+    Some(format!("{}{}{}", &text[..start], translation, &text[end..]))
+}
+
+fn translate_tag(tag: &str, catalogue: &HashMap<String, String>) -> String
+{
+    let mut tag = tag.to_string();
+
+    for attribute in TRANSLATED_ATTRIBUTES
+    {
+        let opening = format!(" {}=\"", attribute);
+        let Some(start) = tag.find(&opening).map(|start| start + opening.len()) else { continue };
+        let Some(length) = tag[start..].find('"') else { continue };
+
+        if let Some(translation) = translate_text(&tag[start..start + length], catalogue)
+        {
+            tag.replace_range(start..start + length, &translation);
+        }
+    }
+
+    tag
+}
+
+/// Byte length of the tag starting at the beginning of `html`, quotes included.
+fn tag_length(html: &str) -> usize
+{
+    let mut quote: Option<char> = None;
+
+    for (i, ch) in html.char_indices()
+    {
+        match (quote, ch)
+        {
+            (None, '"') | (None, '\'') => quote = Some(ch),
+            (Some(open), _) if open == ch => quote = None,
+            (None, '>') => return i + 1,
+            _ => ()
+        }
+    }
+
+    html.len()
+}
+
+/// Translates whole text nodes and user-visible attributes of a page. Scripts,
+/// styles and comments are copied untouched.
+fn translate_page(html: &str, catalogue: &HashMap<String, String>) -> String
+{
+    let mut body = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while !rest.is_empty()
+    {
+        let Some(open) = rest.find('<') else {
+            body.push_str(&translate_text(rest, catalogue).unwrap_or_else(|| rest.to_string()));
+            break;
+        };
+
+        let text = &rest[..open];
+        body.push_str(&translate_text(text, catalogue).unwrap_or_else(|| text.to_string()));
+        rest = &rest[open..];
+
+        if rest.starts_with("<!--")
+        {
+            let end = rest.find("-->").map(|end| end + 3).unwrap_or(rest.len());
+            body.push_str(&rest[..end]);
+            rest = &rest[end..];
+            continue;
+        }
+
+        let length = tag_length(rest);
+        body.push_str(&translate_tag(&rest[..length], catalogue));
+
+        let name: String = rest[1..length]
+            .chars()
+            .take_while(|ch| ch.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_lowercase();
+
+        rest = &rest[length..];
+
+        if name == "script" || name == "style"
+        {
+            let closing = format!("</{}", name);
+            let end = rest.find(&closing).unwrap_or(rest.len());
+            body.push_str(&rest[..end]);
+            rest = &rest[end..];
+        }
+    }
+
+    body
+}
+
+/// Fragment routes mounted under `/en` and `/ru` next to the pages (see
+/// `pages()` in main.rs).
+const FRAGMENTS: [&str; 7] = ["find", "best", "counters", "nominated", "facts", "types", "assets"];
+
+/// Names of the pages in `path`, each a directory with `{name}/{name}.html`.
+fn page_names(path: &str) -> Vec<String>
+{
+    std::fs::read_dir(path)
+        .map(|entries| entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|name| std::path::Path::new(path).join(name).join(format!("{}.html", name)).is_file())
+            .collect())
+        .unwrap_or_default()
+}
+
+/// Makes site-relative links, form actions and fragment requests stay under
+/// `prefix`. Only what the prefixed scope serves is rewritten: the main page,
+/// `pages` and `FRAGMENTS`; feeds, RID and author pages, attachments and the
+/// like live at the root only.
+fn prefix_links(html: &str, prefix: &str, pages: &[String]) -> String
+{
+    let mut body = html.to_string();
+
+    for opening in ["href=\"/", "action=\"/", "fetch('/"]
+    {
+        let mut from = 0;
+
+        while let Some(found) = body[from..].find(opening)
+        {
+            let start = from + found + opening.len() - 1;
+            let target = &body[start..];
+            let segment = target[1..].split(['/', '?', '#', '"', '\'']).next().unwrap_or_default();
+
+            if !target.starts_with("//") && (segment.is_empty() || FRAGMENTS.contains(&segment) || pages.iter().any(|page| page == segment))
+            {
+                body.insert_str(start, prefix);
+            }
+
+            from = start + 1;
+        }
+    }
+
+    body
+}
+
+/// Prepares a page from `appearance` for the locale of the request.
+pub fn page(req: &HttpRequest, html: String, path: &str) -> String
+{
+    let locale = locale(req);
+    let mut body = html;
+
+    if locale == Locale::En
+    {
+        body = translate_page(&body, &catalogue(path, locale))
+            .replacen("<html lang=\"ru\">", "<html lang=\"en\">", 1)
+            .replace("href=\"/en/", "href=\"/ru/")
+            .replace("hreflang=\"en\" target=\"_top\"><span style=\"padding: 14px;\">EN</span>", "hreflang=\"ru\" target=\"_top\"><span style=\"padding: 14px;\">RU</span>");
+    }
+
+    let prefix = prefix(req);
+    if !prefix.is_empty()
+    {
+        body = prefix_links(&body, prefix, &page_names(path));
+    }
+
+    body
+}
+
+#[cfg(test)]
+mod tests
+{
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    const PATENTS: [&str; 3] = ["патент", "патента", "патентов"];
+
+    #[test]
+    fn plural_russian_forms()
+    {
+        for (n, form) in [(1, "патент"), (2, "патента"), (4, "патента"), (5, "патентов"), (11, "патентов"), (12, "патентов"), (14, "патентов"), (21, "патент"), (22, "патента"), (111, "патентов"), (0, "патентов")]
+        {
+            assert_eq!(plural(Locale::Ru, n, PATENTS), form, "{}", n);
+        }
+    }
+
+    #[test]
+    fn plural_english_forms()
+    {
+        assert_eq!(plural(Locale::En, 1, PATENTS), "patent");
+        assert_eq!(plural(Locale::En, 2, PATENTS), "patents");
+        assert_eq!(plural(Locale::En, 21, PATENTS), "patents");
+        assert_eq!(plural(Locale::En, 0, PATENTS), "patents");
+    }
+
+    fn accepted(header: &str) -> Option<Locale>
+    {
+        accepted_locale(&TestRequest::default().insert_header(("Accept-Language", header)).to_http_request())
+    }
+
+    #[test]
+    fn accepted_locale_by_quality()
+    {
+        assert_eq!(accepted("en-US,en;q=0.9,ru;q=0.8"), Some(Locale::En));
+        assert_eq!(accepted("de-DE, en;q=0.5, ru;q=0.7"), Some(Locale::Ru));
+        assert_eq!(accepted("RU"), Some(Locale::Ru));
+        assert_eq!(accepted("en;q=0, ru;q=0.1"), Some(Locale::Ru));
+        assert_eq!(accepted("de, fr;q=0.8"), None);
+        assert_eq!(accepted_locale(&TestRequest::default().to_http_request()), None);
+    }
+
+    #[test]
+    fn translate_page_texts_and_attributes()
+    {
+        let catalogue: HashMap<String, String> = [
+            ("Поиск РИД".to_string(), "RID search".to_string()),
+            ("Найти".to_string(), "Find".to_string()),
+        ].into_iter().collect();
+
+        let html = r#"<h1>
+            Поиск
+            РИД </h1><input placeholder="Найти" data-x="Найти"><!-- Найти --><script>let a = "Найти";</script><p>Найти</p><p>Другое</p>"#;
+
+        assert_eq!(
+            translate_page(html, &catalogue),
+            r#"<h1>
+            RID search </h1><input placeholder="Find" data-x="Найти"><!-- Найти --><script>let a = "Найти";</script><p>Find</p><p>Другое</p>"#
+        );
+    }
+
+    #[test]
+    fn prefix_links_keeps_root_routes()
+    {
+        let pages = ["portfolio".to_string(), "registration".to_string()];
+        let html = r#"<a href="/">Главная</a><a href="/portfolio?y=2024">Портфель</a><form action="/registration"></form><a href="/#top">^</a><script>fetch('/find'); fetch('/facts/patent');</script><link href="/assets/site.js"><a href="/feed.atom">Atom</a><a href="/feed.rss">RSS</a><a href="/disclose">Заявка</a><a href="/rids/5">РИД</a><a href="/authors/7">Автор</a><a href="/attachments/3">Файл</a><a href="//cdn.example.org/x.css">CDN</a><a href="/en/search">EN</a>"#;
+
+        assert_eq!(
+            prefix_links(html, "/en", &pages),
+            r#"<a href="/en/">Главная</a><a href="/en/portfolio?y=2024">Портфель</a><form action="/en/registration"></form><a href="/en/#top">^</a><script>fetch('/en/find'); fetch('/en/facts/patent');</script><link href="/en/assets/site.js"><a href="/feed.atom">Atom</a><a href="/feed.rss">RSS</a><a href="/disclose">Заявка</a><a href="/rids/5">РИД</a><a href="/authors/7">Автор</a><a href="/attachments/3">Файл</a><a href="//cdn.example.org/x.css">CDN</a><a href="/en/search">EN</a>"#
+        );
+    }
+}
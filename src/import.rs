@@ -0,0 +1,834 @@
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+
+use actix_multipart::Multipart;
+use actix_web::{web, HttpResponse, Responder};
+use calamine::{Reader, Xlsx};
+use futures_util::{StreamExt, TryStreamExt};
+use sqlx::mysql::{MySqlConnection, MySqlPool};
+use sqlx::{Connection, Row};
+use log::error;
+
+use crate::admin;
+use crate::staff::Staff;
+use crate::registry;
+
+const MAX_SIZE: usize = 20 * 1024 * 1024;
+
+/// Spreadsheet columns stored in RID as they are, with their RID columns.
+/// The headers are those of `/export`, so an exported table can be edited
+/// and loaded back; its derived columns (status, agreement) are ignored.
+const RID_COLUMNS: [(&str, &str); 9] = [
+    ("Название", "name"),
+    ("Название (англ.)", "name_en"),
+    ("Описание", "description"),
+    ("Описание (англ.)", "description_en"),
+    ("Номер", "numPotent"),
+    ("Ссылка", "link"),
+    ("TRL", "trl"),
+    ("Доступно для лицензирования", "available"),
+    ("Условия партнёрства", "partnership_terms"),
+];
+
+/// Spreadsheet columns naming a row of a lookup table, with their RID columns.
+const LOOKUP_COLUMNS: [(&str, &str); 4] = [
+    ("Факультет", "idFaculty"),
+    ("Вид", "idType"),
+    ("Год", "idYear"),
+    ("Предметная область", "idSubjectArea"),
+];
+
+const REQUIRED: [&str; 3] = ["Название", "Номер", "Вид"];
+
+#[derive(Debug)]
+pub struct ImportedAuthor
+{
+    pub id: Option<i32>,
+    pub surname: String,
+    pub name: String,
+    pub patronymic: String,
+    pub country: Option<String>
+}
+
+/// A spreadsheet column, the RID column it goes to and the value.
+type Field = (&'static str, &'static str, Option<String>);
+
+/// One valid spreadsheet row. Only the columns present in the file are set,
+/// so that a partial table updates just those fields.
+#[derive(Debug)]
+struct Record
+{
+    row: usize,
+    id: Option<i32>,
+    values: Vec<Field>,
+    lookups: Vec<Field>,
+    authors: Option<Vec<ImportedAuthor>>
+}
+
+impl Record
+{
+    fn value(&self, column: &str) -> Option<&str>
+    {
+        self.values.iter().find(|(_, key, _)| *key == column).and_then(|(_, _, value)| value.as_deref())
+    }
+
+    fn lookup(&self, column: &str) -> Option<&str>
+    {
+        self.lookups.iter().find(|(_, key, _)| *key == column).and_then(|(_, _, value)| value.as_deref())
+    }
+}
+
+/// What happened to one row: the action, the RID and what was created or changed.
+struct Outcome
+{
+    action: &'static str,
+    rid: u64,
+    notes: Vec<String>
+}
+
+/// A row that cannot be imported, or a failed query that stops the import.
+pub enum Problem
+{
+    Invalid(String),
+    Database(sqlx::Error)
+}
+
+impl From<sqlx::Error> for Problem
+{
+    /// A statement the server refuses (a violated constraint, a too long value)
+    /// fails only its row; MySQL keeps the transaction going.
+    fn from(err: sqlx::Error) -> Problem
+    {
+        match err
+        {
+            sqlx::Error::Database(err) => Problem::Invalid(err.message().to_string()),
+            err => Problem::Database(err)
+        }
+    }
+}
+
+fn server_error(err: impl std::fmt::Display, page: &str) -> HttpResponse
+{
+    error!("{}: {}", err, page);
+    HttpResponse::ServiceUnavailable()
+        .content_type("text/html; charset=utf-8")
+        .body(err.to_string())
+}
+
+fn bad_request(message: &str) -> HttpResponse
+{
+    HttpResponse::BadRequest()
+        .content_type("text/html; charset=utf-8")
+        .body(message.to_string())
+}
+
+/// Cells of the first sheet of an XLSX workbook, or of a UTF-8 CSV file
+/// separated by commas or semicolons (as Excel saves it with Russian settings).
+fn read_table(data: &[u8]) -> Result<Vec<Vec<String>>, String>
+{
+    if data.starts_with(b"PK")
+    {
+        let mut workbook: Xlsx<_> = calamine::open_workbook_from_rs(Cursor::new(data)).map_err(|err: calamine::XlsxError| err.to_string())?;
+        let range = workbook
+            .worksheet_range_at(0)
+            .ok_or("В книге нет листов")?
+            .map_err(|err| err.to_string())?;
+
+        return Ok(range.rows().map(|row| row.iter().map(|cell| cell.to_string()).collect()).collect());
+    }
+
+    let text = std::str::from_utf8(data).map_err(|_| "CSV-файл должен быть в кодировке UTF-8")?;
+    let text = text.trim_start_matches('\u{feff}');
+
+    let header = text.lines().next().unwrap_or_default();
+    let delimiter = if header.matches(';').count() > header.matches(',').count() { b';' } else { b',' };
+
+    csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.as_bytes())
+        .records()
+        .map(|record| record
+            .map(|record| record.iter().map(str::to_string).collect())
+            .map_err(|err| err.to_string()))
+        .collect()
+}
+
+/// Undoes the quote `/export` puts before CSV fields that look like formulas.
+fn unquote(value: &str) -> &str
+{
+    match value.strip_prefix('\'')
+    {
+        Some(rest) if rest.starts_with(['=', '+', '-', '@']) => rest,
+        _ => value
+    }
+}
+
+fn parse_flag(value: &str) -> Option<&'static str>
+{
+    match value.to_lowercase().as_str()
+    {
+        "" | "нет" | "0" | "no" | "false" => Some("0"),
+        "да" | "1" | "yes" | "true" => Some("1"),
+        _ => None
+    }
+}
+
+/// `Фамилия Имя Отчество` entries separated by `;`, with the matching ids and
+/// countries, if given, in the columns next to them.
+fn parse_authors(names: &str, ids: &str, countries: &str) -> Result<Vec<ImportedAuthor>, String>
+{
+    let split = |text: &str| -> Vec<String> { text.split(';').map(|part| part.trim().to_string()).collect() };
+    let names: Vec<String> = split(names).into_iter().filter(|name| !name.is_empty()).collect();
+    let ids = split(ids);
+    let countries = split(countries);
+
+    names
+        .iter()
+        .enumerate()
+        .map(|(i, full_name)| {
+            let id = match ids.get(i).map(|id| id.as_str()).unwrap_or_default()
+            {
+                "" => None,
+                id => Some(id.parse::<i32>().map_err(|_| format!("неверный id автора «{}»", id))?)
+            };
+
+            let mut parts = full_name.split_whitespace();
+            Ok(ImportedAuthor {
+                id,
+                surname: parts.next().unwrap_or_default().to_string(),
+                name: parts.next().unwrap_or_default().to_string(),
+                patronymic: parts.collect::<Vec<&str>>().join(" "),
+                country: countries.get(i).filter(|country| !country.is_empty()).cloned()
+            })
+        })
+        .collect()
+}
+
+/// Rows of the spreadsheet, numbered from the header, with their problems.
+type RowErrors = Vec<(usize, String)>;
+
+/// Checks every row without touching the database. Returns the valid records
+/// and the errors of the others.
+fn validate(table: &[Vec<String>]) -> Result<(Vec<Record>, RowErrors), String>
+{
+    let Some((header, rows)) = table.split_first() else { return Err("Файл пуст".to_string()) };
+
+    let columns: HashMap<&str, usize> = header.iter().enumerate().map(|(i, name)| (name.trim(), i)).collect();
+    let missing: Vec<&str> = REQUIRED.iter().filter(|name| !columns.contains_key(*name)).copied().collect();
+    if !missing.is_empty()
+    {
+        return Err(format!("Нет обязательных столбцов: {}", missing.join(", ")));
+    }
+
+    let mut records: Vec<Record> = Vec::new();
+    let mut errors: RowErrors = Vec::new();
+    let mut ids: HashSet<i32> = HashSet::new();
+
+    for (i, cells) in rows.iter().enumerate()
+    {
+        let row = i + 2;
+
+        if cells.iter().all(|cell| cell.trim().is_empty())
+        {
+            continue;
+        }
+
+        let cell = |name: &str| columns
+            .get(name)
+            .and_then(|&i| cells.get(i))
+            .map(|value| unquote(value.trim()).to_string())
+            .unwrap_or_default();
+
+        let mut problems: Vec<String> = Vec::new();
+
+        let id = match cell("ID").as_str()
+        {
+            "" => None,
+            id => match id.parse::<i32>()
+            {
+                Ok(id) if ids.insert(id) => Some(id),
+                Ok(id) => {
+                    problems.push(format!("РИД {} уже встречался в файле", id));
+                    None
+                },
+                Err(_) => {
+                    problems.push(format!("неверный ID «{}»", id));
+                    None
+                }
+            }
+        };
+
+        let mut values = Vec::new();
+        for (label, column) in RID_COLUMNS.iter().filter(|(label, _)| columns.contains_key(label))
+        {
+            let value = cell(label);
+            let value = match *column
+            {
+                "name" if value.is_empty() => {
+                    problems.push("не указано название".to_string());
+                    None
+                },
+                "numPotent" if value.parse::<u32>().is_err() => {
+                    problems.push(format!("неверный номер «{}»", value));
+                    None
+                },
+                "trl" if !value.is_empty() && !value.parse::<u8>().is_ok_and(|trl| (1..=9).contains(&trl)) => {
+                    problems.push(format!("TRL должен быть от 1 до 9, а не «{}»", value));
+                    None
+                },
+                "available" => match parse_flag(&value)
+                {
+                    Some(flag) => Some(flag.to_string()),
+                    None => {
+                        problems.push(format!("«{}» в столбце «{}»: нужно да или нет", value, label));
+                        None
+                    }
+                },
+                _ if value.is_empty() => None,
+                _ => Some(value)
+            };
+            values.push((*label, *column, value));
+        }
+
+        let mut lookups = Vec::new();
+        for (label, column) in LOOKUP_COLUMNS.iter().filter(|(label, _)| columns.contains_key(label))
+        {
+            let value = cell(label);
+            match *column
+            {
+                "idType" if value.is_empty() => problems.push("не указан вид".to_string()),
+                "idYear" if !value.is_empty() && value.parse::<u16>().is_err() => problems.push(format!("неверный год «{}»", value)),
+                _ => {}
+            }
+            lookups.push((*label, *column, Some(value).filter(|value| !value.is_empty())));
+        }
+
+        let authors = if columns.contains_key("Авторы")
+        {
+            match parse_authors(&cell("Авторы"), &cell("Авторы (id)"), &cell("Страны авторов"))
+            {
+                Ok(authors) => Some(authors),
+                Err(problem) => {
+                    problems.push(problem);
+                    None
+                }
+            }
+        }
+        else
+        {
+            None
+        };
+
+        if problems.is_empty()
+        {
+            records.push(Record { row, id, values, lookups, authors });
+        }
+        else
+        {
+            errors.push((row, problems.join("; ")));
+        }
+    }
+
+    Ok((records, errors))
+}
+
+/// Id of the row of `table` with `column = value`, added if there is none.
+pub async fn resolve(conn: &mut MySqlConnection, table: &str, column: &str, value: &str, notes: &mut Vec<String>) -> Result<i64, sqlx::Error>
+{
+    let found: Option<i64> = sqlx::query_scalar(&format!("select id from {} where {} = ? order by id limit 1", table, column))
+        .bind(value)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    if let Some(id) = found
+    {
+        return Ok(id);
+    }
+
+    let inserted = sqlx::query(&format!("insert into {} ({}) values (?)", table, column))
+        .bind(value)
+        .execute(&mut *conn)
+        .await?;
+
+    notes.push(format!("добавлено в {}: «{}»", table, value));
+    Ok(inserted.last_insert_id() as i64)
+}
+
+/// ConnectionAuthorCountry id of an author: the given id, or the one author
+/// with this name (and country, if given). Unknown authors are added.
+pub async fn author(conn: &mut MySqlConnection, author: &ImportedAuthor, notes: &mut Vec<String>) -> Result<i64, Problem>
+{
+    let full_name = [author.surname.as_str(), author.name.as_str(), author.patronymic.as_str()].join(" ").trim().to_string();
+
+    if let Some(id) = author.id
+    {
+        let exists: i64 = sqlx::query_scalar("select count(*) from ConnectionAuthorCountry where id = ?")
+            .bind(id)
+            .fetch_one(&mut *conn)
+            .await?;
+
+        return match exists
+        {
+            0 => Err(Problem::Invalid(format!("нет автора с id {} ({})", id, full_name))),
+            _ => Ok(id.into())
+        };
+    }
+
+    let matches: Vec<i64> = sqlx::query_scalar(r#"
+        select ConnectionAuthorCountry.id
+        from ConnectionAuthorCountry
+        join FIO on (FIO.id = ConnectionAuthorCountry.idFIO)
+        left join AuthorCountry on (AuthorCountry.id = ConnectionAuthorCountry.idCountry)
+        where FIO.surname = ? and coalesce(FIO.name, '') = ? and coalesce(FIO.lastname, '') = ?
+            and (? is null or AuthorCountry.name = ?)
+        "#)
+        .bind(&author.surname)
+        .bind(&author.name)
+        .bind(&author.patronymic)
+        .bind(&author.country)
+        .bind(&author.country)
+        .fetch_all(&mut *conn)
+        .await?;
+
+    match matches.as_slice()
+    {
+        [id] => return Ok(*id),
+        [] => {},
+        _ => return Err(Problem::Invalid(format!(
+            "несколько авторов «{}», укажите id в столбце «Авторы (id)»",
+            full_name
+        )))
+    }
+
+    let fio: Option<i64> = sqlx::query_scalar(r#"
+        select id from FIO
+        where surname = ? and coalesce(name, '') = ? and coalesce(lastname, '') = ?
+        order by id limit 1
+        "#)
+        .bind(&author.surname)
+        .bind(&author.name)
+        .bind(&author.patronymic)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    let fio = match fio
+    {
+        Some(fio) => fio,
+        None => sqlx::query("insert into FIO (surname, name, lastname) values (?, ?, ?)")
+            .bind(&author.surname)
+            .bind(Some(&author.name).filter(|name| !name.is_empty()))
+            .bind(Some(&author.patronymic).filter(|patronymic| !patronymic.is_empty()))
+            .execute(&mut *conn)
+            .await?
+            .last_insert_id() as i64
+    };
+
+    let country = match &author.country
+    {
+        Some(country) => Some(resolve(conn, "AuthorCountry", "name", country, notes).await?),
+        None => None
+    };
+
+    let id = sqlx::query("insert into ConnectionAuthorCountry (idFIO, idCountry) values (?, ?)")
+        .bind(fio)
+        .bind(country)
+        .execute(&mut *conn)
+        .await?
+        .last_insert_id() as i64;
+
+    notes.push(format!("новый автор: {} [{}]", full_name, id));
+    Ok(id)
+}
+
+/// Writes one record: resolves its lookups and authors, then inserts the RID
+/// or updates the fields that differ.
+async fn import(conn: &mut MySqlConnection, record: &Record) -> Result<Outcome, Problem>
+{
+    let mut notes: Vec<String> = Vec::new();
+    let mut values: Vec<Field> = record.values.clone();
+
+    for (label, column, value) in &record.lookups
+    {
+        let id = match (*column, value)
+        {
+            (_, None) => None,
+            ("idType", Some(name)) => {
+                let id: Option<i64> = sqlx::query_scalar("select id from Type where name = ?")
+                    .bind(name)
+                    .fetch_optional(&mut *conn)
+                    .await?;
+                Some(id.ok_or_else(|| Problem::Invalid(format!("нет вида РИД «{}»", name)))?)
+            },
+            ("idFaculty", Some(name)) => Some(resolve(conn, "Faculty", "name", name, &mut notes).await?),
+            ("idYear", Some(year)) => Some(resolve(conn, "Year", "year", year, &mut notes).await?),
+            ("idSubjectArea", Some(name)) => Some(resolve(conn, "SubjectArea", "name", name, &mut notes).await?),
+            _ => None
+        };
+        values.push((label, column, id.map(|id| id.to_string())));
+    }
+
+    // An exported table carries the generated registry link where no link was
+    // entered; it is not stored as if it had been.
+    if let (Some(link), Some(rid_type), Some(number)) = (record.value("link"), record.lookup("idType"), record.value("numPotent"))
+    {
+        let pattern: Option<Option<String>> = sqlx::query_scalar("select registry_link from Type where name = ?")
+            .bind(rid_type)
+            .fetch_optional(&mut *conn)
+            .await?;
+
+        if registry::generated(pattern.flatten().as_deref(), number.parse().unwrap_or_default()).as_deref() == Some(link)
+        {
+            for value in values.iter_mut().filter(|(_, column, _)| *column == "link")
+            {
+                value.2 = None;
+            }
+        }
+    }
+
+    let mut authors: Option<Vec<i64>> = None;
+    if let Some(imported) = &record.authors
+    {
+        let mut ids = Vec::with_capacity(imported.len());
+        for imported in imported
+        {
+            ids.push(author(conn, imported, &mut notes).await?);
+        }
+        authors = Some(ids);
+    }
+
+    let columns: Vec<&str> = values.iter().map(|(_, column, _)| *column).collect();
+
+    // Without an ID, a registered RID is recognised by its number and type,
+    // so that loading the same table twice does not duplicate it.
+    let number = record.value("numPotent").and_then(|number| number.parse::<i32>().ok()).unwrap_or_default();
+    let rid_type = values.iter().find(|(_, column, _)| *column == "idType").and_then(|(_, _, id)| id.clone());
+
+    let target = match (record.id, rid_type)
+    {
+        (Some(id), _) => Some(id),
+        (None, Some(rid_type)) if number > 0 => sqlx::query_scalar("select id from RID where numPotent = ? and idType = ? order by id limit 1")
+            .bind(number)
+            .bind(rid_type)
+            .fetch_optional(&mut *conn)
+            .await?,
+        _ => None
+    };
+
+    let current = match target
+    {
+        Some(id) => {
+            let casts: Vec<String> = columns.iter().map(|column| format!("cast({} as char)", column)).collect();
+            let row = sqlx::query(&format!("select {} from RID where id = ?", casts.join(", ")))
+                .bind(id)
+                .fetch_optional(&mut *conn)
+                .await?;
+
+            let Some(row) = row else { return Err(Problem::Invalid(format!("нет РИД с ID {}", id))) };
+            Some((0..columns.len()).map(|i| row.try_get::<Option<String>, _>(i)).collect::<Result<Vec<_>, _>>()?)
+        },
+        None => None
+    };
+
+    let (mut action, rid) = match (target, current)
+    {
+        (Some(id), Some(current)) => {
+            let changed: Vec<&Field> = values
+                .iter()
+                .zip(&current)
+                .filter(|((_, _, new), old)| new != *old)
+                .map(|(value, _)| value)
+                .collect();
+
+            if !changed.is_empty()
+            {
+                let assignments: Vec<String> = changed.iter().map(|(_, column, _)| format!("{} = ?", column)).collect();
+                let statement = format!("update RID set {} where id = ?", assignments.join(", "));
+
+                let mut query = sqlx::query(&statement);
+                for (_, _, value) in &changed
+                {
+                    query = query.bind(value.clone());
+                }
+                query.bind(id).execute(&mut *conn).await?;
+
+                notes.push(format!("изменено: {}", changed.iter().map(|(label, _, _)| *label).collect::<Vec<&str>>().join(", ")));
+            }
+
+            (if changed.is_empty() { "Без изменений" } else { "Изменение" }, id as u64)
+        },
+        _ => {
+            let statement = format!("insert into RID ({}) values ({})", columns.join(", "), vec!["?"; columns.len()].join(", "));
+
+            let mut query = sqlx::query(&statement);
+            for (_, _, value) in &values
+            {
+                query = query.bind(value.clone());
+            }
+
+            ("Добавление", query.execute(&mut *conn).await?.last_insert_id())
+        }
+    };
+
+    if let Some(authors) = authors
+    {
+        let current: Vec<i64> = sqlx::query_scalar(r#"
+            select idAuthor from AUTHORxRID
+            where idRID = ?
+            order by position is null, position, idAuthor
+            "#)
+            .bind(rid)
+            .fetch_all(&mut *conn)
+            .await?;
+
+        if current != authors
+        {
+            sqlx::query("delete from AUTHORxRID where idRID = ?").bind(rid).execute(&mut *conn).await?;
+            for (position, id) in authors.iter().enumerate()
+            {
+                sqlx::query("insert into AUTHORxRID (idRID, idAuthor, position) values (?, ?, ?)")
+                    .bind(rid)
+                    .bind(id)
+                    .bind(position as i32 + 1)
+                    .execute(&mut *conn)
+                    .await?;
+            }
+
+            if action != "Добавление"
+            {
+                action = "Изменение";
+                notes.push("изменены авторы".to_string());
+            }
+        }
+    }
+
+    Ok(Outcome { action, rid, notes })
+}
+
+pub async fn form(staff: Staff) -> impl Responder
+{
+    if !staff.may_write()
+    {
+        return staff.deny("admin/import");
+    }
+
+    admin::page("Загрузка РИД из таблицы", &format!(
+        r#"<p>CSV (UTF-8) или XLSX, первая строка — названия столбцов, как в выгрузке таблицы с портфолио.
+        Обязательны «{}»; остальные столбцы можно опустить, тогда эти поля у существующих РИД не меняются.
+        Строка с ID изменяет этот РИД, строка без ID — РИД с тем же номером и видом, а если такого нет, добавляет новый.
+        Авторы перечисляются через «;» в виде «Фамилия Имя Отчество», их id и страны — через «;» в столбцах «Авторы (id)» и «Страны авторов».</p>
+        <form method="post" action="/admin/import" enctype="multipart/form-data">
+        <input type="file" name="file" accept=".csv,.xlsx" required>
+        <label><input type="checkbox" name="apply" value="1"> записать в базу (без отметки — пробный прогон)</label>
+        <button type="submit">Загрузить</button>
+        </form>"#,
+        REQUIRED.join("», «")
+    ))
+}
+
+/// Imports every record in one transaction, which is rolled back after a dry
+/// run or if any row has an error. Returns the report rows as HTML; problems
+/// and notes quote cells of the file, so they are escaped.
+async fn run(conn: &mut MySqlConnection, records: &[Record], errors: &[(usize, String)], apply: bool) -> Result<(Vec<Vec<String>>, bool), sqlx::Error>
+{
+    let mut report: Vec<(usize, Vec<String>)> = errors
+        .iter()
+        .map(|(row, problem)| (*row, vec![row.to_string(), "Ошибка".to_string(), String::default(), admin::escape(problem)]))
+        .collect();
+
+    let mut tx = conn.begin().await?;
+    let mut failed = !errors.is_empty();
+
+    for record in records
+    {
+        let line = match import(&mut tx, record).await
+        {
+            Ok(outcome) => vec![
+                record.row.to_string(),
+                outcome.action.to_string(),
+                outcome.rid.to_string(),
+                outcome.notes.iter().map(|note| admin::escape(note)).collect::<Vec<String>>().join("<br>")
+            ],
+            Err(Problem::Invalid(problem)) => {
+                failed = true;
+                vec![record.row.to_string(), "Ошибка".to_string(), String::default(), admin::escape(&problem)]
+            },
+            Err(Problem::Database(err)) => return Err(err)
+        };
+        report.push((record.row, line));
+    }
+
+    let written = apply && !failed;
+    if written
+    {
+        tx.commit().await?;
+    }
+    else
+    {
+        tx.rollback().await?;
+    }
+
+    report.sort_by_key(|(row, _)| *row);
+    Ok((report.into_iter().map(|(_, line)| line).collect(), written))
+}
+
+pub async fn upload(staff: Staff, mut payload: Multipart, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    if !staff.may_write()
+    {
+        return staff.deny("admin/import");
+    }
+
+    let mut data: Vec<u8> = Vec::new();
+    let mut apply = false;
+
+    while let Ok(Some(mut field)) = payload.try_next().await
+    {
+        match field.name().unwrap_or_default()
+        {
+            "file" => {
+                while let Some(chunk) = field.next().await
+                {
+                    let Ok(chunk) = chunk else { return bad_request("Upload interrupted") };
+
+                    if data.len() + chunk.len() > MAX_SIZE
+                    {
+                        return HttpResponse::PayloadTooLarge()
+                            .content_type("text/html; charset=utf-8")
+                            .body(format!("File is larger than {} bytes", MAX_SIZE));
+                    }
+                    data.extend_from_slice(&chunk);
+                }
+            },
+            "apply" => apply = true,
+            _ => {}
+        }
+    }
+
+    let table = match web::block(move || read_table(&data)).await
+    {
+        Ok(Ok(table)) => table,
+        Ok(Err(problem)) => return bad_request(&problem),
+        Err(err) => return server_error(err, "admin/import")
+    };
+
+    let (records, errors) = match validate(&table)
+    {
+        Ok(checked) => checked,
+        Err(problem) => return bad_request(&problem)
+    };
+
+    let mut conn = match pool.acquire().await
+    {
+        Ok(conn) => conn,
+        Err(err) => return server_error(err, "admin/import")
+    };
+
+    let imported = match sqlx::query("set @audit_actor = ?").bind(format!("{} (импорт)", staff.name)).execute(&mut *conn).await
+    {
+        Ok(_) => run(&mut conn, &records, &errors, apply).await,
+        Err(err) => Err(err)
+    };
+
+    // The variable lives as long as the pooled connection.
+    if let Err(err) = sqlx::query("set @audit_actor = null").execute(&mut *conn).await
+    {
+        error!("{}: admin/import", err);
+        conn.close_on_drop();
+    }
+
+    let (report, written) = match imported
+    {
+        Ok(imported) => imported,
+        Err(err) => return server_error(err, "admin/import")
+    };
+
+    let count = |action: &str| report.iter().filter(|line| line[1] == action).count();
+    let summary = format!(
+        "Добавление: {}, изменение: {}, без изменений: {}, ошибок: {}.",
+        count("Добавление"),
+        count("Изменение"),
+        count("Без изменений"),
+        count("Ошибка")
+    );
+
+    let status = if written
+    {
+        "Записано в базу."
+    }
+    else if count("Ошибка") > 0
+    {
+        "Ничего не записано: исправьте ошибки и загрузите файл снова."
+    }
+    else
+    {
+        "Пробный прогон, ничего не записано."
+    };
+
+    admin::page("Загрузка РИД из таблицы", &format!(
+        r#"<p>{} {}</p>{}<p><a href="/admin/import">Загрузить другой файл</a></p>"#,
+        status,
+        summary,
+        admin::table(&["Строка", "Действие", "РИД", "Подробности"], &report)
+    ))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn table(rows: &[&[&str]]) -> Vec<Vec<String>>
+    {
+        rows.iter().map(|row| row.iter().map(|cell| cell.to_string()).collect()).collect()
+    }
+
+    #[test]
+    fn parse_authors_with_ids_and_countries()
+    {
+        let authors = parse_authors("Иванов Иван Иванович; Smith John ; Ли", "12;;", "Россия; США").unwrap();
+
+        assert_eq!(authors.len(), 3);
+        assert_eq!((authors[0].id, authors[0].surname.as_str(), authors[0].name.as_str(), authors[0].patronymic.as_str()), (Some(12), "Иванов", "Иван", "Иванович"));
+        assert_eq!(authors[0].country.as_deref(), Some("Россия"));
+        assert_eq!((authors[1].id, authors[1].surname.as_str(), authors[1].name.as_str(), authors[1].patronymic.as_str()), (None, "Smith", "John", ""));
+        assert_eq!(authors[1].country.as_deref(), Some("США"));
+        assert_eq!((authors[2].surname.as_str(), authors[2].name.as_str(), authors[2].country.as_deref()), ("Ли", "", None));
+    }
+
+    #[test]
+    fn parse_authors_rejects_bad_ids()
+    {
+        assert_eq!(parse_authors("Иванов", "x1", "").err().as_deref(), Some("неверный id автора «x1»"));
+        assert!(parse_authors("", "", "").unwrap().is_empty());
+    }
+
+    #[test]
+    fn validate_requires_columns()
+    {
+        assert_eq!(validate(&[]).err().as_deref(), Some("Файл пуст"));
+        assert_eq!(validate(&table(&[&["Название", "Описание"]])).err().as_deref(), Some("Нет обязательных столбцов: Номер, Вид"));
+    }
+
+    #[test]
+    fn validate_collects_row_problems()
+    {
+        let (records, errors) = validate(&table(&[
+            &["ID", "Название", "Номер", "Вид", "TRL", "Доступно для лицензирования", "Авторы"],
+            &["5", "Сплав", "2700000", "Патент", "4", "да", "Иванов Иван"],
+            &["", "", "", "", "", "", ""],
+            &["5", "", "12a", "", "10", "может быть", ""],
+            &["", "'=Формула", "0", "Свидетельство", "", "нет", ""],
+        ])).unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!((records[0].row, records[0].id), (2, Some(5)));
+        assert_eq!(records[0].value("trl"), Some("4"));
+        assert_eq!(records[0].lookup("idType"), Some("Патент"));
+        assert_eq!(records[0].authors.as_ref().map(|authors| authors.len()), Some(1));
+        assert_eq!((records[1].row, records[1].value("name")), (5, Some("=Формула")));
+
+        assert_eq!(errors, [(4, "РИД 5 уже встречался в файле; не указано название; неверный номер «12a»; TRL должен быть от 1 до 9, а не «10»; «может быть» в столбце «Доступно для лицензирования»: нужно да или нет; не указан вид".to_string())]);
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::env;
+
+use actix_session::{Session, SessionExt};
+use actix_web::dev::Payload;
+use actix_web::error::{ErrorServiceUnavailable, InternalError};
+use actix_web::{http::header, web, FromRequest, HttpRequest, HttpResponse, Responder};
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use futures_util::future::LocalBoxFuture;
+use sqlx::mysql::MySqlPool;
+use log::error;
+
+use crate::admin;
+use crate::mail;
+
+/// Session entry with the id of the signed-in inventor.
+const INVENTOR_ID: &str = "inventor";
+
+/// How long a sign-in link stays valid.
+const LINK_MINUTES: u32 = 30;
+
+/// An inventor signed in with a link sent to their address. Handlers taking
+/// it are only reached with a session; everyone else is sent to the page
+/// asking for the address.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Inventor
+{
+    pub id: i32,
+    pub email: String,
+    pub name: Option<String>
+}
+
+impl FromRequest for Inventor
+{
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future
+    {
+        let req = req.clone();
+
+        Box::pin(async move {
+            let id: Option<i32> = req.get_session().get(INVENTOR_ID).unwrap_or(None);
+            let pool = req.app_data::<web::Data<MySqlPool>>().cloned();
+
+            let inventor = match (id, pool)
+            {
+                (Some(id), Some(pool)) => sqlx::query_as("select id, email, name from Inventor where id = ?")
+                    .bind(id)
+                    .fetch_optional(pool.get_ref())
+                    .await
+                    .map_err(|err| {
+                        error!("{}: {}", err, req.path());
+                        ErrorServiceUnavailable(err.to_string())
+                    })?,
+                _ => None
+            };
+
+            match inventor
+            {
+                Some(inventor) => Ok(inventor),
+                None => Err(InternalError::from_response(
+                    "sign-in required",
+                    HttpResponse::SeeOther()
+                        .append_header((header::LOCATION, "/disclose/login"))
+                        .finish()
+                ).into())
+            }
+        })
+    }
+}
+
+/// `INVENTOR_EMAIL_DOMAINS`, comma separated: the addresses sign-in links
+/// are sent to. `rudn.ru` by default.
+fn allowed(email: &str) -> bool
+{
+    let domains = env::var("INVENTOR_EMAIL_DOMAINS").unwrap_or("rudn.ru".to_string());
+
+    email
+        .rsplit_once('@')
+        .is_some_and(|(user, domain)| !user.is_empty() && domains
+            .split(',')
+            .any(|allowed| allowed.trim().eq_ignore_ascii_case(domain)))
+}
+
+fn server_error(err: impl std::fmt::Display, page: &str) -> HttpResponse
+{
+    error!("{}: {}", err, page);
+    HttpResponse::ServiceUnavailable()
+        .content_type("text/html; charset=utf-8")
+        .body(err.to_string())
+}
+
+fn login_page(message: &str, email: &str) -> HttpResponse
+{
+    admin::page("Заявка о РИД: вход", &format!(
+        r#"{}<p>Укажите университетскую почту, на неё придёт ссылка для входа.</p>
+        <form method="post" action="/disclose/login">
+            <p><label>Почта<br><input type="email" name="email" value="{}" size="40" autofocus required></label></p>
+            <p><button type="submit">Получить ссылку</button></p>
+        </form>"#,
+        if message.is_empty() { String::default() } else { format!(r#"<p style="color: #b00020;">{}</p>"#, message) },
+        admin::escape(email)
+    ))
+}
+
+pub async fn login_form() -> impl Responder
+{
+    login_page("", "")
+}
+
+/// Mails a one-time sign-in link to the address.
+pub async fn login(form: web::Form<HashMap<String, String>>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let email = form.get("email").map(|email| email.trim().to_lowercase()).unwrap_or_default();
+
+    if !allowed(&email)
+    {
+        return login_page("Ссылки для входа отправляются только на университетскую почту", &email);
+    }
+
+    // The link must not follow the Host header of whoever asked for it.
+    let Some(base) = env::var("PUBLIC_URL").ok().filter(|url| !url.is_empty()) else {
+        return server_error("PUBLIC_URL is unset", "disclose/login");
+    };
+
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let token: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+
+    let stored = sqlx::query("insert into InventorLogin (token, email, expires_at) values (?, ?, now() + interval ? minute)")
+        .bind(&token)
+        .bind(&email)
+        .bind(LINK_MINUTES)
+        .execute(pool.get_ref())
+        .await;
+
+    if let Err(err) = stored
+    {
+        return server_error(err, "disclose/login");
+    }
+
+    let sent = mail::send(&email, "Вход для подачи заявки о РИД", format!(
+        "Чтобы войти и подать заявку о результате интеллектуальной деятельности, откройте ссылку:\n\n{}/disclose/login/{}\n\nСсылка действует {} минут и только один раз. Если вы не запрашивали вход, просто удалите это письмо.\n",
+        base.trim_end_matches('/'),
+        token,
+        LINK_MINUTES
+    )).await;
+
+    match sent
+    {
+        Ok(()) => admin::page("Заявка о РИД: вход", &format!(
+            "<p>Ссылка для входа отправлена на {}. Она действует {} минут.</p>",
+            admin::escape(&email),
+            LINK_MINUTES
+        )),
+        Err(err) => server_error(err, "disclose/login")
+    }
+}
+
+/// The page the mailed link opens. Mail scanners follow links on their own,
+/// so the link is only used up by the button below.
+pub async fn confirm_form(token: web::Path<String>) -> impl Responder
+{
+    admin::page("Заявка о РИД: вход", &format!(
+        r#"<p>Нажмите кнопку, чтобы войти и подать заявку о РИД.</p>
+        <form method="post" action="/disclose/login/{}">
+            <p><button type="submit" autofocus>Войти</button></p>
+        </form>"#,
+        admin::escape(&token)
+    ))
+}
+
+/// Opens the session of the link's address, creating the inventor on the
+/// first sign-in.
+pub async fn confirm(token: web::Path<String>, session: Session, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let used = sqlx::query("update InventorLogin set used_at = now() where token = ? and used_at is null and expires_at > now()")
+        .bind(token.as_str())
+        .execute(pool.get_ref())
+        .await;
+
+    match used
+    {
+        Ok(result) if result.rows_affected() == 1 => {},
+        Ok(_) => return login_page("Ссылка устарела или уже использована, запросите новую", ""),
+        Err(err) => return server_error(err, "disclose/login")
+    }
+
+    let inserted = sqlx::query(r#"
+        insert into Inventor (email)
+        select email from InventorLogin where token = ?
+        on duplicate key update id = last_insert_id(id)
+        "#)
+        .bind(token.as_str())
+        .execute(pool.get_ref())
+        .await;
+
+    let id = match inserted
+    {
+        Ok(result) => result.last_insert_id() as i32,
+        Err(err) => return server_error(err, "disclose/login")
+    };
+
+    session.renew();
+    if let Err(err) = session.insert(INVENTOR_ID, id)
+    {
+        return server_error(err, "disclose/login");
+    }
+
+    HttpResponse::SeeOther()
+        .append_header((header::LOCATION, "/disclose"))
+        .finish()
+}
+
+pub async fn logout(session: Session) -> impl Responder
+{
+    session.remove(INVENTOR_ID);
+
+    HttpResponse::SeeOther()
+        .append_header((header::LOCATION, "/disclose/login"))
+        .finish()
+}
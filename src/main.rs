@@ -2,11 +2,38 @@ use std::time;
 use std::env;
 
 use actix_web::{web, App, HttpServer};
+use actix_session::{storage::CookieSessionStore, SessionMiddleware};
 use actix_governor::{Governor, GovernorConfigBuilder};
 use sqlx::mysql::MySqlPoolOptions;
 use actix_web::middleware::Logger;
 
 mod handlers;
+mod admin;
+mod deadlines;
+mod agreements;
+mod attachments;
+mod i18n;
+mod authors;
+mod history;
+mod duplicates;
+mod registry;
+mod citations;
+mod export;
+mod import;
+mod patents;
+mod oai;
+mod profiles;
+mod sitemap;
+mod feeds;
+mod openapi;
+mod graphql;
+mod staff;
+mod catalogue;
+mod mail;
+mod inventors;
+mod disclosures;
+mod workflow;
+mod statuses;
 
 
 // <!DOCTYPE html>
@@ -28,6 +55,23 @@ mod handlers;
 //         </html>
 
 
+/// Public pages and the fragments they load. Mounted at the root and under
+/// `/en` and `/ru`, where the prefix selects the locale (see `i18n`).
+fn pages(cfg: &mut web::ServiceConfig)
+{
+    cfg
+        .service(web::resource("/").route(web::get().to(handlers::main_page)))
+        .service(web::resource("/find").route(web::post().to(handlers::find)))
+        .service(web::resource("/best").route(web::get().to(handlers::best)))
+        .service(web::resource("/counters").route(web::get().to(handlers::counters)))
+        .service(web::resource("/nominated").route(web::get().to(handlers::nominated)))
+        .service(web::resource("/facts/{category}").route(web::get().to(handlers::facts)))
+        .service(web::resource("/types").route(web::get().to(handlers::types)))
+        .service(web::resource("/{name}").route(web::get().to(handlers::index)))
+        .service(web::resource("{name}/assets/content.css").route(web::get().to(handlers::styles)))
+        .service(web::resource("/assets/{name}.js").route(web::get().to(handlers::scripts)));
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()>
 {
@@ -57,20 +101,123 @@ async fn main() -> std::io::Result<()>
         .await
         .expect("pool failed");
 
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(String::as_str) == Some("staff")
+    {
+        return staff::command(&pool, &args[2..]).await;
+    }
+
+    let storage = web::Data::new(attachments::Storage::from_env());
+    let session_key = staff::session_key();
+    let graphql_schema = web::Data::new(graphql::schema());
+
+    actix_web::rt::spawn(duplicates::schedule(pool.clone()));
+
     HttpServer::new(move || App::new()
         .wrap(Logger::default())
         .wrap(Governor::new(&governor_conf))
+        .wrap(SessionMiddleware::builder(CookieSessionStore::default(), session_key.clone())
+            .cookie_secure(staff::secure_cookies())
+            .build())
         .app_data(web::Data::new(pool.clone()))
+        .app_data(storage.clone())
+        .app_data(graphql_schema.clone())
         .app_data(web::Data::new(env::var("FILE_PATH").expect("No env var found").clone()))
-        .service(web::resource("/").route(web::get().to(handlers::main_page)))
         .service(web::resource("/favicon.ico").route(web::get().to(handlers::favicon)))
-        .service(web::resource("/find").route(web::post().to(handlers::find)))
-        .service(web::resource("/best").route(web::get().to(handlers::best)))
-        .service(web::resource("/counters").route(web::get().to(handlers::counters)))
-        .service(web::resource("/nominated").route(web::get().to(handlers::nominated)))
-        .service(web::resource("/{name}").route(web::get().to(handlers::index)))
-        .service(web::resource("{name}/assets/content.css").route(web::get().to(handlers::styles)))
-        .service(web::resource("/assets/{name}.js").route(web::get().to(handlers::scripts)))
+        .service(web::resource("/deadlines.ics").route(web::get().to(deadlines::calendar)))
+        .service(web::resource("/admin").route(web::get().to(staff::home)))
+        .service(web::resource("/admin/login")
+            .route(web::get().to(staff::login_form))
+            .route(web::post().to(staff::login)))
+        .service(web::resource("/admin/logout").route(web::post().to(staff::logout)))
+        .service(web::resource("/admin/staff").route(web::get().to(staff::accounts)))
+        .service(web::resource("/admin/staff/{id}").route(web::post().to(staff::update_account)))
+        .service(web::resource("/admin/deadlines").route(web::get().to(deadlines::admin_view)))
+        .service(web::resource("/admin/deadlines/feed").route(web::post().to(deadlines::renew_feed)))
+        .service(web::resource("/admin/agreements").route(web::get().to(agreements::register)))
+        .service(web::resource("/admin/agreements/new")
+            .route(web::get().to(agreements::create_form))
+            .route(web::post().to(agreements::create)))
+        .service(web::resource("/admin/agreements/{id}/edit")
+            .route(web::get().to(agreements::edit_form))
+            .route(web::post().to(agreements::edit)))
+        .service(web::resource("/admin/agreements/{id}/payments").route(web::post().to(agreements::add_payment)))
+        .service(web::resource("/admin/agreements/{id}/payments/{payment}/delete").route(web::post().to(agreements::delete_payment)))
+        .service(web::resource("/admin/revenue").route(web::get().to(agreements::revenue)))
+        .service(web::resource("/admin/rids/{id}/attachments").route(web::post().to(attachments::upload)))
+        .service(web::resource("/admin/attachments/{id}").route(web::delete().to(attachments::delete)))
+        .service(web::resource("/admin/attachments/{id}/delete").route(web::post().to(attachments::delete_form)))
+        .service(web::resource("/admin/workflow").route(web::get().to(workflow::dashboard)))
+        .service(web::resource("/admin/rids/{id}/workflow")
+            .route(web::get().to(workflow::view))
+            .route(web::post().to(workflow::advance)))
+        .service(web::resource("/admin/rids/{id}/status")
+            .route(web::get().to(statuses::list))
+            .route(web::post().to(statuses::add)))
+        .service(web::resource("/admin/rids/{id}/status/{entry}").route(web::post().to(statuses::update)))
+        .service(web::resource("/admin/rids/{id}/history").route(web::get().to(history::list)))
+        .service(web::resource("/admin/rids/{id}/history/compare").route(web::get().to(history::compare)))
+        .service(web::resource("/admin/rids/{id}/history/{version}/rollback").route(web::post().to(history::rollback)))
+        .service(web::resource("/admin/duplicates").route(web::get().to(duplicates::report)))
+        .service(web::resource("/admin/duplicates/scan").route(web::post().to(duplicates::run)))
+        .service(web::resource("/admin/duplicates/{id}/dismiss").route(web::post().to(duplicates::dismiss)))
+        .service(web::resource("/admin/duplicates/{id}/merge/{rid}").route(web::post().to(duplicates::merge)))
+        .service(web::resource("/admin/import")
+            .route(web::get().to(import::form))
+            .route(web::post().to(import::upload)))
+        .service(web::resource("/admin/patents")
+            .route(web::get().to(patents::form))
+            .route(web::post().to(patents::compare)))
+        .service(web::resource("/admin/patents/apply").route(web::post().to(patents::apply)))
+        .service(web::resource("/admin/disclosures").route(web::get().to(disclosures::list)))
+        .service(web::resource("/admin/disclosures/files/{id}").route(web::get().to(disclosures::file)))
+        .service(web::resource("/admin/disclosures/{id}").route(web::get().to(disclosures::view)))
+        .service(web::resource("/admin/disclosures/{id}/convert").route(web::post().to(disclosures::convert)))
+        .service(web::resource("/admin/disclosures/{id}/decline").route(web::post().to(disclosures::decline)))
+        .service(web::resource("/admin/{section}").route(web::get().to(catalogue::list)))
+        .service(web::resource("/admin/{section}/new")
+            .route(web::get().to(catalogue::create_form))
+            .route(web::post().to(catalogue::create)))
+        .service(web::resource("/admin/{section}/{id}/edit")
+            .route(web::get().to(catalogue::edit_form))
+            .route(web::post().to(catalogue::edit)))
+        .service(web::resource("/admin/{section}/{id}/delete")
+            .route(web::get().to(catalogue::delete_form))
+            .route(web::post().to(catalogue::delete)))
+        .service(web::resource("/disclose")
+            .route(web::get().to(disclosures::form))
+            .route(web::post().to(disclosures::submit)))
+        .service(web::resource("/disclose/login")
+            .route(web::get().to(inventors::login_form))
+            .route(web::post().to(inventors::login)))
+        .service(web::resource("/disclose/login/{token}")
+            .route(web::get().to(inventors::confirm_form))
+            .route(web::post().to(inventors::confirm)))
+        .service(web::resource("/disclose/logout").route(web::post().to(inventors::logout)))
+        .service(web::resource("/attachments/{id}").route(web::get().to(attachments::download)))
+        .service(web::resource("/attachments/{id}/thumbnail").route(web::get().to(attachments::thumbnail)))
+        .service(web::resource("/sitemap.xml").route(web::get().to(sitemap::sitemap)))
+        .service(web::resource("/robots.txt").route(web::get().to(sitemap::robots)))
+        .service(web::resource("/feed.atom").route(web::get().to(feeds::atom)))
+        .service(web::resource("/feed.rss").route(web::get().to(feeds::rss)))
+        .service(web::resource("/rids/{id}").route(web::get().to(profiles::rid)))
+        .service(web::resource("/authors/{id}").route(web::get().to(profiles::author)))
+        .service(web::resource("/faculties/{id}").route(web::get().to(profiles::faculty)))
+        .service(web::resource("/rids/{id}/authors").route(web::get().to(authors::of_rid)))
+        .service(web::resource("/rids/{id}/cite").route(web::get().to(citations::rid)))
+        .service(web::resource("/cite").route(web::post().to(citations::search)))
+        .service(web::resource("/export").route(web::post().to(export::search)))
+        .service(web::resource("/oai")
+            .route(web::get().to(oai::get))
+            .route(web::post().to(oai::post)))
+        .service(web::resource("/oai/rid.xsd").route(web::get().to(oai::schema)))
+        .service(web::resource("/graphql")
+            .route(web::get().to(graphql::get))
+            .route(web::post().to(graphql::post)))
+        .configure(openapi::configure)
+        .service(web::scope("/en").configure(pages))
+        .service(web::scope("/ru").configure(pages))
+        .configure(pages)
         // .service(web::resource("/assets/{file}.png").route(web::get().to(handlers::png)))
         // .service(web::resource("/assets/{file}.svg").route(web::get().to(handlers::svg)))
         // .service(web::resource("/assets/{file}.ttf").route(web::get().to(handlers::fonts)))
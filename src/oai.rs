@@ -0,0 +1,826 @@
+use std::collections::HashMap;
+use std::env;
+
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use sqlx::mysql::MySqlPool;
+use log::error;
+
+use crate::authors::{self, Author, NameStyle};
+use crate::citations::HOLDER;
+use crate::export::xml_escape;
+use crate::handlers;
+use crate::registry;
+
+/// Records or identifiers per response; the rest follows a resumption token.
+const PAGE_SIZE: i64 = 100;
+
+const METADATA_PREFIXES: [&str; 2] = ["oai_dc", "rid"];
+
+const DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%SZ";
+
+/// A RID as a harvested item: the time of its last change and the sets it is in.
+/// RIDs deleted since the history began are kept as deleted records.
+#[derive(Debug, sqlx::FromRow)]
+struct Item
+{
+    id: i64,
+    changed: i64,
+    deleted: i64,
+    faculty: Option<i64>,
+    rid_type: Option<i64>
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct HarvestedRid
+{
+    id: i32,
+    name: String,
+    name_en: Option<String>,
+    description: Option<String>,
+    description_en: Option<String>,
+    number: i32,
+    faculty: Option<String>,
+    rid_type: Option<String>,
+    category: Option<String>,
+    year: Option<String>,
+    sub_area: Option<String>,
+    link: Option<String>,
+    registry_link: Option<String>,
+    status: Option<String>,
+    status_date: Option<String>,
+    trl: Option<i8>,
+    available: bool,
+    partnership_terms: Option<String>
+}
+
+/// Selective harvesting arguments of ListIdentifiers and ListRecords, which
+/// the resumption token carries on with the position reached.
+#[derive(Debug, Default)]
+struct Selection
+{
+    prefix: String,
+    set: Option<String>,
+    from: Option<i64>,
+    until: Option<i64>,
+    after: i64,
+    cursor: i64
+}
+
+impl Selection
+{
+    fn token(&self) -> String
+    {
+        let optional = |value: Option<i64>| value.map(|value| value.to_string()).unwrap_or_default();
+
+        format!(
+            "{}!{}!{}!{}!{}!{}",
+            self.prefix,
+            self.set.clone().unwrap_or_default(),
+            optional(self.from),
+            optional(self.until),
+            self.after,
+            self.cursor
+        )
+    }
+
+    fn from_token(token: &str) -> Option<Selection>
+    {
+        let parts: Vec<&str> = token.split('!').collect();
+        let [prefix, set, from, until, after, cursor] = parts.as_slice() else { return None };
+
+        let optional = |value: &str| -> Option<Option<i64>> {
+            match value
+            {
+                "" => Some(None),
+                value => value.parse().ok().map(Some)
+            }
+        };
+
+        Some(Selection {
+            prefix: METADATA_PREFIXES.contains(prefix).then(|| prefix.to_string())?,
+            set: Some(set.to_string()).filter(|set| !set.is_empty()),
+            from: optional(from)?,
+            until: optional(until)?,
+            after: after.parse().ok()?,
+            cursor: cursor.parse().ok()?
+        })
+    }
+
+    /// The ` where ...` part selecting the items after the position reached.
+    fn condition(&self, count: bool) -> Option<String>
+    {
+        let mut conditions: Vec<String> = Vec::new();
+
+        if !count
+        {
+            conditions.push(format!("Item.id > {}", self.after));
+        }
+        if let Some(from) = self.from
+        {
+            conditions.push(format!("Item.changed >= {}", from));
+        }
+        if let Some(until) = self.until
+        {
+            conditions.push(format!("Item.changed <= {}", until));
+        }
+
+        match self.set.as_deref().map(|set| set.split_once(':').unwrap_or((set, "")))
+        {
+            None => {},
+            Some((kind @ ("faculty" | "type"), id)) => {
+                let column = if kind == "faculty" { "Item.faculty" } else { "Item.rid_type" };
+                match id
+                {
+                    "" => conditions.push(format!("{} is not null", column)),
+                    id => conditions.push(format!("{} = {}", column, id.parse::<i64>().ok()?))
+                }
+            },
+            Some(_) => return None
+        }
+
+        match conditions.is_empty()
+        {
+            true => Some(String::default()),
+            false => Some(format!(" where {}", conditions.join(" and ")))
+        }
+    }
+}
+
+/// Every harvested item, present or deleted, with unix times of the last change.
+const ITEMS: &str = r#"
+    (
+        select
+            cast(RID.id as signed) as id,
+            cast(coalesce(unix_timestamp((select max(RIDHistory.changed_at) from RIDHistory where RIDHistory.idRID = RID.id)), 0) as signed) as changed,
+            cast(0 as signed) as deleted,
+            cast(RID.idFaculty as signed) as faculty,
+            cast(RID.idType as signed) as rid_type
+        from RID
+
+        union all
+
+        select
+            cast(RIDHistory.idRID as signed),
+            cast(unix_timestamp(max(RIDHistory.changed_at)) as signed),
+            cast(1 as signed),
+            null,
+            null
+        from RIDHistory
+        left join RID on (RID.id = RIDHistory.idRID)
+        where RID.id is null
+        group by RIDHistory.idRID
+    ) as Item
+"#;
+
+fn datestamp(unix: i64) -> String
+{
+    DateTime::from_timestamp(unix, 0).unwrap_or_default().format(DATE_FORMAT).to_string()
+}
+
+/// `from` and `until` in either granularity, as unix time; a bare `until` date
+/// takes in the whole day.
+fn parse_datestamp(value: &str, end_of_day: bool) -> Option<(i64, bool)>
+{
+    if let Ok(time) = NaiveDateTime::parse_from_str(value, DATE_FORMAT)
+    {
+        return Some((time.and_utc().timestamp(), true));
+    }
+
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok().filter(|_| value.len() == 10)?;
+    let time = if end_of_day { date.and_hms_opt(23, 59, 59)? } else { date.and_hms_opt(0, 0, 0)? };
+
+    Some((time.and_utc().timestamp(), false))
+}
+
+/// `oai:<host>:<id>`, the host taken from the site address.
+fn repository_identifier(base: &str) -> String
+{
+    base.split("://").nth(1).unwrap_or(base).split(['/', ':']).next().unwrap_or_default().to_string()
+}
+
+fn identifier(base: &str, id: i64) -> String
+{
+    format!("oai:{}:{}", repository_identifier(base), id)
+}
+
+fn parse_identifier(base: &str, identifier: &str) -> Option<i64>
+{
+    identifier
+        .strip_prefix(&format!("oai:{}:", repository_identifier(base)))
+        .and_then(|id| id.parse().ok())
+}
+
+fn error(code: &str, message: &str) -> String
+{
+    format!(r#"<error code="{}">{}</error>"#, code, xml_escape(message))
+}
+
+fn header(base: &str, item: &Item) -> String
+{
+    let mut sets: Vec<String> = Vec::new();
+    if let Some(faculty) = item.faculty
+    {
+        sets.push("<setSpec>faculty</setSpec>".to_string());
+        sets.push(format!("<setSpec>faculty:{}</setSpec>", faculty));
+    }
+    if let Some(rid_type) = item.rid_type
+    {
+        sets.push("<setSpec>type</setSpec>".to_string());
+        sets.push(format!("<setSpec>type:{}</setSpec>", rid_type));
+    }
+
+    format!(
+        r#"<header{}><identifier>{}</identifier><datestamp>{}</datestamp>{}</header>"#,
+        if item.deleted != 0 { r#" status="deleted""# } else { "" },
+        identifier(base, item.id),
+        datestamp(item.changed),
+        sets.concat()
+    )
+}
+
+/// Simple element if there is a value; the value is escaped.
+fn element(name: &str, value: Option<&str>) -> String
+{
+    match value.map(str::trim).filter(|value| !value.is_empty())
+    {
+        Some(value) => format!("<{0}>{1}</{0}>", name, xml_escape(value)),
+        None => String::default()
+    }
+}
+
+fn oai_dc(rid: &HarvestedRid, authors: &[Author]) -> String
+{
+    let mut fields: Vec<String> = vec![format!(r#"<dc:title xml:lang="ru">{}</dc:title>"#, xml_escape(rid.name.trim()))];
+
+    if let Some(name_en) = rid.name_en.as_deref().filter(|name| !name.trim().is_empty())
+    {
+        fields.push(format!(r#"<dc:title xml:lang="en">{}</dc:title>"#, xml_escape(name_en.trim())));
+    }
+
+    for author in authors
+    {
+        fields.push(element("dc:creator", Some(&authors::name(author, NameStyle::FULL))));
+    }
+
+    fields.push(element("dc:subject", rid.sub_area.as_deref()));
+    fields.push(element("dc:description", rid.description.as_deref()));
+    if let Some(description_en) = rid.description_en.as_deref().filter(|description| !description.trim().is_empty())
+    {
+        fields.push(format!(r#"<dc:description xml:lang="en">{}</dc:description>"#, xml_escape(description_en.trim())));
+    }
+    fields.push(element("dc:publisher", Some(HOLDER)));
+    fields.push(element("dc:date", rid.year.as_deref()));
+    fields.push(element("dc:type", rid.rid_type.as_deref()));
+    fields.push(element("dc:identifier", registry::link(rid.link.as_deref(), rid.registry_link.as_deref(), rid.number).as_deref()));
+    if rid.number > 0
+    {
+        fields.push(element("dc:identifier", Some(&format!("№ {}", rid.number))));
+    }
+    fields.push(element("dc:language", Some("ru")));
+    fields.push(element("dc:rights", Some(HOLDER)));
+
+    format!(
+        r#"<oai_dc:dc xmlns:oai_dc="http://www.openarchives.org/OAI/2.0/oai_dc/" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:schemaLocation="http://www.openarchives.org/OAI/2.0/oai_dc/ http://www.openarchives.org/OAI/2.0/oai_dc.xsd">{}</oai_dc:dc>"#,
+        fields.concat()
+    )
+}
+
+/// The `rid` schema (`/oai/rid.xsd`): every field of the record, with
+/// structured authors.
+fn rid_record(base: &str, rid: &HarvestedRid, authors: &[Author]) -> String
+{
+    let authors: Vec<String> = authors
+        .iter()
+        .enumerate()
+        .map(|(i, author)| format!(
+            r#"<author sequence="{}" id="{}">{}{}{}{}</author>"#,
+            author.position.unwrap_or(i as i32 + 1),
+            author.id,
+            element("surname", Some(&author.surname)),
+            element("givenName", author.name.as_deref()),
+            element("patronymic", author.patronymic.as_deref()),
+            element("country", author.country.as_deref())
+        ))
+        .collect();
+
+    let status = match (&rid.status, &rid.status_date)
+    {
+        (Some(status), Some(date)) => format!(r#"<status date="{}">{}</status>"#, xml_escape(date), xml_escape(status)),
+        _ => String::default()
+    };
+
+    format!(
+        r#"<rid xmlns="{0}/oai/rid" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:schemaLocation="{0}/oai/rid {0}/oai/rid.xsd" id="{1}">{2}{3}{4}{5}{6}{7}{8}{9}{10}{11}{12}{13}{14}{15}<authors>{16}</authors>{17}</rid>"#,
+        base,
+        rid.id,
+        element("name", Some(&rid.name)),
+        element("nameEn", rid.name_en.as_deref()),
+        element("description", rid.description.as_deref()),
+        element("descriptionEn", rid.description_en.as_deref()),
+        element("number", Some(&rid.number.to_string())),
+        match &rid.rid_type
+        {
+            Some(rid_type) => format!(
+                r#"<type{}>{}</type>"#,
+                rid.category.as_deref().map(|category| format!(r#" category="{}""#, xml_escape(category))).unwrap_or_default(),
+                xml_escape(rid_type)
+            ),
+            None => String::default()
+        },
+        element("faculty", rid.faculty.as_deref()),
+        element("subjectArea", rid.sub_area.as_deref()),
+        element("year", rid.year.as_deref()),
+        status,
+        element("trl", rid.trl.map(|trl| trl.to_string()).as_deref()),
+        element("available", Some(if rid.available { "true" } else { "false" })),
+        element("partnershipTerms", rid.partnership_terms.as_deref()),
+        element("link", registry::link(rid.link.as_deref(), rid.registry_link.as_deref(), rid.number).as_deref()),
+        authors.concat(),
+        element("holder", Some(HOLDER))
+    )
+}
+
+async fn items(pool: &MySqlPool, condition: &str, limit: i64) -> Result<Vec<Item>, sqlx::Error>
+{
+    sqlx::query_as(&format!("select * from {} {} order by Item.id limit {}", ITEMS, condition, limit))
+        .fetch_all(pool)
+        .await
+}
+
+/// Metadata of every present RID among `items` in `prefix`, by RID id.
+async fn metadata(pool: &MySqlPool, base: &str, prefix: &str, items: &[Item]) -> Result<HashMap<i64, String>, sqlx::Error>
+{
+    let ids: Vec<String> = items.iter().filter(|item| item.deleted == 0).map(|item| item.id.to_string()).collect();
+
+    if ids.is_empty()
+    {
+        return Ok(HashMap::new());
+    }
+
+    let rids: Vec<HarvestedRid> = sqlx::query_as(&format!(r#"
+        select
+            RID.id as id,
+            RID.name as name,
+            RID.name_en as name_en,
+            RID.description as description,
+            RID.description_en as description_en,
+            RID.numPotent as number,
+            Faculty.name as faculty,
+            Type.name as rid_type,
+            cast(Type.category as char) as category,
+            cast(Year.year as char) as year,
+            SubjectArea.name as sub_area,
+            RID.link as link,
+            Type.registry_link as registry_link,
+            cast(CurrentStatus.status as char) as status,
+            date_format(CurrentStatus.date, '%Y-%m-%d') as status_date,
+            RID.trl as trl,
+            RID.available as available,
+            RID.partnership_terms as partnership_terms
+        {}
+        where RID.id in ({})
+        "#, handlers::FILTERED_RIDS, ids.join(", ")))
+        .fetch_all(pool)
+        .await?;
+
+    let rid_ids: Vec<i32> = rids.iter().map(|rid| rid.id).collect();
+    let authors = authors::for_rids(pool, &rid_ids).await?;
+
+    Ok(rids
+        .iter()
+        .map(|rid| {
+            let authors = authors.get(&rid.id).map(|authors| authors.as_slice()).unwrap_or_default();
+            let record = match prefix
+            {
+                "oai_dc" => oai_dc(rid, authors),
+                _ => rid_record(base, rid, authors)
+            };
+            (rid.id.into(), record)
+        })
+        .collect())
+}
+
+fn record(base: &str, item: &Item, metadata: &HashMap<i64, String>) -> String
+{
+    match metadata.get(&item.id)
+    {
+        Some(metadata) => format!("<record>{}<metadata>{}</metadata></record>", header(base, item), metadata),
+        None => format!("<record>{}</record>", header(base, item))
+    }
+}
+
+fn identify(base: &str, earliest: i64) -> String
+{
+    let email = env::var("OAI_ADMIN_EMAIL").unwrap_or(format!("webmaster@{}", repository_identifier(base)));
+
+    format!(
+        r#"<Identify><repositoryName>РИД РУДН</repositoryName><baseURL>{0}/oai</baseURL><protocolVersion>2.0</protocolVersion><adminEmail>{1}</adminEmail><earliestDatestamp>{2}</earliestDatestamp><deletedRecord>transient</deletedRecord><granularity>YYYY-MM-DDThh:mm:ssZ</granularity><description><oai-identifier xmlns="http://www.openarchives.org/OAI/2.0/oai-identifier" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:schemaLocation="http://www.openarchives.org/OAI/2.0/oai-identifier http://www.openarchives.org/OAI/2.0/oai-identifier.xsd"><scheme>oai</scheme><repositoryIdentifier>{3}</repositoryIdentifier><delimiter>:</delimiter><sampleIdentifier>{4}</sampleIdentifier></oai-identifier></description></Identify>"#,
+        base,
+        xml_escape(&email),
+        datestamp(earliest),
+        repository_identifier(base),
+        identifier(base, 1)
+    )
+}
+
+fn metadata_formats(base: &str) -> String
+{
+    format!(
+        r#"<ListMetadataFormats><metadataFormat><metadataPrefix>oai_dc</metadataPrefix><schema>http://www.openarchives.org/OAI/2.0/oai_dc.xsd</schema><metadataNamespace>http://www.openarchives.org/OAI/2.0/oai_dc/</metadataNamespace></metadataFormat><metadataFormat><metadataPrefix>rid</metadataPrefix><schema>{0}/oai/rid.xsd</schema><metadataNamespace>{0}/oai/rid</metadataNamespace></metadataFormat></ListMetadataFormats>"#,
+        base
+    )
+}
+
+async fn sets(pool: &MySqlPool) -> Result<String, sqlx::Error>
+{
+    let faculties: Vec<(i32, String)> = sqlx::query_as("select id, name from Faculty order by name").fetch_all(pool).await?;
+    let types: Vec<(i32, String)> = sqlx::query_as("select id, name from Type order by id").fetch_all(pool).await?;
+
+    let set = |spec: String, name: &str| format!("<set><setSpec>{}</setSpec><setName>{}</setName></set>", spec, xml_escape(name));
+
+    let mut body = String::from("<ListSets>");
+    body.push_str(&set("faculty".to_string(), "Факультеты и институты"));
+    for (id, name) in &faculties
+    {
+        body.push_str(&set(format!("faculty:{}", id), name));
+    }
+    body.push_str(&set("type".to_string(), "Виды РИД"));
+    for (id, name) in &types
+    {
+        body.push_str(&set(format!("type:{}", id), name));
+    }
+    body.push_str("</ListSets>");
+
+    Ok(body)
+}
+
+/// ListIdentifiers or ListRecords for one page of `selection`.
+async fn list(pool: &MySqlPool, base: &str, verb: &str, selection: Selection) -> Result<String, sqlx::Error>
+{
+    let (Some(condition), Some(count_condition)) = (selection.condition(false), selection.condition(true)) else {
+        return Ok(error("badArgument", "Unknown set"));
+    };
+
+    let mut page = items(pool, &condition, PAGE_SIZE + 1).await?;
+
+    if page.is_empty()
+    {
+        return Ok(error("noRecordsMatch", "No records match the request"));
+    }
+
+    let more = page.len() as i64 > PAGE_SIZE;
+    page.truncate(PAGE_SIZE as usize);
+
+    let metadata = match verb
+    {
+        "ListRecords" => metadata(pool, base, &selection.prefix, &page).await?,
+        _ => HashMap::new()
+    };
+
+    let entries: Vec<String> = page
+        .iter()
+        .map(|item| match verb
+        {
+            "ListRecords" => record(base, item, &metadata),
+            _ => header(base, item)
+        })
+        .collect();
+
+    // The token is sent on every page of a resumed list, empty on the last one.
+    let cursor = selection.cursor;
+    let token = if more || cursor > 0
+    {
+        let size: i64 = sqlx::query_scalar(&format!("select count(*) from {} {}", ITEMS, count_condition))
+            .fetch_one(pool)
+            .await?;
+
+        let next = Selection {
+            after: page.last().map(|item| item.id).unwrap_or_default(),
+            cursor: cursor + page.len() as i64,
+            ..selection
+        };
+
+        format!(
+            r#"<resumptionToken completeListSize="{}" cursor="{}">{}</resumptionToken>"#,
+            size,
+            cursor,
+            if more { xml_escape(&next.token()) } else { String::default() }
+        )
+    }
+    else
+    {
+        String::default()
+    };
+
+    Ok(format!("<{0}>{1}{2}</{0}>", verb, entries.concat(), token))
+}
+
+/// Arguments each verb takes: required, optional, and the exclusive one.
+fn arguments(verb: &str) -> Option<(&'static [&'static str], &'static [&'static str], Option<&'static str>)>
+{
+    match verb
+    {
+        "Identify" => Some((&[], &[], None)),
+        "ListMetadataFormats" => Some((&[], &["identifier"], None)),
+        "ListSets" => Some((&[], &[], Some("resumptionToken"))),
+        "GetRecord" => Some((&["identifier", "metadataPrefix"], &[], None)),
+        "ListIdentifiers" | "ListRecords" => Some((&["metadataPrefix"], &["from", "until", "set"], Some("resumptionToken"))),
+        _ => None
+    }
+}
+
+/// The body of the response and whether the request was well-formed enough
+/// to be echoed with its arguments.
+async fn respond(pool: &MySqlPool, base: &str, args: &HashMap<String, String>) -> Result<(String, bool), sqlx::Error>
+{
+    let Some(verb) = args.get("verb") else { return Ok((error("badVerb", "Missing verb"), false)) };
+    let Some((required, optional, exclusive)) = arguments(verb) else { return Ok((error("badVerb", "Illegal verb"), false)) };
+
+    let given: Vec<&str> = args.keys().map(String::as_str).filter(|key| *key != "verb").collect();
+    let valid = match exclusive.filter(|exclusive| given.contains(exclusive))
+    {
+        Some(_) => given.len() == 1,
+        None => required.iter().all(|key| given.contains(key)) && given.iter().all(|key| required.contains(key) || optional.contains(key))
+    };
+
+    if !valid
+    {
+        return Ok((error("badArgument", "Missing, illegal or repeated arguments"), false));
+    }
+
+    let body = match verb.as_str()
+    {
+        "Identify" => {
+            let earliest: Option<i64> = sqlx::query_scalar("select cast(unix_timestamp(min(changed_at)) as signed) from RIDHistory")
+                .fetch_one(pool)
+                .await?;
+            identify(base, earliest.unwrap_or_default())
+        },
+        "ListMetadataFormats" => match args.get("identifier")
+        {
+            Some(identifier) => {
+                let id = parse_identifier(base, identifier).unwrap_or_default();
+                match items(pool, &format!(" where Item.id = {}", id), 1).await?.is_empty()
+                {
+                    true => error("idDoesNotExist", "No such identifier"),
+                    false => metadata_formats(base)
+                }
+            },
+            None => metadata_formats(base)
+        },
+        "ListSets" => match args.get("resumptionToken")
+        {
+            Some(_) => error("badResumptionToken", "Sets are listed at once"),
+            None => sets(pool).await?
+        },
+        "GetRecord" => {
+            let prefix = &args["metadataPrefix"];
+            let id = parse_identifier(base, &args["identifier"]).unwrap_or_default();
+            let found = items(pool, &format!(" where Item.id = {}", id), 1).await?;
+
+            match found.first()
+            {
+                None => error("idDoesNotExist", "No such identifier"),
+                Some(_) if !METADATA_PREFIXES.contains(&prefix.as_str()) => error("cannotDisseminateFormat", "Unknown metadata format"),
+                Some(item) => format!("<GetRecord>{}</GetRecord>", record(base, item, &metadata(pool, base, prefix, &found).await?))
+            }
+        },
+        _ => {
+            let selection = match args.get("resumptionToken")
+            {
+                Some(token) => match Selection::from_token(token)
+                {
+                    Some(selection) => selection,
+                    None => return Ok((error("badResumptionToken", "Invalid or expired resumption token"), true))
+                },
+                None => {
+                    let prefix = &args["metadataPrefix"];
+                    if !METADATA_PREFIXES.contains(&prefix.as_str())
+                    {
+                        return Ok((error("cannotDisseminateFormat", "Unknown metadata format"), true));
+                    }
+
+                    let from = args.get("from").map(|from| parse_datestamp(from, false));
+                    let until = args.get("until").map(|until| parse_datestamp(until, true));
+
+                    let granularity = |bound: &Option<Option<(i64, bool)>>| bound.map(|bound| bound.map(|(_, time)| time));
+                    if matches!(from, Some(None)) || matches!(until, Some(None))
+                        || (from.is_some() && until.is_some() && granularity(&from) != granularity(&until))
+                    {
+                        return Ok((error("badArgument", "Invalid from or until"), true));
+                    }
+
+                    Selection {
+                        prefix: prefix.clone(),
+                        set: args.get("set").cloned(),
+                        from: from.flatten().map(|(time, _)| time),
+                        until: until.flatten().map(|(time, _)| time),
+                        ..Selection::default()
+                    }
+                }
+            };
+
+            list(pool, base, verb, selection).await?
+        }
+    };
+
+    Ok((body, true))
+}
+
+async fn provider(req: HttpRequest, pairs: Vec<(String, String)>, pool: &MySqlPool) -> HttpResponse
+{
+    let base = handlers::base_url(&req);
+
+    let mut args: HashMap<String, String> = HashMap::new();
+    let mut repeated = false;
+    for (key, value) in pairs
+    {
+        repeated |= args.insert(key, value).is_some();
+    }
+
+    let result = if repeated
+    {
+        Ok((error("badArgument", "Repeated argument"), false))
+    }
+    else
+    {
+        respond(pool, &base, &args).await
+    };
+
+    let (body, echo) = match result
+    {
+        Ok(result) => result,
+        Err(err) => {
+            error!("{}: oai", err);
+            return HttpResponse::ServiceUnavailable()
+                .content_type("text/html; charset=utf-8")
+                .body(err.to_string());
+        }
+    };
+
+    let attributes: String = match echo
+    {
+        true => {
+            let mut args: Vec<(&String, &String)> = args.iter().collect();
+            args.sort();
+            args.iter().map(|(key, value)| format!(r#" {}="{}""#, key, xml_escape(value))).collect()
+        },
+        false => String::default()
+    };
+
+    HttpResponse::Ok()
+        .content_type("text/xml; charset=utf-8")
+        .body(format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<OAI-PMH xmlns="http://www.openarchives.org/OAI/2.0/" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:schemaLocation="http://www.openarchives.org/OAI/2.0/ http://www.openarchives.org/OAI/2.0/OAI-PMH.xsd"><responseDate>{}</responseDate><request{}>{}/oai</request>{}</OAI-PMH>"#,
+            Utc::now().format(DATE_FORMAT),
+            attributes,
+            base,
+            body
+        ))
+}
+
+/// OAI-PMH 2.0 requests sent as a query string.
+#[utoipa::path(
+    get,
+    path = "/oai",
+    tag = "harvesting",
+    params(
+        ("verb" = String, Query, description = "`Identify`, `ListMetadataFormats`, `ListSets`, `ListIdentifiers`, `ListRecords` or `GetRecord`"),
+        ("metadataPrefix" = Option<String>, Query, description = "`oai_dc` or `rid`"),
+        ("identifier" = Option<String>, Query),
+        ("set" = Option<String>, Query, description = "`faculty`, `faculty:{id}`, `type` or `type:{id}`"),
+        ("from" = Option<String>, Query),
+        ("until" = Option<String>, Query),
+        ("resumptionToken" = Option<String>, Query)
+    ),
+    responses((status = 200, description = "OAI-PMH response, errors included", content_type = "text/xml"))
+)]
+pub async fn get(req: HttpRequest, query: web::Query<Vec<(String, String)>>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    provider(req, query.into_inner(), pool.get_ref()).await
+}
+
+/// OAI-PMH 2.0 requests sent as a form.
+pub async fn post(req: HttpRequest, form: web::Form<Vec<(String, String)>>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    provider(req, form.into_inner(), pool.get_ref()).await
+}
+
+/// XML schema of the `rid` metadata format.
+pub async fn schema(req: HttpRequest) -> impl Responder
+{
+    HttpResponse::Ok()
+        .content_type("text/xml; charset=utf-8")
+        .body(format!(r#"<?xml version="1.0" encoding="UTF-8"?>
+<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema" targetNamespace="{0}/oai/rid" xmlns="{0}/oai/rid" elementFormDefault="qualified">
+  <xs:element name="rid">
+    <xs:complexType>
+      <xs:sequence>
+        <xs:element name="name" type="xs:string"/>
+        <xs:element name="nameEn" type="xs:string" minOccurs="0"/>
+        <xs:element name="description" type="xs:string" minOccurs="0"/>
+        <xs:element name="descriptionEn" type="xs:string" minOccurs="0"/>
+        <xs:element name="number" type="xs:integer"/>
+        <xs:element name="type" minOccurs="0">
+          <xs:complexType>
+            <xs:simpleContent>
+              <xs:extension base="xs:string">
+                <xs:attribute name="category" type="xs:string"/>
+              </xs:extension>
+            </xs:simpleContent>
+          </xs:complexType>
+        </xs:element>
+        <xs:element name="faculty" type="xs:string" minOccurs="0"/>
+        <xs:element name="subjectArea" type="xs:string" minOccurs="0"/>
+        <xs:element name="year" type="xs:gYear" minOccurs="0"/>
+        <xs:element name="status" minOccurs="0">
+          <xs:complexType>
+            <xs:simpleContent>
+              <xs:extension base="xs:string">
+                <xs:attribute name="date" type="xs:date" use="required"/>
+              </xs:extension>
+            </xs:simpleContent>
+          </xs:complexType>
+        </xs:element>
+        <xs:element name="trl" type="xs:integer" minOccurs="0"/>
+        <xs:element name="available" type="xs:boolean"/>
+        <xs:element name="partnershipTerms" type="xs:string" minOccurs="0"/>
+        <xs:element name="link" type="xs:anyURI" minOccurs="0"/>
+        <xs:element name="authors">
+          <xs:complexType>
+            <xs:sequence>
+              <xs:element name="author" minOccurs="0" maxOccurs="unbounded">
+                <xs:complexType>
+                  <xs:sequence>
+                    <xs:element name="surname" type="xs:string"/>
+                    <xs:element name="givenName" type="xs:string" minOccurs="0"/>
+                    <xs:element name="patronymic" type="xs:string" minOccurs="0"/>
+                    <xs:element name="country" type="xs:string" minOccurs="0"/>
+                  </xs:sequence>
+                  <xs:attribute name="sequence" type="xs:integer" use="required"/>
+                  <xs:attribute name="id" type="xs:integer" use="required"/>
+                </xs:complexType>
+              </xs:element>
+            </xs:sequence>
+          </xs:complexType>
+        </xs:element>
+        <xs:element name="holder" type="xs:string"/>
+      </xs:sequence>
+      <xs:attribute name="id" type="xs:integer" use="required"/>
+    </xs:complexType>
+  </xs:element>
+</xs:schema>
+"#, handlers::base_url(&req)))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn round_trip(selection: &Selection) -> Selection
+    {
+        Selection::from_token(&selection.token()).unwrap()
+    }
+
+    #[test]
+    fn token_round_trip_full()
+    {
+        let selection = Selection {
+            prefix: "rid".to_string(),
+            set: Some("type:3".to_string()),
+            from: Some(1_600_000_000),
+            until: Some(1_700_000_000),
+            after: 1234,
+            cursor: 100
+        };
+        let restored = round_trip(&selection);
+
+        assert_eq!(selection.token(), "rid!type:3!1600000000!1700000000!1234!100");
+        assert_eq!(
+            (restored.prefix, restored.set, restored.from, restored.until, restored.after, restored.cursor),
+            (selection.prefix, selection.set, selection.from, selection.until, selection.after, selection.cursor)
+        );
+    }
+
+    #[test]
+    fn token_round_trip_without_options()
+    {
+        let selection = Selection { prefix: "oai_dc".to_string(), ..Selection::default() };
+        let restored = round_trip(&selection);
+
+        assert_eq!(selection.token(), "oai_dc!!!!0!0");
+        assert_eq!((restored.set, restored.from, restored.until, restored.after, restored.cursor), (None, None, None, 0, 0));
+    }
+
+    #[test]
+    fn from_token_rejects_bad_tokens()
+    {
+        for token in ["", "marc!!!!0!0", "rid!!!!0", "rid!!!!0!0!0", "rid!!x!!0!0", "rid!!!!a!0", "rid!!!!0!"]
+        {
+            assert!(Selection::from_token(token).is_none(), "{}", token);
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use actix_web::{web, HttpResponse, Responder};
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::{authors, citations, export, feeds, handlers, oai};
+
+/// The public API. Paths and schemas come from the `#[utoipa::path]`
+/// annotations of the handlers and the structs they return; the admin pages
+/// are left out.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "РИД РУДН",
+        description = "Results of intellectual activity of RUDN University. The HTML endpoints answer with JSON when the request accepts `application/json`; `/en/...` and `/ru/...` prefixes (or `Accept-Language`) choose the language of `/find`, `/best` and `/counters`."
+    ),
+    paths(
+        handlers::find,
+        handlers::counters,
+        handlers::best,
+        citations::search,
+        export::search,
+        authors::of_rid,
+        citations::rid,
+        feeds::atom,
+        feeds::rss,
+        oai::get
+    ),
+    components(schemas(
+        handlers::Rid,
+        handlers::BestRID,
+        handlers::Counters,
+        handlers::SubjectAreaFilter,
+        handlers::YearFilter,
+        handlers::FacultyFilter,
+        handlers::TypeFilter,
+        handlers::StatusFilter,
+        handlers::TrlFilter,
+        authors::Author
+    )),
+    tags(
+        (name = "search", description = "The portfolio search and what is built from its results"),
+        (name = "rids", description = "Single RIDs"),
+        (name = "feeds", description = "Newly added and granted RIDs"),
+        (name = "harvesting", description = "OAI-PMH 2.0")
+    )
+)]
+pub struct ApiDoc;
+
+/// The OpenAPI 3 document at `/openapi.json` and Swagger UI for it at
+/// `/docs/`, served from the assets built into the binary.
+pub fn configure(cfg: &mut web::ServiceConfig)
+{
+    cfg
+        .service(SwaggerUi::new("/docs/{_:.*}").url("/openapi.json", ApiDoc::openapi()))
+        .service(web::resource("/docs").route(web::get().to(docs)));
+}
+
+async fn docs() -> impl Responder
+{
+    HttpResponse::PermanentRedirect()
+        .append_header(("Location", "/docs/"))
+        .finish()
+}
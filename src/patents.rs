@@ -0,0 +1,613 @@
+use std::collections::HashMap;
+
+use actix_multipart::Multipart;
+use actix_web::{http::header, web, HttpResponse, Responder};
+use futures_util::{StreamExt, TryStreamExt};
+use roxmltree::{Document, Node, ParsingOptions};
+use serde::{Deserialize, Serialize};
+use sqlx::mysql::{MySqlConnection, MySqlPool};
+use sqlx::{Connection, Row};
+use log::error;
+
+use crate::admin::{self, escape};
+use crate::authors::{self, NameStyle};
+use crate::import::{self, ImportedAuthor, Problem};
+use crate::staff::Staff;
+
+const MAX_SIZE: usize = 10 * 1024 * 1024;
+
+/// Root elements of one patent document: ST.36 and the national variants
+/// built on it, then ST.96.
+const DOCUMENTS: [&str; 5] = ["patent-document", "ru-patent-document", "us-patent-grant", "PatentPublication", "PatentGrant"];
+
+/// RID columns taken over from the registry as they are.
+const COLUMNS: [&str; 5] = ["name", "name_en", "description", "description_en", "numPotent"];
+
+/// Compared dates of StatusHistory with their labels.
+const DATES: [(&str, &str); 3] = [
+    ("filed", "Заявка подана"),
+    ("published", "Заявка опубликована"),
+    ("granted", "Выдан охранный документ"),
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Inventor
+{
+    surname: String,
+    name: String,
+    patronymic: String
+}
+
+impl Inventor
+{
+    fn full_name(&self) -> String
+    {
+        [self.surname.as_str(), self.name.as_str(), self.patronymic.as_str()].join(" ").trim().to_string()
+    }
+}
+
+/// Bibliographic data of one patent document.
+#[derive(Debug, Default)]
+struct Bibliographic
+{
+    standard: &'static str,
+    country: String,
+    number: i32,
+    kind: String,
+    title: Option<String>,
+    title_en: Option<String>,
+    summary: Option<String>,
+    summary_en: Option<String>,
+    filed: Option<String>,
+    published: Option<String>,
+    granted: Option<String>,
+    inventors: Vec<Inventor>
+}
+
+impl Bibliographic
+{
+    fn year(&self) -> Option<String>
+    {
+        self.granted.as_ref().or(self.published.as_ref()).map(|date| date[..4].to_string())
+    }
+
+    /// Registry values of the compared fields, keyed as in the apply form.
+    fn values(&self) -> Vec<(&'static str, &'static str, Option<String>)>
+    {
+        let mut values = vec![
+            ("name", "Название", self.title.clone()),
+            ("name_en", "Название (англ.)", self.title_en.clone()),
+            ("description", "Описание", self.summary.clone()),
+            ("description_en", "Описание (англ.)", self.summary_en.clone()),
+            ("numPotent", "Номер", Some(self.number.to_string())),
+            ("year", "Год", self.year()),
+            ("authors", "Авторы", Some(self.inventors.iter().map(Inventor::full_name).collect::<Vec<String>>().join("; ")).filter(|names| !names.is_empty())),
+        ];
+
+        for ((status, label), date) in DATES.into_iter().zip([&self.filed, &self.published, &self.granted])
+        {
+            values.push((status, label, date.clone()));
+        }
+
+        values
+    }
+}
+
+fn server_error(err: impl std::fmt::Display, page: &str) -> HttpResponse
+{
+    error!("{}: {}", err, page);
+    HttpResponse::ServiceUnavailable()
+        .content_type("text/html; charset=utf-8")
+        .body(err.to_string())
+}
+
+fn bad_request(message: &str) -> HttpResponse
+{
+    HttpResponse::BadRequest()
+        .content_type("text/html; charset=utf-8")
+        .body(message.to_string())
+}
+
+/// Elements are matched by local name, so that both the unprefixed ST.36 and
+/// the namespaced ST.96 (`pat:`, `com:`) documents are read the same way.
+fn find<'a, 'input>(node: Node<'a, 'input>, names: &[&str]) -> Option<Node<'a, 'input>>
+{
+    node.descendants().find(|child| child.is_element() && names.contains(&child.tag_name().name()))
+}
+
+fn find_all<'a, 'input>(node: Node<'a, 'input>, names: &[&str]) -> Vec<Node<'a, 'input>>
+{
+    node.descendants().filter(|child| child.is_element() && names.contains(&child.tag_name().name())).collect()
+}
+
+/// Text of an element with its children, paragraphs on separate lines.
+fn text(node: Node) -> String
+{
+    let mut paragraphs: Vec<String> = Vec::new();
+    let mut current = String::default();
+
+    for child in node.descendants()
+    {
+        if child.is_element() && ["p", "P"].contains(&child.tag_name().name()) && !current.trim().is_empty()
+        {
+            paragraphs.push(current.split_whitespace().collect::<Vec<&str>>().join(" "));
+            current.clear();
+        }
+        if let Some(text) = child.text().filter(|_| child.is_text())
+        {
+            current.push_str(text);
+            current.push(' ');
+        }
+    }
+    if !current.trim().is_empty()
+    {
+        paragraphs.push(current.split_whitespace().collect::<Vec<&str>>().join(" "));
+    }
+
+    paragraphs.join("\n")
+}
+
+fn find_text(node: Node, names: &[&str]) -> Option<String>
+{
+    find(node, names).map(text).filter(|text| !text.is_empty())
+}
+
+/// `lang` in ST.36, `com:languageCode` in ST.96.
+fn language<'a>(node: Node<'a, '_>) -> Option<&'a str>
+{
+    node.attributes().find(|attribute| ["lang", "languageCode"].contains(&attribute.name())).map(|attribute| attribute.value())
+}
+
+/// Text of the element in `lang`, or of the one without a language for Russian.
+fn in_language(node: Node, names: &[&str], lang: &str) -> Option<String>
+{
+    let nodes = find_all(node, names);
+
+    nodes
+        .iter()
+        .find(|node| language(**node).is_some_and(|code| code.eq_ignore_ascii_case(lang)))
+        .or_else(|| nodes.iter().find(|node| lang == "ru" && language(**node).is_none()))
+        .map(|node| text(*node))
+        .filter(|text| !text.is_empty())
+}
+
+/// `YYYY-MM-DD` from `YYYYMMDD` (ST.36) or `YYYY-MM-DD` (ST.96).
+fn date(value: &str) -> Option<String>
+{
+    let digits: String = value.chars().filter(char::is_ascii_digit).collect();
+
+    match digits.len()
+    {
+        8 => Some(format!("{}-{}-{}", &digits[..4], &digits[4..6], &digits[6..])),
+        _ => None
+    }
+}
+
+fn find_date(node: Node, names: &[&str]) -> Option<String>
+{
+    find_text(node, names).and_then(|value| date(&value))
+}
+
+fn inventor(node: Node) -> Inventor
+{
+    let part = |names: &[&str]| find_text(node, names).unwrap_or_default();
+
+    let mut surname = part(&["last-name", "LastName"]);
+    let mut name = part(&["first-name", "FirstName"]);
+    let mut patronymic = part(&["middle-name", "MiddleName"]);
+
+    // Russian offices put the patronymic after the first name, and some
+    // documents give the whole name in one element.
+    if surname.is_empty()
+    {
+        let full_name = part(&["name", "PersonFullName"]);
+        let mut parts = full_name.split_whitespace();
+        surname = parts.next().unwrap_or_default().to_string();
+        name = parts.next().unwrap_or_default().to_string();
+        patronymic = parts.collect::<Vec<&str>>().join(" ");
+    }
+    else if patronymic.is_empty() && let Some((first, rest)) = name.clone().split_once(' ')
+    {
+        name = first.to_string();
+        patronymic = rest.trim().to_string();
+    }
+
+    Inventor { surname, name, patronymic }
+}
+
+/// The patent documents of an ST.36 or ST.96 file.
+fn parse(xml: &str) -> Result<Vec<Bibliographic>, String>
+{
+    // DTDs are common in ST.36 files; roxmltree never loads external ones.
+    let options = ParsingOptions { allow_dtd: true, ..ParsingOptions::default() };
+    let document = Document::parse_with_options(xml, options).map_err(|err| err.to_string())?;
+
+    let roots = find_all(document.root(), &DOCUMENTS);
+    let roots = if roots.is_empty() { vec![document.root_element()] } else { roots };
+
+    roots
+        .into_iter()
+        .map(|root| {
+            let st96 = root.tag_name().namespace().is_some_and(|namespace| namespace.contains("wipo.int/standards/XMLSchema/ST96"));
+
+            let publication = find(root, &["publication-reference", "PatentPublicationIdentification", "PublicationIdentification"]).unwrap_or(root);
+            let grant = find(root, &["PatentGrantIdentification"]);
+            let application = find(root, &["application-reference", "ApplicationIdentification"]);
+
+            let number = grant
+                .and_then(|grant| find_text(grant, &["PatentNumber"]))
+                .or_else(|| find_text(publication, &["doc-number", "PatentNumber", "PublicationNumber"]))
+                .or_else(|| root.attribute("doc-number").map(str::to_string))
+                .unwrap_or_default();
+            let number = number
+                .chars()
+                .filter(char::is_ascii_digit)
+                .collect::<String>()
+                .parse::<i32>()
+                .map_err(|_| format!("Нет номера документа: «{}»", number))?;
+
+            let kind = find_text(publication, &["kind", "PatentDocumentKindCode"])
+                .or_else(|| root.attribute("kind").map(str::to_string))
+                .unwrap_or_default();
+
+            let published_on = find_date(publication, &["date", "PublicationDate"]).or_else(|| root.attribute("date").and_then(date));
+
+            // B and C documents (U for utility models) are published on grant,
+            // A documents are published applications.
+            let granted_kind = kind.starts_with(['B', 'C', 'U', 'S']);
+            let granted = grant
+                .and_then(|grant| find_date(grant, &["GrantDate"]))
+                .or_else(|| find_date(root, &["GrantDate"]))
+                .or(if granted_kind { published_on.clone() } else { None });
+            let published = if granted_kind { None } else { published_on };
+
+            Ok(Bibliographic {
+                standard: if st96 { "ST.96" } else { "ST.36" },
+                country: find_text(publication, &["country", "IPOfficeCode"])
+                    .or_else(|| root.attribute("country").map(str::to_string))
+                    .unwrap_or_default(),
+                number,
+                kind,
+                title: in_language(root, &["invention-title", "InventionTitle"], "ru"),
+                title_en: in_language(root, &["invention-title", "InventionTitle"], "en"),
+                summary: in_language(root, &["abstract", "Abstract"], "ru"),
+                summary_en: in_language(root, &["abstract", "Abstract"], "en"),
+                filed: application.and_then(|application| find_date(application, &["date", "FilingDate"]))
+                    .or_else(|| find_date(root, &["FilingDate"])),
+                published,
+                granted,
+                inventors: find_all(root, &["inventor", "Inventor"]).into_iter().map(inventor).collect()
+            })
+        })
+        .collect()
+}
+
+/// Current values of the compared fields of a RID, keyed as in the apply form.
+async fn current(pool: &MySqlPool, rid: i32) -> Result<Option<HashMap<&'static str, String>>, sqlx::Error>
+{
+    let row = sqlx::query(r#"
+        select
+            RID.name,
+            RID.name_en,
+            RID.description,
+            RID.description_en,
+            cast(RID.numPotent as char),
+            cast(Year.year as char)
+        from RID
+        left join Year on (Year.id = RID.idYear)
+        where RID.id = ?
+        "#)
+        .bind(rid)
+        .fetch_optional(pool)
+        .await?;
+
+    let Some(row) = row else { return Ok(None) };
+
+    let mut values: HashMap<&'static str, String> = HashMap::new();
+    for (i, key) in COLUMNS.into_iter().chain(["year"]).enumerate()
+    {
+        values.insert(key, row.try_get::<Option<String>, _>(i)?.unwrap_or_default());
+    }
+
+    let authors = authors::for_rids(pool, &[rid]).await?;
+    values.insert("authors", authors
+        .get(&rid)
+        .map(|authors| authors.iter().map(|author| authors::name(author, NameStyle::FULL)).collect::<Vec<String>>().join("; "))
+        .unwrap_or_default());
+
+    let dates: Vec<(String, String)> = sqlx::query_as(r#"
+        select cast(status as char), date_format(min(date), '%Y-%m-%d')
+        from StatusHistory
+        where idRID = ?
+        group by status
+        "#)
+        .bind(rid)
+        .fetch_all(pool)
+        .await?;
+
+    for (status, _) in DATES
+    {
+        let date = dates.iter().find(|(found, _)| found == status).map(|(_, date)| date.clone()).unwrap_or_default();
+        values.insert(status, date);
+    }
+
+    Ok(Some(values))
+}
+
+/// Patent RID registered under `number`.
+async fn matching(pool: &MySqlPool, number: i32) -> Result<Option<i32>, sqlx::Error>
+{
+    sqlx::query_scalar(r#"
+        select RID.id from RID
+        join Type on (Type.id = RID.idType)
+        where RID.numPotent = ? and Type.category = 'patent'
+        order by RID.id
+        limit 1
+        "#)
+        .bind(number)
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn form(staff: Staff) -> impl Responder
+{
+    if !staff.may_write()
+    {
+        return staff.deny("admin/patents");
+    }
+
+    admin::page("Загрузка данных из реестра",
+        r#"<p>Библиографические данные патентов в XML по стандартам ВОИС ST.36 или ST.96. Каждый документ сравнивается с РИД с тем же номером; записывается только отмеченное.</p>
+        <form method="post" action="/admin/patents" enctype="multipart/form-data">
+        <input type="file" name="file" accept=".xml" required>
+        <button type="submit">Сравнить</button>
+        </form>"#
+    )
+}
+
+/// Side-by-side comparison of every document in the file with its RID, each
+/// with a form to take over the chosen registry values.
+pub async fn compare(staff: Staff, mut payload: Multipart, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    if !staff.may_write()
+    {
+        return staff.deny("admin/patents");
+    }
+
+    let mut data: Vec<u8> = Vec::new();
+
+    while let Ok(Some(mut field)) = payload.try_next().await
+    {
+        while let Some(chunk) = field.next().await
+        {
+            let Ok(chunk) = chunk else { return bad_request("Upload interrupted") };
+
+            if data.len() + chunk.len() > MAX_SIZE
+            {
+                return HttpResponse::PayloadTooLarge()
+                    .content_type("text/html; charset=utf-8")
+                    .body(format!("File is larger than {} bytes", MAX_SIZE));
+            }
+            data.extend_from_slice(&chunk);
+        }
+    }
+
+    let Ok(xml) = String::from_utf8(data) else { return bad_request("XML-файл должен быть в кодировке UTF-8") };
+
+    let documents = match parse(&xml)
+    {
+        Ok(documents) => documents,
+        Err(problem) => return bad_request(&format!("Не удалось прочитать файл: {}", problem))
+    };
+
+    let mut body = String::default();
+
+    for document in &documents
+    {
+        let rid = match matching(pool.get_ref(), document.number).await
+        {
+            Ok(rid) => rid,
+            Err(err) => return server_error(err, "admin/patents")
+        };
+
+        let ours = match rid
+        {
+            Some(rid) => match current(pool.get_ref(), rid).await
+            {
+                Ok(ours) => ours.unwrap_or_default(),
+                Err(err) => return server_error(err, "admin/patents")
+            },
+            None => HashMap::new()
+        };
+
+        let rows: Vec<Vec<String>> = document
+            .values()
+            .into_iter()
+            .map(|(key, label, registry)| {
+                let registry = registry.unwrap_or_default();
+                let ours = ours.get(key).cloned().unwrap_or_default();
+                let differs = !registry.is_empty() && registry != ours;
+                let style = if differs { "background-color: #fff3b0;" } else { "" };
+
+                vec![
+                    label.to_string(),
+                    format!(r#"<div style="{}">{}</div>"#, style, escape(&registry).replace('\n', "<br>")),
+                    format!(r#"<div style="{}">{}</div>"#, style, escape(&ours).replace('\n', "<br>")),
+                    if registry.is_empty()
+                    {
+                        String::default()
+                    }
+                    else
+                    {
+                        format!(
+                            r#"<input type="hidden" name="{0}" value="{1}"><input type="checkbox" name="update_{0}" value="1"{2}>"#,
+                            key,
+                            escape(&registry),
+                            if differs { " checked" } else { "" }
+                        )
+                    }
+                ]
+            })
+            .collect();
+
+        let inventors = serde_json::to_string(&document.inventors).unwrap_or_default();
+
+        body.push_str(&format!(
+            r#"<h2>{} {} {} ({})</h2>
+            <form method="post" action="/admin/patents/apply">
+            <p>РИД: <input name="rid" size="6" value="{}" required> {}</p>
+            <input type="hidden" name="inventors" value="{}">
+            {}
+            <p><button type="submit">Записать отмеченное</button></p>
+            </form>"#,
+            escape(&document.country),
+            document.number,
+            escape(&document.kind),
+            document.standard,
+            rid.map(|rid| rid.to_string()).unwrap_or_default(),
+            if rid.is_none() { "патент с этим номером в базе не найден, укажите ID РИД" } else { "" },
+            escape(&inventors),
+            admin::table(&["Поле", "В реестре", "В базе", "Обновить"], &rows)
+        ));
+    }
+
+    if documents.is_empty()
+    {
+        body.push_str("<p>В файле нет патентных документов.</p>");
+    }
+
+    admin::page("Сравнение с данными реестра", &body)
+}
+
+/// Takes over the checked fields of the form into the RID, its authors and
+/// its status dates, in one transaction.
+async fn apply_fields(conn: &mut MySqlConnection, rid: i32, form: &HashMap<String, String>) -> Result<(), Problem>
+{
+    let checked = |key: &str| form.contains_key(&format!("update_{}", key)) && form.get(key).is_some_and(|value| !value.is_empty());
+
+    let mut tx = conn.begin().await?;
+    let mut notes: Vec<String> = Vec::new();
+
+    let mut assignments: Vec<(&str, String)> = COLUMNS
+        .into_iter()
+        .filter(|key| checked(key))
+        .map(|key| (key, form[key].clone()))
+        .collect();
+
+    if checked("year")
+    {
+        let year = import::resolve(&mut tx, "Year", "year", &form["year"], &mut notes).await?;
+        assignments.push(("idYear", year.to_string()));
+    }
+
+    if !assignments.is_empty()
+    {
+        let columns: Vec<String> = assignments.iter().map(|(column, _)| format!("{} = ?", column)).collect();
+        let statement = format!("update RID set {} where id = ?", columns.join(", "));
+
+        let mut update = sqlx::query(&statement);
+        for (_, value) in &assignments
+        {
+            update = update.bind(value);
+        }
+        update.bind(rid).execute(&mut *tx).await?;
+    }
+
+    if checked("authors")
+    {
+        let inventors: Vec<Inventor> = serde_json::from_str(form.get("inventors").map(|inventors| inventors.as_str()).unwrap_or("[]"))
+            .map_err(|err| Problem::Invalid(err.to_string()))?;
+
+        let mut ids: Vec<i64> = Vec::with_capacity(inventors.len());
+        for inventor in inventors
+        {
+            let author = ImportedAuthor { id: None, surname: inventor.surname, name: inventor.name, patronymic: inventor.patronymic, country: None };
+            ids.push(import::author(&mut tx, &author, &mut notes).await?);
+        }
+
+        sqlx::query("delete from AUTHORxRID where idRID = ?").bind(rid).execute(&mut *tx).await?;
+        for (position, id) in ids.iter().enumerate()
+        {
+            sqlx::query("insert into AUTHORxRID (idRID, idAuthor, position) values (?, ?, ?)")
+                .bind(rid)
+                .bind(id)
+                .bind(position as i32 + 1)
+                .execute(&mut *tx)
+                .await?;
+        }
+    }
+
+    for (status, _) in DATES.iter().filter(|(status, _)| checked(status))
+    {
+        let entry: Option<i32> = sqlx::query_scalar("select id from StatusHistory where idRID = ? and status = ? order by date, id limit 1")
+            .bind(rid)
+            .bind(status)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        match entry
+        {
+            Some(id) => sqlx::query("update StatusHistory set date = ? where id = ?").bind(&form[*status]).bind(id).execute(&mut *tx).await?,
+            None => sqlx::query("insert into StatusHistory (idRID, status, date, notes) values (?, ?, ?, 'по данным реестра')")
+                .bind(rid)
+                .bind(status)
+                .bind(&form[*status])
+                .execute(&mut *tx)
+                .await?
+        };
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn apply(staff: Staff, form: web::Form<HashMap<String, String>>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    if !staff.may_write()
+    {
+        return staff.deny("admin/patents/apply");
+    }
+
+    let Some(rid) = form.get("rid").and_then(|rid| rid.trim().parse::<i32>().ok()) else { return bad_request("Не указан ID РИД") };
+
+    let exists = sqlx::query_scalar::<_, i64>("select count(*) from RID where id = ?")
+        .bind(rid)
+        .fetch_one(pool.get_ref())
+        .await;
+    match exists
+    {
+        Ok(0) => return bad_request(&format!("Нет РИД с ID {}", rid)),
+        Ok(_) => {},
+        Err(err) => return server_error(err, "admin/patents/apply")
+    }
+
+    let mut conn = match pool.acquire().await
+    {
+        Ok(conn) => conn,
+        Err(err) => return server_error(err, "admin/patents/apply")
+    };
+
+    let applied = match sqlx::query("set @audit_actor = ?").bind(format!("{} (данные реестра)", staff.name)).execute(&mut *conn).await
+    {
+        Ok(_) => apply_fields(&mut conn, rid, &form).await,
+        Err(err) => Err(Problem::Database(err))
+    };
+
+    // The variable lives as long as the pooled connection.
+    if let Err(err) = sqlx::query("set @audit_actor = null").execute(&mut *conn).await
+    {
+        error!("{}: admin/patents/apply", err);
+        conn.close_on_drop();
+    }
+
+    match applied
+    {
+        Ok(()) => {
+            HttpResponse::SeeOther()
+                .append_header((header::LOCATION, format!("/admin/rids/{}/history", rid)))
+                .finish()
+        },
+        Err(Problem::Invalid(problem)) => bad_request(&problem),
+        Err(Problem::Database(err)) => server_error(err, "admin/patents/apply")
+    }
+}
@@ -0,0 +1,460 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use serde_json::{json, Value};
+use sqlx::mysql::MySqlPool;
+use log::error;
+
+use crate::admin::escape;
+use crate::authors::{self, Author, NameStyle};
+use crate::citations::HOLDER;
+use crate::handlers;
+use crate::i18n::Locale;
+use crate::registry;
+
+#[derive(Debug, sqlx::FromRow)]
+struct ProfileRid
+{
+    id: i32,
+    name: String,
+    name_en: Option<String>,
+    description: Option<String>,
+    number: i32,
+    faculty_id: Option<i32>,
+    faculty: Option<String>,
+    rid_type: Option<String>,
+    category: Option<String>,
+    year: Option<String>,
+    sub_area: Option<String>,
+    link: Option<String>,
+    registry_link: Option<String>,
+    status: Option<String>,
+    status_date: Option<String>,
+    trl: Option<i8>,
+    available: bool,
+    partnership_terms: Option<String>,
+    documents: Option<String>
+}
+
+/// A line of the RID lists on author and faculty pages.
+#[derive(Debug, sqlx::FromRow)]
+struct ListedRid
+{
+    id: i32,
+    name: String,
+    rid_type: Option<String>,
+    year: Option<String>
+}
+
+fn server_error(err: impl std::fmt::Display, page: &str) -> HttpResponse
+{
+    error!("{}: {}", err, page);
+    HttpResponse::ServiceUnavailable()
+        .content_type("text/html; charset=utf-8")
+        .body(err.to_string())
+}
+
+fn not_found() -> HttpResponse
+{
+    HttpResponse::NotFound()
+        .content_type("text/html; charset=utf-8")
+        .body("<h1>Error 404</h1>")
+}
+
+/// A public page rendered on the server, so that search engines see the
+/// content without running the portfolio scripts.
+fn page(title: &str, description: &str, canonical: &str, content: &str, structured: Option<Value>) -> HttpResponse
+{
+    // `</script>` inside a JSON string would end the script element.
+    let structured = structured
+        .map(|data| format!(r#"<script type="application/ld+json">{}</script>"#, data.to_string().replace("</", "<\\/")))
+        .unwrap_or_default();
+
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="ru">
+<head>
+    <meta charset="utf-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1">
+    <title>{0} — РИД РУДН</title>
+    <meta name="description" content="{1}">
+    <link rel="canonical" href="{2}">
+    {3}
+</head>
+<body style="font-family: sans-serif; max-inline-size: 60rem; margin: 2rem auto; padding: 0 1rem; line-height: 1.5;">
+    <p><a href="/">РИД РУДН</a> / <a href="/portfolio">РИД-портфель</a></p>
+    <h1>{0}</h1>
+    {4}
+</body>
+</html>"#,
+            escape(title),
+            escape(description),
+            canonical,
+            structured,
+            content
+        ))
+}
+
+/// First sentences of a text for `<meta name="description">`.
+fn summary(text: &str) -> String
+{
+    let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+
+    match text.char_indices().nth(200)
+    {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text
+    }
+}
+
+fn rid_list(rids: &[ListedRid]) -> String
+{
+    let items: Vec<String> = rids
+        .iter()
+        .map(|rid| format!(
+            r#"<li><a href="/rids/{}">{}</a>{}</li>"#,
+            rid.id,
+            escape(rid.name.trim()),
+            [rid.rid_type.as_deref(), rid.year.as_deref()]
+                .into_iter()
+                .flatten()
+                .map(|part| format!(", {}", escape(part)))
+                .collect::<String>()
+        ))
+        .collect();
+
+    format!("<ul>{}</ul>", items.concat())
+}
+
+/// schema.org type of a RID: software and databases have their own, patents
+/// and everything else are creative works.
+fn schema_type(rid: &ProfileRid) -> (&'static str, Option<&'static str>)
+{
+    let rid_type = rid.rid_type.as_deref().unwrap_or_default().to_lowercase();
+
+    match rid.category.as_deref()
+    {
+        Some("registration") if rid_type.contains("программ") => ("SoftwareApplication", None),
+        Some("registration") if rid_type.contains("баз") => ("Dataset", None),
+        // Wikidata item "patent", as schema.org has no type for it.
+        Some("patent") => ("CreativeWork", Some("https://www.wikidata.org/wiki/Q253623")),
+        _ => ("CreativeWork", None)
+    }
+}
+
+fn structured_data(base: &str, rid: &ProfileRid, authors: &[Author]) -> Value
+{
+    let url = format!("{}/rids/{}", base, rid.id);
+    let (kind, additional) = schema_type(rid);
+
+    let mut data = json!({
+        "@context": "https://schema.org",
+        "@type": kind,
+        "@id": url,
+        "url": url,
+        "name": rid.name.trim(),
+        "inLanguage": "ru",
+        "author": authors
+            .iter()
+            .map(|author| json!({
+                "@type": "Person",
+                "name": authors::name(author, NameStyle::FULL),
+                "familyName": author.surname,
+                "givenName": author.name,
+                "additionalName": author.patronymic,
+                "url": format!("{}/authors/{}", base, author.id)
+            }))
+            .collect::<Vec<Value>>(),
+        "copyrightHolder": {
+            "@type": "CollegeOrUniversity",
+            "name": HOLDER
+        }
+    });
+
+    if let Some(additional) = additional
+    {
+        data["additionalType"] = json!(additional);
+    }
+    if let Some(name_en) = rid.name_en.as_deref().filter(|name| !name.trim().is_empty())
+    {
+        data["alternateName"] = json!(name_en.trim());
+    }
+    if let Some(description) = rid.description.as_deref().filter(|description| !description.trim().is_empty())
+    {
+        data["description"] = json!(description.trim());
+    }
+    if rid.number > 0
+    {
+        data["identifier"] = json!({ "@type": "PropertyValue", "propertyID": rid.rid_type, "value": rid.number.to_string() });
+    }
+    if let Some(year) = &rid.year
+    {
+        data["dateCreated"] = json!(year);
+    }
+    if let Some(rid_type) = &rid.rid_type
+    {
+        data["genre"] = json!(rid_type);
+    }
+    if let Some(sub_area) = &rid.sub_area
+    {
+        data["about"] = json!(sub_area);
+    }
+    if let Some(link) = registry::link(rid.link.as_deref(), rid.registry_link.as_deref(), rid.number)
+    {
+        data["sameAs"] = json!(link);
+    }
+    if let (Some(faculty_id), Some(faculty)) = (rid.faculty_id, &rid.faculty)
+    {
+        data["sourceOrganization"] = json!({
+            "@type": "Organization",
+            "name": faculty,
+            "url": format!("{}/faculties/{}", base, faculty_id),
+            "parentOrganization": { "@type": "CollegeOrUniversity", "name": HOLDER }
+        });
+    }
+
+    data
+}
+
+pub async fn rid(req: HttpRequest, id: web::Path<i32>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let query = format!(r#"
+        select
+            RID.id as id,
+            RID.name as name,
+            RID.name_en as name_en,
+            RID.description as description,
+            RID.numPotent as number,
+            Faculty.id as faculty_id,
+            Faculty.name as faculty,
+            Type.name as rid_type,
+            cast(Type.category as char) as category,
+            cast(Year.year as char) as year,
+            SubjectArea.name as sub_area,
+            RID.link as link,
+            Type.registry_link as registry_link,
+            cast(CurrentStatus.status as char) as status,
+            date_format(CurrentStatus.date, '%d.%m.%Y') as status_date,
+            RID.trl as trl,
+            RID.available as available,
+            RID.partnership_terms as partnership_terms,
+            (select group_concat(Attachment.id, '|', Attachment.title order by Attachment.id SEPARATOR '\n') from Attachment
+                where Attachment.idRID = RID.id) as documents
+        {}
+        where RID.id = ?
+        "#, handlers::FILTERED_RIDS);
+
+    let rid: Option<ProfileRid> = match sqlx::query_as(&query).bind(*id).fetch_optional(pool.get_ref()).await
+    {
+        Ok(rid) => rid,
+        Err(err) => return server_error(err, "rids")
+    };
+
+    let Some(rid) = rid else { return not_found() };
+
+    let authors = match authors::for_rids(pool.get_ref(), &[rid.id]).await
+    {
+        Ok(mut authors) => authors.remove(&rid.id).unwrap_or_default(),
+        Err(err) => return server_error(err, "rids")
+    };
+
+    let base = handlers::base_url(&req);
+    let mut facts: Vec<(&str, String)> = Vec::new();
+
+    if let Some(rid_type) = &rid.rid_type
+    {
+        facts.push(("Вид", escape(rid_type)));
+    }
+    if rid.number > 0
+    {
+        facts.push(("Номер", rid.number.to_string()));
+    }
+    if let Some(year) = &rid.year
+    {
+        facts.push(("Год", escape(year)));
+    }
+    if let (Some(faculty_id), Some(faculty)) = (rid.faculty_id, &rid.faculty)
+    {
+        facts.push(("Подразделение", format!(r#"<a href="/faculties/{}">{}</a>"#, faculty_id, escape(faculty))));
+    }
+    if let Some(sub_area) = &rid.sub_area
+    {
+        facts.push(("Предметная область", escape(sub_area)));
+    }
+    if let Some(status) = &rid.status
+    {
+        facts.push(("Правовой статус", format!(
+            "{}{}",
+            escape(handlers::status_label(Locale::Ru, status)),
+            rid.status_date.as_deref().map(|date| format!(" с {}", date)).unwrap_or_default()
+        )));
+    }
+    if let Some(trl) = rid.trl
+    {
+        facts.push(("Уровень готовности (TRL)", trl.to_string()));
+    }
+    if rid.available
+    {
+        facts.push(("Лицензирование", format!(
+            "доступно{}",
+            rid.partnership_terms.as_deref().map(|terms| format!(": {}", escape(terms))).unwrap_or_default()
+        )));
+    }
+    if !authors.is_empty()
+    {
+        facts.push(("Авторы", authors
+            .iter()
+            .map(|author| format!(r#"<a href="/authors/{}">{}</a>"#, author.id, escape(&authors::display(author, NameStyle::FULL))))
+            .collect::<Vec<String>>()
+            .join(", ")));
+    }
+    if let Some(link) = registry::link(rid.link.as_deref(), rid.registry_link.as_deref(), rid.number)
+    {
+        facts.push(("Запись в реестре", format!(r#"<a href="{0}">{0}</a>"#, escape(&link))));
+    }
+
+    let documents: Vec<String> = rid
+        .documents
+        .as_deref()
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_once('|'))
+        .map(|(id, title)| format!(r#"<a href="/attachments/{}">{}</a>"#, id, escape(title)))
+        .collect();
+    if !documents.is_empty()
+    {
+        facts.push(("Документы", documents.join("<br>")));
+    }
+
+    facts.push(("Цитировать", format!(
+        r#"<a href="/rids/{0}/cite">ГОСТ</a> | <a href="/rids/{0}/cite?format=bibtex">BibTeX</a> | <a href="/rids/{0}/cite?format=ris">RIS</a> | <a href="/rids/{0}/cite?format=csl">CSL-JSON</a>"#,
+        rid.id
+    )));
+
+    let rows: Vec<String> = facts
+        .iter()
+        .map(|(label, value)| format!(r#"<tr><th style="text-align: left; vertical-align: top; padding: .3rem 1rem .3rem 0;">{}</th><td style="padding: .3rem 0;">{}</td></tr>"#, label, value))
+        .collect();
+
+    let description = rid.description.as_deref().unwrap_or_default().trim();
+    let content = format!(
+        "{}<table>{}</table>{}",
+        rid.name_en.as_deref().filter(|name| !name.trim().is_empty()).map(|name| format!(r#"<p lang="en"><i>{}</i></p>"#, escape(name.trim()))).unwrap_or_default(),
+        rows.concat(),
+        if description.is_empty() { String::default() } else { format!("<h2>Описание</h2><p>{}</p>", escape(description).replace('\n', "<br>")) }
+    );
+
+    page(
+        rid.name.trim(),
+        &summary(if description.is_empty() { &rid.name } else { description }),
+        &format!("{}/rids/{}", base, rid.id),
+        &content,
+        Some(structured_data(&base, &rid, &authors))
+    )
+}
+
+pub async fn author(req: HttpRequest, id: web::Path<i32>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let author: Option<Author> = match sqlx::query_as(r#"
+        select
+            ConnectionAuthorCountry.id as rid,
+            ConnectionAuthorCountry.id as id,
+            coalesce(FIO.surname, '') as surname,
+            FIO.name as name,
+            FIO.lastname as patronymic,
+            AuthorCountry.name as country,
+            null as position
+        from ConnectionAuthorCountry
+        join FIO on (FIO.id = ConnectionAuthorCountry.idFIO)
+        left join AuthorCountry on (AuthorCountry.id = ConnectionAuthorCountry.idCountry)
+        where ConnectionAuthorCountry.id = ?
+        "#)
+        .bind(*id)
+        .fetch_optional(pool.get_ref())
+        .await
+    {
+        Ok(author) => author,
+        Err(err) => return server_error(err, "authors")
+    };
+
+    let Some(author) = author else { return not_found() };
+
+    let rids: Vec<ListedRid> = match sqlx::query_as(r#"
+        select RID.id as id, RID.name as name, Type.name as rid_type, cast(Year.year as char) as year
+        from AUTHORxRID
+        join RID on (RID.id = AUTHORxRID.idRID)
+        left join Type on (Type.id = RID.idType)
+        left join Year on (Year.id = RID.idYear)
+        where AUTHORxRID.idAuthor = ?
+        order by Year.year desc, RID.id
+        "#)
+        .bind(*id)
+        .fetch_all(pool.get_ref())
+        .await
+    {
+        Ok(rids) => rids,
+        Err(err) => return server_error(err, "authors")
+    };
+
+    let name = authors::display(&author, NameStyle::FULL);
+    let base = handlers::base_url(&req);
+
+    page(
+        &name,
+        &format!("Результаты интеллектуальной деятельности РУДН, автор — {}", name),
+        &format!("{}/authors/{}", base, author.id),
+        &format!("<h2>Результаты интеллектуальной деятельности</h2>{}", rid_list(&rids)),
+        Some(json!({
+            "@context": "https://schema.org",
+            "@type": "Person",
+            "@id": format!("{}/authors/{}", base, author.id),
+            "name": authors::name(&author, NameStyle::FULL),
+            "familyName": author.surname,
+            "givenName": author.name,
+            "additionalName": author.patronymic,
+            "affiliation": { "@type": "CollegeOrUniversity", "name": HOLDER }
+        }))
+    )
+}
+
+pub async fn faculty(req: HttpRequest, id: web::Path<i32>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let name: Option<String> = match sqlx::query_scalar("select name from Faculty where id = ?")
+        .bind(*id)
+        .fetch_optional(pool.get_ref())
+        .await
+    {
+        Ok(name) => name,
+        Err(err) => return server_error(err, "faculties")
+    };
+
+    let Some(name) = name else { return not_found() };
+
+    let rids: Vec<ListedRid> = match sqlx::query_as(r#"
+        select RID.id as id, RID.name as name, Type.name as rid_type, cast(Year.year as char) as year
+        from RID
+        left join Type on (Type.id = RID.idType)
+        left join Year on (Year.id = RID.idYear)
+        where RID.idFaculty = ?
+        order by Year.year desc, RID.id
+        "#)
+        .bind(*id)
+        .fetch_all(pool.get_ref())
+        .await
+    {
+        Ok(rids) => rids,
+        Err(err) => return server_error(err, "faculties")
+    };
+
+    page(
+        &name,
+        &format!("Результаты интеллектуальной деятельности РУДН: {}", name),
+        &format!("{}/faculties/{}", handlers::base_url(&req), id),
+        &format!(
+            r#"<p>Новые РИД подразделения: <a href="/feed.atom?faculty={0}">Atom</a> | <a href="/feed.rss?faculty={0}">RSS</a></p><h2>Результаты интеллектуальной деятельности</h2>{1}"#,
+            id,
+            rid_list(&rids)
+        ),
+        None
+    )
+}
@@ -0,0 +1,23 @@
+/// Link shown for a RID: the one entered by hand, otherwise the registry
+/// record built from the `Type.registry_link` pattern of its type.
+pub fn link(manual: Option<&str>, pattern: Option<&str>, number: i32) -> Option<String>
+{
+    match manual.map(str::trim).filter(|link| !link.is_empty())
+    {
+        Some(link) => Some(link.to_string()),
+        None => generated(pattern, number)
+    }
+}
+
+/// `pattern` with `{number}` replaced, if the RID has a registration number.
+pub fn generated(pattern: Option<&str>, number: i32) -> Option<String>
+{
+    let pattern = pattern.map(str::trim).filter(|pattern| !pattern.is_empty())?;
+
+    if number <= 0
+    {
+        return None;
+    }
+
+    Some(pattern.replace("{number}", &number.to_string()))
+}
@@ -0,0 +1,154 @@
+use std::fs;
+use std::time::SystemTime;
+
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use sqlx::mysql::MySqlPool;
+use log::error;
+
+use crate::export::xml_escape;
+use crate::handlers;
+
+/// Last change of a page, `YYYY-MM-DD` or nothing if unknown.
+#[derive(Debug, sqlx::FromRow)]
+struct Entry
+{
+    id: i32,
+    lastmod: Option<String>
+}
+
+fn server_error(err: impl std::fmt::Display, page: &str) -> HttpResponse
+{
+    error!("{}: {}", err, page);
+    HttpResponse::ServiceUnavailable()
+        .content_type("text/html; charset=utf-8")
+        .body(err.to_string())
+}
+
+/// Static pages in the appearance folder, `/` for the main one, with the
+/// modification time of their HTML file.
+fn static_pages(path: &str) -> Vec<(String, Option<String>)>
+{
+    let Ok(folders) = fs::read_dir(path) else { return Vec::new() };
+
+    let mut pages: Vec<(String, Option<String>)> = folders
+        .flatten()
+        .filter_map(|folder| {
+            let name = folder.file_name().into_string().ok()?;
+            let modified = fs::metadata(format!("{}/{}/{}.html", path, name, name)).ok()?.modified().ok();
+            let lastmod = modified.map(|time: SystemTime| DateTime::<Utc>::from(time).format("%Y-%m-%d").to_string());
+
+            Some((if name == "main" { String::default() } else { name }, lastmod))
+        })
+        .collect();
+
+    pages.sort();
+    pages
+}
+
+fn url(location: &str, lastmod: Option<&str>, alternates: &[(&str, String)]) -> String
+{
+    let mut url = format!("<url><loc>{}</loc>", xml_escape(location));
+
+    for (language, alternate) in alternates
+    {
+        url.push_str(&format!(r#"<xhtml:link rel="alternate" hreflang="{}" href="{}"/>"#, language, xml_escape(alternate)));
+    }
+    if let Some(lastmod) = lastmod
+    {
+        url.push_str(&format!("<lastmod>{}</lastmod>", lastmod));
+    }
+
+    url.push_str("</url>\n");
+    url
+}
+
+/// Static pages in both languages, then every RID, author and faculty page.
+/// A RID was last modified at its latest RIDHistory entry, authors and
+/// faculties with the latest of their RIDs.
+pub async fn sitemap(req: HttpRequest, pool: web::Data<MySqlPool>, path: web::Data<String>) -> impl Responder
+{
+    let rids: Vec<Entry> = match sqlx::query_as(r#"
+        select RID.id as id, date_format(max(RIDHistory.changed_at), '%Y-%m-%d') as lastmod
+        from RID
+        left join RIDHistory on (RIDHistory.idRID = RID.id)
+        group by RID.id
+        order by RID.id
+        "#)
+        .fetch_all(pool.get_ref())
+        .await
+    {
+        Ok(rids) => rids,
+        Err(err) => return server_error(err, "sitemap.xml")
+    };
+
+    let authors: Vec<Entry> = match sqlx::query_as(r#"
+        select AUTHORxRID.idAuthor as id, date_format(max(RIDHistory.changed_at), '%Y-%m-%d') as lastmod
+        from AUTHORxRID
+        left join RIDHistory on (RIDHistory.idRID = AUTHORxRID.idRID)
+        group by AUTHORxRID.idAuthor
+        order by AUTHORxRID.idAuthor
+        "#)
+        .fetch_all(pool.get_ref())
+        .await
+    {
+        Ok(authors) => authors,
+        Err(err) => return server_error(err, "sitemap.xml")
+    };
+
+    let faculties: Vec<Entry> = match sqlx::query_as(r#"
+        select RID.idFaculty as id, date_format(max(RIDHistory.changed_at), '%Y-%m-%d') as lastmod
+        from RID
+        left join RIDHistory on (RIDHistory.idRID = RID.id)
+        where RID.idFaculty is not null
+        group by RID.idFaculty
+        order by RID.idFaculty
+        "#)
+        .fetch_all(pool.get_ref())
+        .await
+    {
+        Ok(faculties) => faculties,
+        Err(err) => return server_error(err, "sitemap.xml")
+    };
+
+    let base = handlers::base_url(&req);
+    let mut body = String::from(concat!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>"#, "\n",
+        r#"<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9" xmlns:xhtml="http://www.w3.org/1999/xhtml">"#, "\n"
+    ));
+
+    for (name, lastmod) in static_pages(path.get_ref())
+    {
+        let alternates = [
+            ("ru", format!("{}/ru/{}", base, name)),
+            ("en", format!("{}/en/{}", base, name))
+        ];
+
+        body.push_str(&url(&format!("{}/{}", base, name), lastmod.as_deref(), &alternates));
+        for (_, alternate) in &alternates
+        {
+            body.push_str(&url(alternate, lastmod.as_deref(), &alternates));
+        }
+    }
+
+    for (kind, entries) in [("rids", &rids), ("authors", &authors), ("faculties", &faculties)]
+    {
+        for entry in entries
+        {
+            body.push_str(&url(&format!("{}/{}/{}", base, kind, entry.id), entry.lastmod.as_deref(), &[]));
+        }
+    }
+
+    body.push_str("</urlset>\n");
+
+    HttpResponse::Ok()
+        .content_type("application/xml; charset=utf-8")
+        .body(body)
+}
+
+pub async fn robots(req: HttpRequest) -> impl Responder
+{
+    HttpResponse::Ok()
+        .content_type("text/plain; charset=utf-8")
+        .body(format!("User-agent: *\nDisallow: /admin/\n\nSitemap: {}/sitemap.xml\n", handlers::base_url(&req)))
+}
@@ -0,0 +1,519 @@
+use std::collections::HashMap;
+use std::env;
+use std::io::{self, BufRead, Write};
+
+use actix_session::{Session, SessionExt};
+use actix_web::cookie::Key;
+use actix_web::dev::Payload;
+use actix_web::error::{ErrorServiceUnavailable, InternalError};
+use actix_web::{http::header, web, FromRequest, HttpRequest, HttpResponse, Responder};
+use argon2::password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use futures_util::future::LocalBoxFuture;
+use sqlx::mysql::MySqlPool;
+use log::{error, warn};
+
+use crate::admin;
+
+/// Session entries: the id of the signed-in account and the page to return
+/// to after signing in.
+const STAFF_ID: &str = "staff";
+const NEXT: &str = "next";
+
+/// What an account may change; everyone signed in may read every staff page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role
+{
+    /// Everything, including the accounts themselves.
+    Superadmin,
+    /// All records and lookup tables, imports and merges.
+    Office,
+    /// RIDs of the assigned faculty and their attachments.
+    Editor,
+    /// Nothing.
+    Auditor
+}
+
+pub const ROLES: [(Role, &str, &str); 4] = [
+    (Role::Superadmin, "superadmin", "Администратор"),
+    (Role::Office, "office", "Сотрудник управления"),
+    (Role::Editor, "editor", "Редактор факультета"),
+    (Role::Auditor, "auditor", "Аудитор")
+];
+
+impl Role
+{
+    pub fn name(self) -> &'static str
+    {
+        ROLES.iter().find(|(role, _, _)| *role == self).map(|(_, name, _)| *name).unwrap_or_default()
+    }
+
+    pub fn label(self) -> &'static str
+    {
+        ROLES.iter().find(|(role, _, _)| *role == self).map(|(_, _, label)| *label).unwrap_or_default()
+    }
+
+    pub fn parse(name: &str) -> Option<Role>
+    {
+        ROLES.iter().find(|(_, role, _)| *role == name).map(|(role, _, _)| *role)
+    }
+}
+
+impl TryFrom<String> for Role
+{
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error>
+    {
+        Role::parse(&name).ok_or(format!("unknown role {}", name))
+    }
+}
+
+/// A signed-in, active account. Handlers taking it are only reached with a
+/// session; everyone else is sent to the login page.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Staff
+{
+    pub id: i32,
+    pub login: String,
+    pub name: String,
+    #[sqlx(try_from = "String")]
+    pub role: Role,
+    /// Faculty of an editor.
+    pub faculty: Option<i32>
+}
+
+impl Staff
+{
+    /// Changes outside RIDs: lookup tables, imports, merges.
+    pub fn may_write(&self) -> bool
+    {
+        matches!(self.role, Role::Superadmin | Role::Office)
+    }
+
+    /// Changes of a RID of `faculty`; editors only reach their own faculty.
+    pub fn may_edit(&self, faculty: Option<i32>) -> bool
+    {
+        self.may_write() || (self.role == Role::Editor && faculty.is_some() && faculty == self.faculty)
+    }
+
+    /// Logs the refused `action` and answers 403.
+    pub fn deny(&self, action: &str) -> HttpResponse
+    {
+        // Logged as an error: app.log keeps nothing below that by default.
+        error!("access denied: {} ({}) {}", self.login, self.role.name(), action);
+        admin::forbidden()
+    }
+}
+
+/// Faculty of a RID, `None` if there is no such RID.
+pub async fn rid_faculty(pool: &MySqlPool, rid: i32) -> Result<Option<Option<i32>>, sqlx::Error>
+{
+    sqlx::query_scalar("select idFaculty from RID where id = ?")
+        .bind(rid)
+        .fetch_optional(pool)
+        .await
+}
+
+fn to_login(req: &HttpRequest, session: &Session) -> HttpResponse
+{
+    // Only pages can be returned to, not form submissions.
+    if req.method() == actix_web::http::Method::GET
+    {
+        let _ = session.insert(NEXT, req.uri().to_string());
+    }
+
+    HttpResponse::SeeOther()
+        .append_header((header::LOCATION, "/admin/login"))
+        .finish()
+}
+
+impl FromRequest for Staff
+{
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future
+    {
+        let req = req.clone();
+
+        Box::pin(async move {
+            let session = req.get_session();
+            let id: Option<i32> = session.get(STAFF_ID).unwrap_or(None);
+            let pool = req.app_data::<web::Data<MySqlPool>>().cloned();
+
+            let staff = match (id, pool)
+            {
+                (Some(id), Some(pool)) => sqlx::query_as("select id, login, name, cast(role as char) as role, idFaculty as faculty from Staff where id = ? and active = 1")
+                    .bind(id)
+                    .fetch_optional(pool.get_ref())
+                    .await
+                    .map_err(|err| {
+                        error!("{}: {}", err, req.path());
+                        ErrorServiceUnavailable(err.to_string())
+                    })?,
+                _ => None
+            };
+
+            match staff
+            {
+                Some(staff) => Ok(staff),
+                None => Err(InternalError::from_response("login required", to_login(&req, &session)).into())
+            }
+        })
+    }
+}
+
+/// `SESSION_KEY` signs and encrypts the session cookie and must be at least
+/// 64 bytes. Without it a random key is used and every restart signs
+/// everybody out.
+pub fn session_key() -> Key
+{
+    match env::var("SESSION_KEY")
+    {
+        Ok(key) if key.len() >= 64 => Key::from(key.as_bytes()),
+        _ => {
+            warn!("SESSION_KEY is unset or shorter than 64 bytes, sessions end with the process");
+            Key::generate()
+        }
+    }
+}
+
+/// Session cookies are only sent over HTTPS when the site is published
+/// over it.
+pub fn secure_cookies() -> bool
+{
+    env::var("PUBLIC_URL").is_ok_and(|url| url.starts_with("https://"))
+}
+
+fn hash(password: &str) -> Result<String, argon2::password_hash::Error>
+{
+    let salt = SaltString::generate(&mut OsRng);
+    Ok(Argon2::default().hash_password(password.as_bytes(), &salt)?.to_string())
+}
+
+fn verify(password: &str, hash: &str) -> bool
+{
+    PasswordHash::new(hash).is_ok_and(|hash| Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+}
+
+/// `server staff <login> <name> <role> [faculty id]`: creates the account
+/// or resets its password, role and faculty and activates it. The password
+/// is read from standard input.
+pub async fn command(pool: &MySqlPool, args: &[String]) -> io::Result<()>
+{
+    let usage = || {
+        let roles: Vec<&str> = ROLES.iter().map(|(_, name, _)| *name).collect();
+        eprintln!("usage: server staff <login> <name> <{}> [faculty id]", roles.join("|"));
+        Ok(())
+    };
+
+    let (login, name, role, faculty) = match args
+    {
+        [login, name, role] => (login, name, role, None),
+        [login, name, role, faculty] => match faculty.parse::<i32>()
+        {
+            Ok(faculty) => (login, name, role, Some(faculty)),
+            Err(_) => return usage()
+        },
+        _ => return usage()
+    };
+
+    let Some(role) = Role::parse(role) else { return usage() };
+    if (role == Role::Editor) != faculty.is_some()
+    {
+        eprintln!("Факультет указывается для роли editor, и только для неё");
+        return Ok(());
+    }
+
+    print!("Пароль для {}: ", login);
+    io::stdout().flush()?;
+    let mut password = String::new();
+    io::stdin().lock().read_line(&mut password)?;
+    let password = password.trim_end_matches(['\r', '\n']);
+
+    if password.chars().count() < 8
+    {
+        eprintln!("Пароль короче 8 символов");
+        return Ok(());
+    }
+
+    let hash = hash(password).map_err(io::Error::other)?;
+
+    sqlx::query(r#"
+        insert into Staff (login, name, password_hash, role, idFaculty) values (?, ?, ?, ?, ?)
+        on duplicate key update
+            name = values(name),
+            password_hash = values(password_hash),
+            role = values(role),
+            idFaculty = values(idFaculty),
+            active = 1
+        "#)
+        .bind(login)
+        .bind(name)
+        .bind(hash)
+        .bind(role.name())
+        .bind(faculty)
+        .execute(pool)
+        .await
+        .map_err(io::Error::other)?;
+
+    println!("Учётная запись {} сохранена", login);
+    Ok(())
+}
+
+fn login_page(message: &str, login: &str) -> HttpResponse
+{
+    admin::page("Вход", &format!(
+        r#"{}<form method="post" action="/admin/login">
+            <p><label>Логин<br><input name="login" value="{}" autofocus required></label></p>
+            <p><label>Пароль<br><input type="password" name="password" required></label></p>
+            <p><button type="submit">Войти</button></p>
+        </form>"#,
+        if message.is_empty() { String::default() } else { format!(r#"<p style="color: #b00020;">{}</p>"#, message) },
+        admin::escape(login)
+    ))
+}
+
+pub async fn login_form() -> impl Responder
+{
+    login_page("", "")
+}
+
+pub async fn login(req: HttpRequest, session: Session, form: web::Form<HashMap<String, String>>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let login = form.get("login").map(|login| login.trim().to_string()).unwrap_or_default();
+    let password = form.get("password").cloned().unwrap_or_default();
+
+    let account: Option<(i32, String)> = match sqlx::query_as("select id, password_hash from Staff where login = ? and active = 1")
+        .bind(&login)
+        .fetch_optional(pool.get_ref())
+        .await
+    {
+        Ok(account) => account,
+        Err(err) => {
+            error!("{}: admin/login", err);
+            return HttpResponse::ServiceUnavailable()
+                .content_type("text/html; charset=utf-8")
+                .body(err.to_string());
+        }
+    };
+
+    let id = match account
+    {
+        Some((id, hash)) => match web::block(move || verify(&password, &hash)).await
+        {
+            Ok(true) => Some(id),
+            _ => None
+        },
+        None => None
+    };
+
+    let Some(id) = id else {
+        error!("failed login for {:?} from {}", login, req.connection_info().realip_remote_addr().unwrap_or_default());
+        return login_page("Неверный логин или пароль", &login);
+    };
+
+    let next = session
+        .get::<String>(NEXT)
+        .unwrap_or(None)
+        .filter(|next| next.starts_with("/admin"))
+        .unwrap_or_else(|| "/admin".to_string());
+
+    session.renew();
+    session.remove(NEXT);
+    if let Err(err) = session.insert(STAFF_ID, id)
+    {
+        error!("{}: admin/login", err);
+        return login_page("Не удалось начать сеанс", &login);
+    }
+
+    HttpResponse::SeeOther()
+        .append_header((header::LOCATION, next))
+        .finish()
+}
+
+pub async fn logout(session: Session) -> impl Responder
+{
+    session.purge();
+
+    HttpResponse::SeeOther()
+        .append_header((header::LOCATION, "/admin/login"))
+        .finish()
+}
+
+/// Start page of the staff area.
+pub async fn home(staff: Staff) -> impl Responder
+{
+    let mut sections = vec![
+        ("/admin/rids", "РИД"),
+        ("/admin/disclosures", "Заявки изобретателей"),
+        ("/admin/workflow", "Ход регистрации"),
+        ("/admin/faculties", "Факультеты"),
+        ("/admin/types", "Виды РИД"),
+        ("/admin/years", "Годы"),
+        ("/admin/subject-areas", "Предметные области"),
+        ("/admin/names", "ФИО авторов"),
+        ("/admin/countries", "Страны авторов"),
+        ("/admin/best", "Лучшие РИД"),
+        ("/admin/deadlines", "Сроки"),
+        ("/admin/agreements", "Договоры"),
+        ("/admin/revenue", "Доходы"),
+        ("/admin/duplicates", "Возможные дубликаты")
+    ];
+
+    if staff.may_write()
+    {
+        sections.push(("/admin/import", "Импорт из таблицы"));
+        sections.push(("/admin/patents", "Сверка с данными реестра"));
+    }
+    if staff.role == Role::Superadmin
+    {
+        sections.push(("/admin/staff", "Учётные записи"));
+    }
+
+    let links: Vec<String> = sections
+        .iter()
+        .map(|(path, title)| format!(r#"<li><a href="{}">{}</a></li>"#, path, title))
+        .collect();
+
+    admin::page("Администрирование", &format!(
+        r#"<p>{} ({}), {} <form method="post" action="/admin/logout" style="display: inline;"><button type="submit">Выйти</button></form></p><ul>{}</ul>"#,
+        admin::escape(&staff.name),
+        admin::escape(&staff.login),
+        staff.role.label().to_lowercase(),
+        links.concat()
+    ))
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct Account
+{
+    id: i32,
+    login: String,
+    name: String,
+    #[sqlx(try_from = "String")]
+    role: Role,
+    faculty: Option<i32>,
+    active: bool
+}
+
+/// The accounts with their roles, for superadmins; passwords are only set
+/// from the command line.
+pub async fn accounts(staff: Staff, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    if staff.role != Role::Superadmin
+    {
+        return staff.deny("admin/staff");
+    }
+
+    let accounts: Result<Vec<Account>, sqlx::Error> = sqlx::query_as(r#"
+        select id, login, name, cast(role as char) as role, idFaculty as faculty, active
+        from Staff
+        order by login
+        "#)
+        .fetch_all(pool.get_ref())
+        .await;
+
+    let faculties: Result<Vec<(i32, String)>, sqlx::Error> = sqlx::query_as("select id, name from Faculty order by name")
+        .fetch_all(pool.get_ref())
+        .await;
+
+    let (accounts, faculties) = match (accounts, faculties)
+    {
+        (Ok(accounts), Ok(faculties)) => (accounts, faculties),
+        (Err(err), _) | (_, Err(err)) => {
+            error!("{}: admin/staff", err);
+            return HttpResponse::ServiceUnavailable()
+                .content_type("text/html; charset=utf-8")
+                .body(err.to_string());
+        }
+    };
+
+    let rows: Vec<Vec<String>> = accounts
+        .iter()
+        .map(|account| {
+            let roles: Vec<String> = ROLES
+                .iter()
+                .map(|(role, name, label)| format!(
+                    r#"<option value="{}"{}>{}</option>"#,
+                    name, if *role == account.role { " selected" } else { "" }, label
+                ))
+                .collect();
+            let faculty_options: Vec<String> = faculties
+                .iter()
+                .map(|(id, name)| format!(
+                    r#"<option value="{}"{}>{}</option>"#,
+                    id, if Some(*id) == account.faculty { " selected" } else { "" }, admin::escape(name)
+                ))
+                .collect();
+
+            vec![
+                admin::escape(&account.login),
+                admin::escape(&account.name),
+                format!(
+                    r#"<form method="post" action="/admin/staff/{}"><select name="role">{}</select> <select name="faculty"><option value="">—</option>{}</select> <label><input type="checkbox" name="active" value="1"{}> активна</label> <button type="submit">Сохранить</button></form>"#,
+                    account.id,
+                    roles.concat(),
+                    faculty_options.concat(),
+                    if account.active { " checked" } else { "" }
+                )
+            ]
+        })
+        .collect();
+
+    admin::page("Учётные записи", &format!(
+        r#"<p><a href="/admin">Администрирование</a></p><p>Факультет указывается только редактору. Новые учётные записи и пароли: <code>server staff &lt;логин&gt; "&lt;имя&gt;" &lt;роль&gt; [id факультета]</code>.</p>{}"#,
+        admin::table(&["Логин", "Имя", "Роль, факультет"], &rows)
+    ))
+}
+
+pub async fn update_account(staff: Staff, id: web::Path<i32>, form: web::Form<HashMap<String, String>>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    if staff.role != Role::Superadmin
+    {
+        return staff.deny("admin/staff");
+    }
+
+    let Some(role) = form.get("role").and_then(|role| Role::parse(role)) else {
+        return HttpResponse::BadRequest().content_type("text/html; charset=utf-8").body("Неизвестная роль");
+    };
+    let faculty: Option<i32> = form.get("faculty").and_then(|faculty| faculty.parse().ok());
+    let active = form.get("active").is_some_and(|active| active == "1");
+
+    if (role == Role::Editor) != faculty.is_some()
+    {
+        return HttpResponse::BadRequest()
+            .content_type("text/html; charset=utf-8")
+            .body("Факультет указывается для редактора, и только для него");
+    }
+
+    // Nobody would be left to undo it.
+    if *id == staff.id && (role != Role::Superadmin || !active)
+    {
+        return HttpResponse::BadRequest()
+            .content_type("text/html; charset=utf-8")
+            .body("Свою роль администратора снять нельзя");
+    }
+
+    let updated = sqlx::query("update Staff set role = ?, idFaculty = ?, active = ? where id = ?")
+        .bind(role.name())
+        .bind(faculty)
+        .bind(active)
+        .bind(*id)
+        .execute(pool.get_ref())
+        .await;
+
+    match updated
+    {
+        Ok(_) => HttpResponse::SeeOther()
+            .append_header((header::LOCATION, "/admin/staff"))
+            .finish(),
+        Err(err) => {
+            error!("{}: admin/staff", err);
+            HttpResponse::ServiceUnavailable()
+                .content_type("text/html; charset=utf-8")
+                .body(err.to_string())
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+
+use actix_web::{http::header, web, HttpResponse, Responder};
+use chrono::NaiveDate;
+use sqlx::mysql::{MySqlConnection, MySqlPool};
+use log::error;
+
+use crate::admin::{self, escape};
+use crate::handlers;
+use crate::i18n::Locale;
+use crate::staff::{self, Staff};
+
+/// Values of `StatusHistory.status`, in the order a RID usually goes through them.
+const STATUSES: [&str; 6] = ["filed", "published", "granted", "in_force", "lapsed", "expired"];
+
+#[derive(Debug, sqlx::FromRow)]
+struct Entry
+{
+    id: i32,
+    status: String,
+    date: String,
+    notes: Option<String>
+}
+
+fn server_error(err: impl std::fmt::Display, page: &str) -> HttpResponse
+{
+    error!("{}: {}", err, page);
+    HttpResponse::ServiceUnavailable()
+        .content_type("text/html; charset=utf-8")
+        .body(err.to_string())
+}
+
+fn not_found() -> HttpResponse
+{
+    HttpResponse::NotFound()
+        .content_type("text/html; charset=utf-8")
+        .body("<h1>Error 404</h1>")
+}
+
+fn select(selected: &str) -> String
+{
+    let options: Vec<String> = STATUSES
+        .iter()
+        .map(|status| format!(
+            r#"<option value="{}"{}>{}</option>"#,
+            status, if *status == selected { " selected" } else { "" }, handlers::status_label(Locale::Ru, status)
+        ))
+        .collect();
+
+    format!(r#"<select name="status">{}</select>"#, options.concat())
+}
+
+async fn show(pool: &MySqlPool, staff: &Staff, rid: i32, message: &str) -> HttpResponse
+{
+    let page = format!("admin/rids/{}/status", rid);
+
+    let record: Result<Option<(String, Option<i32>)>, sqlx::Error> = sqlx::query_as("select name, idFaculty from RID where id = ?")
+        .bind(rid)
+        .fetch_optional(pool)
+        .await;
+
+    let (name, faculty) = match record
+    {
+        Ok(Some(record)) => record,
+        Ok(None) => return not_found(),
+        Err(err) => return server_error(err, &page)
+    };
+
+    let entries: Result<Vec<Entry>, sqlx::Error> = sqlx::query_as(r#"
+        select
+            id,
+            cast(status as char) as status,
+            date_format(date, '%Y-%m-%d') as date,
+            notes
+        from StatusHistory
+        where idRID = ?
+        order by date, id
+        "#)
+        .bind(rid)
+        .fetch_all(pool)
+        .await;
+
+    let entries = match entries
+    {
+        Ok(entries) => entries,
+        Err(err) => return server_error(err, &page)
+    };
+
+    let editable = staff.may_edit(faculty);
+
+    let rows: Vec<Vec<String>> = entries
+        .iter()
+        .map(|entry| if editable
+        {
+            vec![format!(
+                r#"<form method="post" action="/admin/rids/{0}/status/{1}">{2} <input type="date" name="date" value="{3}" required> <input name="notes" value="{4}" size="40"> <button type="submit">Сохранить</button> <button type="submit" name="delete" value="1" onclick="return confirm('Удалить запись?')">Удалить</button></form>"#,
+                rid, entry.id, select(&entry.status), entry.date, escape(entry.notes.as_deref().unwrap_or_default())
+            )]
+        }
+        else
+        {
+            vec![format!(
+                "{} — {} {}",
+                entry.date, handlers::status_label(Locale::Ru, &entry.status), escape(entry.notes.as_deref().unwrap_or_default())
+            )]
+        })
+        .collect();
+
+    let add = if editable
+    {
+        format!(
+            r#"<h2>Новая запись</h2>
+            <form method="post" action="/admin/rids/{}/status">{} <input type="date" name="date" required> <input name="notes" size="40" placeholder="Примечание"> <button type="submit">Добавить</button></form>"#,
+            rid, select("")
+        )
+    }
+    else
+    {
+        String::default()
+    };
+
+    admin::page(&format!("Правовой статус: {}", escape(&name)), &format!(
+        r#"<p><a href="/admin">Администрирование</a> / <a href="/admin/rids/{}/edit">РИД {}</a></p>{}{}{}"#,
+        rid,
+        rid,
+        if message.is_empty() { String::default() } else { format!(r#"<p style="color: #b00020;">{}</p>"#, escape(message)) },
+        if rows.is_empty() { "<p>Статус не записан.</p>".to_string() } else { admin::table(&["Статус, дата, примечание"], &rows) },
+        add
+    ))
+}
+
+pub async fn list(staff: Staff, rid: web::Path<i32>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    show(pool.get_ref(), &staff, *rid, "").await
+}
+
+/// Adds, changes or (with `delete`) removes one entry of the RID.
+async fn write(conn: &mut MySqlConnection, rid: i32, entry: Option<i32>, form: &HashMap<String, String>) -> Result<(), sqlx::Error>
+{
+    let notes = form.get("notes").map(|notes| notes.trim()).filter(|notes| !notes.is_empty());
+
+    match entry
+    {
+        Some(entry) if form.contains_key("delete") => sqlx::query("delete from StatusHistory where id = ? and idRID = ?")
+            .bind(entry)
+            .bind(rid)
+            .execute(&mut *conn)
+            .await?,
+        Some(entry) => sqlx::query("update StatusHistory set status = ?, date = ?, notes = ? where id = ? and idRID = ?")
+            .bind(&form["status"])
+            .bind(&form["date"])
+            .bind(notes)
+            .bind(entry)
+            .bind(rid)
+            .execute(&mut *conn)
+            .await?,
+        None => sqlx::query("insert into StatusHistory (idRID, status, date, notes) values (?, ?, ?, ?)")
+            .bind(rid)
+            .bind(&form["status"])
+            .bind(&form["date"])
+            .bind(notes)
+            .execute(&mut *conn)
+            .await?
+    };
+
+    Ok(())
+}
+
+async fn submit(pool: &MySqlPool, staff: &Staff, rid: i32, entry: Option<i32>, form: &HashMap<String, String>) -> HttpResponse
+{
+    let page = format!("admin/rids/{}/status", rid);
+
+    match staff::rid_faculty(pool, rid).await
+    {
+        Ok(Some(faculty)) if staff.may_edit(faculty) => {},
+        Ok(Some(_)) => return staff.deny(&page),
+        Ok(None) => return not_found(),
+        Err(err) => return server_error(err, &page)
+    }
+
+    let deleting = entry.is_some() && form.contains_key("delete");
+    if !deleting
+    {
+        if form.get("status").is_none_or(|status| !STATUSES.contains(&status.as_str()))
+        {
+            return show(pool, staff, rid, "Выберите статус из списка").await;
+        }
+        if form.get("date").is_none_or(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err())
+        {
+            return show(pool, staff, rid, "Дата — в виде ГГГГ-ММ-ДД").await;
+        }
+    }
+
+    let mut conn = match pool.acquire().await
+    {
+        Ok(conn) => conn,
+        Err(err) => return server_error(err, &page)
+    };
+
+    // Names the change in the RID history (see migrations/0009_rid_history.sql).
+    let written = match sqlx::query("set @audit_actor = ?").bind(&staff.name).execute(&mut *conn).await
+    {
+        Ok(_) => write(&mut conn, rid, entry, form).await,
+        Err(err) => Err(err)
+    };
+
+    if let Err(err) = sqlx::query("set @audit_actor = null").execute(&mut *conn).await
+    {
+        error!("{}: {}", err, page);
+        conn.close_on_drop();
+    }
+
+    match written
+    {
+        Ok(()) => HttpResponse::SeeOther()
+            .append_header((header::LOCATION, format!("/admin/rids/{}/status", rid)))
+            .finish(),
+        Err(err) => server_error(err, &page)
+    }
+}
+
+pub async fn add(staff: Staff, rid: web::Path<i32>, form: web::Form<HashMap<String, String>>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    submit(pool.get_ref(), &staff, *rid, None, &form).await
+}
+
+pub async fn update(staff: Staff, path: web::Path<(i32, i32)>, form: web::Form<HashMap<String, String>>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let (rid, entry) = path.into_inner();
+    submit(pool.get_ref(), &staff, rid, Some(entry), &form).await
+}
@@ -0,0 +1,594 @@
+use std::collections::HashMap;
+use std::env;
+
+use actix_web::{http::header, web, HttpResponse, Responder};
+use chrono::NaiveDate;
+use sqlx::mysql::{MySqlConnection, MySqlPool};
+use sqlx::Connection;
+use log::error;
+
+use crate::admin::{self, escape};
+use crate::mail;
+use crate::staff::{self, Staff};
+
+/// Stages of the registration of a RID, in their usual order.
+const STATES: [(&str, &str); 7] = [
+    ("office_review", "Проверка в управлении"),
+    ("prior_art_search", "Патентный поиск"),
+    ("drafting", "Подготовка заявки"),
+    ("filing", "Подача заявки"),
+    ("examination", "Экспертиза"),
+    ("granted", "Выдан охранный документ"),
+    ("refused", "Отказ")
+];
+
+pub fn label(state: &str) -> &str
+{
+    STATES.iter().find(|(name, _)| *name == state).map(|(_, label)| *label).unwrap_or(state)
+}
+
+/// Stages a RID may move to from `state`; `None` is a RID not yet in the
+/// workflow. Granted and refused RIDs stay where they are.
+fn next(state: Option<&str>) -> &'static [&'static str]
+{
+    match state
+    {
+        None => &["office_review"],
+        Some("office_review") => &["prior_art_search", "refused"],
+        Some("prior_art_search") => &["drafting", "office_review", "refused"],
+        Some("drafting") => &["filing", "prior_art_search", "refused"],
+        // A rejected filing goes back to drafting for an answer to the examiner.
+        Some("filing") => &["examination", "drafting"],
+        Some("examination") => &["granted", "refused", "drafting"],
+        _ => &[]
+    }
+}
+
+fn is_final(state: &str) -> bool
+{
+    next(Some(state)).is_empty()
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct Current
+{
+    state: String,
+    responsible: Option<i32>,
+    due: Option<String>
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct Step
+{
+    from_state: Option<String>,
+    to_state: String,
+    responsible: Option<String>,
+    due: Option<String>,
+    comment: Option<String>,
+    actor: String,
+    created_at: String
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct Waiting
+{
+    rid: i32,
+    name: String,
+    state: String,
+    responsible: Option<String>,
+    due: Option<String>,
+    overdue: i64,
+    updated_at: String
+}
+
+/// Legal status entry a workflow state starts, dated the day of the step.
+fn status_entry(state: &str) -> Option<&'static str>
+{
+    match state
+    {
+        "filing" => Some("filed"),
+        "granted" => Some("granted"),
+        _ => None
+    }
+}
+
+/// Moves `rid` from `from` to `to`, handing it to `responsible` until `due`,
+/// and logs the step. Filing and the grant also add the legal status entry
+/// the deadline calendar and the granted feed go by. Returns false if the
+/// RID is no longer at `from`.
+#[allow(clippy::too_many_arguments)]
+pub async fn transition(
+    conn: &mut MySqlConnection,
+    rid: i32,
+    from: Option<&str>,
+    to: &str,
+    responsible: Option<i32>,
+    due: Option<&str>,
+    comment: Option<&str>,
+    actor: &str
+) -> Result<bool, sqlx::Error>
+{
+    let moved = match from
+    {
+        None => sqlx::query("insert ignore into Workflow (idRID, state, idStaff, due_date) values (?, ?, ?, ?)")
+            .bind(rid)
+            .bind(to)
+            .bind(responsible)
+            .bind(due)
+            .execute(&mut *conn)
+            .await?,
+        Some(from) => sqlx::query(r#"
+            update Workflow
+            set state = ?, idStaff = ?, due_date = ?, updated_at = now()
+            where idRID = ? and state = ?
+            "#)
+            .bind(to)
+            .bind(responsible)
+            .bind(due)
+            .bind(rid)
+            .bind(from)
+            .execute(&mut *conn)
+            .await?
+    };
+
+    if moved.rows_affected() == 0
+    {
+        return Ok(false);
+    }
+
+    sqlx::query(r#"
+        insert into WorkflowStep (idRID, from_state, to_state, idStaff, due_date, comment, actor)
+        values (?, ?, ?, ?, ?, ?, ?)
+        "#)
+        .bind(rid)
+        .bind(from)
+        .bind(to)
+        .bind(responsible)
+        .bind(due)
+        .bind(comment)
+        .bind(actor)
+        .execute(&mut *conn)
+        .await?;
+
+    if let Some(status) = status_entry(to)
+    {
+        sqlx::query("insert into StatusHistory (idRID, status, date, notes) values (?, ?, curdate(), ?)")
+            .bind(rid)
+            .bind(status)
+            .bind(comment)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(true)
+}
+
+/// Tells the inventors of `rid` (those who disclosed it through /disclose)
+/// that it moved to `state`. Failures are only logged: the step is made.
+pub async fn notify(pool: &MySqlPool, rid: i32, state: &str, comment: Option<&str>)
+{
+    let recipients: Result<Vec<(String, String)>, sqlx::Error> = sqlx::query_as(r#"
+        select distinct Inventor.email, RID.name
+        from Disclosure
+
+        join Inventor on (Inventor.id = Disclosure.idInventor)
+        join RID on (RID.id = Disclosure.idRID)
+        where Disclosure.idRID = ?
+        "#)
+        .bind(rid)
+        .fetch_all(pool)
+        .await;
+
+    let recipients = match recipients
+    {
+        Ok(recipients) => recipients,
+        Err(err) => return error!("{}: workflow notification for RID {}", err, rid)
+    };
+
+    let link = env::var("PUBLIC_URL")
+        .ok()
+        .filter(|url| !url.is_empty())
+        .map(|url| format!("\nВаши заявки: {}/disclose\n", url.trim_end_matches('/')))
+        .unwrap_or_default();
+
+    for (email, name) in recipients
+    {
+        let sent = mail::send(&email, &format!("РИД «{}»: {}", name, label(state)), format!(
+            "Регистрация результата «{}» перешла на этап «{}».\n{}{}",
+            name,
+            label(state),
+            comment.map(|comment| format!("\n{}\n", comment)).unwrap_or_default(),
+            link
+        )).await;
+
+        if let Err(err) = sent
+        {
+            error!("{}: workflow notification for RID {} to {}", err, rid, email);
+        }
+    }
+}
+
+fn server_error(err: impl std::fmt::Display, page: &str) -> HttpResponse
+{
+    error!("{}: {}", err, page);
+    HttpResponse::ServiceUnavailable()
+        .content_type("text/html; charset=utf-8")
+        .body(err.to_string())
+}
+
+fn not_found() -> HttpResponse
+{
+    HttpResponse::NotFound()
+        .content_type("text/html; charset=utf-8")
+        .body("<h1>Error 404</h1>")
+}
+
+async fn active_staff(pool: &MySqlPool) -> Result<Vec<(i32, String)>, sqlx::Error>
+{
+    sqlx::query_as("select id, name from Staff where active order by name")
+        .fetch_all(pool)
+        .await
+}
+
+/// Items still under way, grouped by whoever they wait on, the closest due
+/// dates first.
+pub async fn dashboard(staff: Staff, query: web::Query<HashMap<String, String>>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let only: Option<i32> = match query.get("staff").map(String::as_str)
+    {
+        Some("me") => Some(staff.id),
+        Some(id) => id.parse().ok(),
+        None => None
+    };
+
+    let rows: Result<Vec<Waiting>, sqlx::Error> = sqlx::query_as(r#"
+        select
+            Workflow.idRID as rid,
+            RID.name as name,
+            cast(Workflow.state as char) as state,
+            Staff.name as responsible,
+            date_format(Workflow.due_date, '%d.%m.%Y') as due,
+            cast(coalesce(Workflow.due_date < curdate(), 0) as signed) as overdue,
+            date_format(Workflow.updated_at, '%d.%m.%Y') as updated_at
+        from Workflow
+
+        join RID on (RID.id = Workflow.idRID)
+        left join Staff on (Staff.id = Workflow.idStaff)
+        where Workflow.state not in ('granted', 'refused')
+          and (? is null or Workflow.idStaff = ?)
+        order by Staff.name is null, Staff.name, Workflow.due_date is null, Workflow.due_date
+        "#)
+        .bind(only)
+        .bind(only)
+        .fetch_all(pool.get_ref())
+        .await;
+
+    let (rows, people) = match (rows, active_staff(pool.get_ref()).await)
+    {
+        (Ok(rows), Ok(people)) => (rows, people),
+        (Err(err), _) | (_, Err(err)) => return server_error(err, "admin/workflow")
+    };
+
+    let mut groups: Vec<(String, Vec<Vec<String>>)> = Vec::new();
+    for row in rows
+    {
+        let person = row.responsible.unwrap_or("Не назначен".to_string());
+        let line = vec![
+            format!(r#"<a href="/admin/rids/{}/workflow">{}</a>"#, row.rid, escape(&row.name)),
+            label(&row.state).to_string(),
+            match row.due
+            {
+                Some(due) if row.overdue == 1 => format!(r#"<span style="color: #b00020;">{} (просрочено)</span>"#, due),
+                Some(due) => due,
+                None => String::default()
+            },
+            row.updated_at
+        ];
+
+        match groups.last_mut()
+        {
+            Some((last, lines)) if *last == person => lines.push(line),
+            _ => groups.push((person, vec![line]))
+        }
+    }
+
+    let filter: Vec<String> = people
+        .iter()
+        .map(|(id, name)| format!(
+            r#"<option value="{}"{}>{}</option>"#,
+            id, if only == Some(*id) { " selected" } else { "" }, escape(name)
+        ))
+        .collect();
+
+    let content: String = groups
+        .iter()
+        .map(|(person, lines)| format!(
+            "<h2>{} ({})</h2>{}",
+            escape(person), lines.len(), admin::table(&["РИД", "Этап", "Срок", "Изменено"], lines)
+        ))
+        .collect();
+
+    admin::page("Ход регистрации", &format!(
+        r#"<p><a href="/admin">Администрирование</a></p>
+        <form method="get" action="/admin/workflow"><p><a href="/admin/workflow?staff=me">Мои</a> | <a href="/admin/workflow">Все</a> | <select name="staff">{}</select> <button type="submit">Показать</button></p></form>{}"#,
+        filter.concat(),
+        if content.is_empty() { "<p>Ничего не ждёт.</p>".to_string() } else { content }
+    ))
+}
+
+async fn show(pool: &MySqlPool, staff: &Staff, rid: i32, message: &str) -> HttpResponse
+{
+    let page = format!("admin/rids/{}/workflow", rid);
+
+    let record: Result<Option<(String, Option<i32>)>, sqlx::Error> = sqlx::query_as("select name, idFaculty from RID where id = ?")
+        .bind(rid)
+        .fetch_optional(pool)
+        .await;
+
+    let (name, faculty) = match record
+    {
+        Ok(Some(record)) => record,
+        Ok(None) => return not_found(),
+        Err(err) => return server_error(err, &page)
+    };
+
+    let current: Result<Option<Current>, sqlx::Error> = sqlx::query_as(r#"
+        select
+            cast(state as char) as state,
+            idStaff as responsible,
+            date_format(due_date, '%Y-%m-%d') as due
+        from Workflow
+        where idRID = ?
+        "#)
+        .bind(rid)
+        .fetch_optional(pool)
+        .await;
+
+    let steps: Result<Vec<Step>, sqlx::Error> = sqlx::query_as(r#"
+        select
+            cast(WorkflowStep.from_state as char) as from_state,
+            cast(WorkflowStep.to_state as char) as to_state,
+            Staff.name as responsible,
+            date_format(WorkflowStep.due_date, '%d.%m.%Y') as due,
+            WorkflowStep.comment as comment,
+            WorkflowStep.actor as actor,
+            date_format(WorkflowStep.created_at, '%d.%m.%Y %H:%i') as created_at
+        from WorkflowStep
+
+        left join Staff on (Staff.id = WorkflowStep.idStaff)
+        where WorkflowStep.idRID = ?
+        order by WorkflowStep.id desc
+        "#)
+        .bind(rid)
+        .fetch_all(pool)
+        .await;
+
+    let (current, steps, people) = match (current, steps, active_staff(pool).await)
+    {
+        (Ok(current), Ok(steps), Ok(people)) => (current, steps, people),
+        (Err(err), _, _) | (_, Err(err), _) | (_, _, Err(err)) => return server_error(err, &page)
+    };
+
+    let state = current.as_ref().map(|current| current.state.as_str());
+
+    let steps: Vec<Vec<String>> = steps
+        .into_iter()
+        .map(|step| vec![
+            step.created_at,
+            format!("{} → {}", step.from_state.as_deref().map(label).unwrap_or("—"), label(&step.to_state)),
+            escape(&step.responsible.unwrap_or_default()),
+            step.due.unwrap_or_default(),
+            escape(&step.comment.unwrap_or_default()).replace('\n', "<br>"),
+            escape(&step.actor)
+        ])
+        .collect();
+
+    let form = if next(state).is_empty() || !staff.may_edit(faculty)
+    {
+        String::default()
+    }
+    else
+    {
+        let responsible = current.as_ref().and_then(|current| current.responsible).unwrap_or(staff.id);
+
+        let states: Vec<String> = next(state)
+            .iter()
+            .map(|state| format!(r#"<option value="{}">{}</option>"#, state, label(state)))
+            .collect();
+
+        let people: Vec<String> = people
+            .iter()
+            .map(|(id, name)| format!(
+                r#"<option value="{}"{}>{}</option>"#,
+                id, if *id == responsible { " selected" } else { "" }, escape(name)
+            ))
+            .collect();
+
+        format!(
+            r#"<h2>{}</h2>
+            <form method="post" action="/admin/rids/{}/workflow">
+                <input type="hidden" name="from" value="{}">
+                <p><label>Этап<br><select name="state">{}</select></label></p>
+                <p><label>Ответственный<br><select name="responsible"><option value="">—</option>{}</select></label></p>
+                <p><label>Срок<br><input type="date" name="due" value="{}"></label></p>
+                <p><label>Комментарий (уходит изобретателям в письме об этапе)<br><textarea name="comment" rows="3" cols="80"></textarea></label></p>
+                <p><button type="submit">Сохранить</button></p>
+            </form>"#,
+            if state.is_some() { "Следующий этап" } else { "Начать регистрацию" },
+            rid,
+            state.unwrap_or_default(),
+            states.concat(),
+            people.concat(),
+            current.as_ref().and_then(|current| current.due.as_deref()).unwrap_or_default()
+        )
+    };
+
+    admin::page(&format!("Регистрация: {}", escape(&name)), &format!(
+        r#"<p><a href="/admin">Администрирование</a> / <a href="/admin/workflow">Ход регистрации</a> / <a href="/admin/rids/{}/edit">РИД {}</a></p>{}<p>Этап: <b>{}</b></p>{}{}"#,
+        rid,
+        rid,
+        if message.is_empty() { String::default() } else { format!(r#"<p style="color: #b00020;">{}</p>"#, escape(message)) },
+        state.map(label).unwrap_or("регистрация не начата"),
+        form,
+        if steps.is_empty() { String::default() } else { format!("<h2>История</h2>{}", admin::table(&["Когда", "Этап", "Ответственный", "Срок", "Комментарий", "Кто"], &steps)) }
+    ))
+}
+
+pub async fn view(staff: Staff, rid: web::Path<i32>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    show(pool.get_ref(), &staff, *rid, "").await
+}
+
+/// `transition` in a transaction of its own.
+#[allow(clippy::too_many_arguments)]
+async fn step(
+    conn: &mut MySqlConnection,
+    rid: i32,
+    from: Option<&str>,
+    to: &str,
+    responsible: Option<i32>,
+    due: Option<&str>,
+    comment: Option<&str>,
+    actor: &str
+) -> Result<bool, sqlx::Error>
+{
+    let mut tx = conn.begin().await?;
+    let moved = transition(&mut tx, rid, from, to, responsible, due, comment, actor).await?;
+    tx.commit().await?;
+
+    Ok(moved)
+}
+
+/// Moves the RID to the chosen stage and mails its inventors.
+pub async fn advance(staff: Staff, rid: web::Path<i32>, form: web::Form<HashMap<String, String>>, pool: web::Data<MySqlPool>) -> impl Responder
+{
+    let rid = *rid;
+    let page = format!("admin/rids/{}/workflow", rid);
+
+    match staff::rid_faculty(pool.get_ref(), rid).await
+    {
+        Ok(Some(faculty)) if staff.may_edit(faculty) => {},
+        Ok(Some(_)) => return staff.deny(&page),
+        Ok(None) => return not_found(),
+        Err(err) => return server_error(err, &page)
+    }
+
+    let current: Result<Option<String>, sqlx::Error> = sqlx::query_scalar("select cast(state as char) from Workflow where idRID = ?")
+        .bind(rid)
+        .fetch_optional(pool.get_ref())
+        .await;
+
+    let current = match current
+    {
+        Ok(current) => current,
+        Err(err) => return server_error(err, &page)
+    };
+
+    let from = form.get("from").map(String::as_str).filter(|from| !from.is_empty());
+    if from != current.as_deref()
+    {
+        return show(pool.get_ref(), &staff, rid, "Этап уже изменил кто-то другой, проверьте историю").await;
+    }
+
+    let Some(to) = form.get("state").map(String::as_str).filter(|to| next(from).contains(to)) else {
+        return show(pool.get_ref(), &staff, rid, "С этого этапа туда перейти нельзя").await;
+    };
+
+    // Finished registrations wait on no one.
+    let (responsible, due) = if is_final(to)
+    {
+        (None, None)
+    }
+    else
+    {
+        let due = form.get("due").map(String::as_str).filter(|due| !due.is_empty());
+        if due.is_some_and(|due| NaiveDate::parse_from_str(due, "%Y-%m-%d").is_err())
+        {
+            return show(pool.get_ref(), &staff, rid, "Срок — дата в виде ГГГГ-ММ-ДД").await;
+        }
+        (form.get("responsible").and_then(|id| id.parse::<i32>().ok()), due)
+    };
+
+    let comment = form.get("comment").map(|comment| comment.trim()).filter(|comment| !comment.is_empty());
+
+    let mut conn = match pool.acquire().await
+    {
+        Ok(conn) => conn,
+        Err(err) => return server_error(err, &page)
+    };
+
+    // Names the status entry of the step in the RID history.
+    let moved = match sqlx::query("set @audit_actor = ?").bind(&staff.name).execute(&mut *conn).await
+    {
+        Ok(_) => step(&mut conn, rid, from, to, responsible, due, comment, &staff.name).await,
+        Err(err) => Err(err)
+    };
+
+    if let Err(err) = sqlx::query("set @audit_actor = null").execute(&mut *conn).await
+    {
+        error!("{}: {}", err, page);
+        conn.close_on_drop();
+    }
+
+    match moved
+    {
+        Ok(true) => {},
+        Ok(false) => return show(pool.get_ref(), &staff, rid, "Этап уже изменил кто-то другой, проверьте историю").await,
+        Err(err) => return server_error(err, &page)
+    }
+
+    notify(pool.get_ref(), rid, to, comment).await;
+
+    HttpResponse::SeeOther()
+        .append_header((header::LOCATION, format!("/admin/rids/{}/workflow", rid)))
+        .finish()
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    const STATES: [&str; 7] = ["office_review", "prior_art_search", "drafting", "filing", "examination", "granted", "refused"];
+
+    #[test]
+    fn next_starts_at_office_review()
+    {
+        assert_eq!(next(None), ["office_review"]);
+    }
+
+    #[test]
+    fn next_transitions()
+    {
+        assert_eq!(next(Some("office_review")), ["prior_art_search", "refused"]);
+        assert_eq!(next(Some("prior_art_search")), ["drafting", "office_review", "refused"]);
+        assert_eq!(next(Some("drafting")), ["filing", "prior_art_search", "refused"]);
+        assert_eq!(next(Some("filing")), ["examination", "drafting"]);
+        assert_eq!(next(Some("examination")), ["granted", "refused", "drafting"]);
+    }
+
+    #[test]
+    fn grant_and_refusal_are_final()
+    {
+        assert!(is_final("granted"));
+        assert!(is_final("refused"));
+        assert!(STATES.iter().filter(|state| is_final(state)).eq(["granted", "refused"].iter()));
+    }
+
+    #[test]
+    fn next_stays_within_the_states()
+    {
+        for state in STATES
+        {
+            assert!(next(Some(state)).iter().all(|to| STATES.contains(to) && *to != state), "{}", state);
+        }
+    }
+
+    #[test]
+    fn filing_and_grant_add_status_entries()
+    {
+        let entries: Vec<(&str, &str)> = STATES.iter().filter_map(|state| status_entry(state).map(|status| (*state, status))).collect();
+
+        assert_eq!(entries, [("filing", "filed"), ("granted", "granted")]);
+    }
+}